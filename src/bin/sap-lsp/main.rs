@@ -0,0 +1,23 @@
+//! `sap-lsp`, a language server for sap over stdio
+//!
+//! publishes the diagnostics of every open document and answers
+//! formatting, document symbol, folding range and selection range requests.
+
+mod server;
+
+use std::process::ExitCode;
+
+use lsp_server::Connection;
+
+fn main() -> ExitCode {
+    let (connection, io_threads) = Connection::stdio();
+    let result = server::run(&connection);
+    drop(connection);
+    match result.and_then(|()| io_threads.join().map_err(Into::into)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,716 @@
+//! the server loop and the handlers, independent of the transport
+
+use std::{collections::HashMap, error::Error, ops::Range};
+
+use lsp_server::{
+    Connection, ErrorCode, Message, Notification, Request, RequestId, Response, ResponseError,
+};
+use lsp_types::{
+    DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentFormattingParams, DocumentRangeFormattingParams,
+    DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, FoldingRange, FoldingRangeParams,
+    InitializeParams, NumberOrString, OneOf, Position, PublishDiagnosticsParams, SelectionRange,
+    SelectionRangeParams, SelectionRangeProviderCapability, ServerCapabilities, SymbolKind,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+        PublishDiagnostics,
+    },
+    request::{
+        DocumentSymbolRequest, FoldingRangeRequest, Formatting, RangeFormatting, Request as _,
+        SelectionRangeRequest,
+    },
+};
+use sap_parser::{
+    check_file,
+    diagnostics::{Diagnostic, SapDiagnostic, Severity},
+    expr::{Expr, ExprInner, Inner, Primary, infix::Infix, prefix::Prefix},
+    formatter::{
+        GlobalOptions,
+        config::{find_config, load_config},
+        range::{TextEdit, format_range},
+    },
+    function::LambdaExpr,
+    literal::CompoundLiteral,
+    parse_file,
+    program::{Program, trim_end},
+    visit::{self, Visit},
+};
+
+pub type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// the lines of a document, to turn byte offsets into LSP positions and
+/// back, LSP counts utf-16 code units
+struct LineIndex<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(source: &'a str) -> Self {
+        let starts = [0]
+            .into_iter()
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { source, starts }
+    }
+
+    fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.source.len());
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let character = self.source[self.starts[line]..offset]
+            .encode_utf16()
+            .count();
+        Position::new(line as u32, character as u32)
+    }
+
+    fn offset(&self, position: Position) -> usize {
+        let Some(&start) = self.starts.get(position.line as usize) else {
+            return self.source.len();
+        };
+        let mut units = 0;
+        for (i, c) in self.source[start..].char_indices() {
+            if units >= position.character as usize || c == '\n' {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        self.source.len()
+    }
+
+    fn range(&self, range: Range<usize>) -> lsp_types::Range {
+        lsp_types::Range::new(self.position(range.start), self.position(range.end))
+    }
+
+    /// the span of a node without what pest leaves after it
+    fn span(&self, diag: &Diagnostic) -> Range<usize> {
+        let start = diag.start_offset.min(self.source.len());
+        let end = diag.end_offset.clamp(start, self.source.len());
+        start..trim_end(self.source, start, end)
+    }
+}
+
+fn diagnostic(index: &LineIndex, diagnostic: &SapDiagnostic) -> lsp_types::Diagnostic {
+    let primary = &diagnostic.primary.diag;
+    let mut message = diagnostic.message.clone();
+    for note in &diagnostic.notes {
+        message.push_str("\nnote: ");
+        message.push_str(note);
+    }
+    lsp_types::Diagnostic {
+        range: index.range(primary.start_offset..primary.end_offset),
+        severity: Some(match diagnostic.severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+            Severity::Info => DiagnosticSeverity::INFORMATION,
+            Severity::Hint => DiagnosticSeverity::HINT,
+        }),
+        code: Some(NumberOrString::String(diagnostic.code.clone())),
+        source: Some("sap".to_string()),
+        message,
+        ..Default::default()
+    }
+}
+
+/// a top level `name = value`, through `@@export`
+fn binding(expr: &Expr) -> Option<(&Expr, &Expr)> {
+    match &expr.inner {
+        ExprInner::Prefix(Prefix::AnnotativeMacroCall(..), expr) => binding(expr),
+        ExprInner::Infix(Infix::Assign, lhs, rhs) => Some((lhs, rhs)),
+        _ => None,
+    }
+}
+
+fn is_lambda(expr: &Expr) -> bool {
+    matches!(
+        &expr.inner,
+        ExprInner::Primary(Primary {
+            inner: Inner::LambdaExpr(_),
+            ..
+        })
+    )
+}
+
+#[allow(deprecated)]
+fn document_symbols(source: &str, program: &Program) -> Vec<DocumentSymbol> {
+    let index = LineIndex::new(source);
+    program
+        .stmts
+        .iter()
+        .filter_map(|stmt| {
+            let (lhs, rhs) = binding(&stmt.expr)?;
+            let name = index.span(&lhs.diag);
+            Some(DocumentSymbol {
+                name: source[name.clone()].to_string(),
+                detail: stmt
+                    .expr
+                    .doc
+                    .as_deref()
+                    .and_then(|doc| doc.lines().next())
+                    .map(str::to_string),
+                kind: if is_lambda(rhs) {
+                    SymbolKind::FUNCTION
+                } else {
+                    SymbolKind::VARIABLE
+                },
+                tags: None,
+                deprecated: None,
+                range: index.range(index.span(&stmt.diag)),
+                selection_range: index.range(name),
+                children: None,
+            })
+        })
+        .collect()
+}
+
+/// the blocks, `_{}` lambdas and object literals spanning more than a line
+struct Folds<'a> {
+    index: LineIndex<'a>,
+    ranges: Vec<FoldingRange>,
+}
+
+impl Visit<'_> for Folds<'_> {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let ExprInner::Primary(primary) = &expr.inner
+            && matches!(
+                primary.inner,
+                Inner::Block(_)
+                    | Inner::LambdaExpr(LambdaExpr::NoParamLambdaExpr(_))
+                    | Inner::CompoundLiteral(CompoundLiteral::ObjectLiteral(_))
+            )
+        {
+            let range = self.index.range(self.index.span(&expr.diag));
+            if range.start.line < range.end.line {
+                self.ranges.push(FoldingRange {
+                    start_line: range.start.line,
+                    end_line: range.end.line,
+                    ..Default::default()
+                });
+            }
+        }
+        visit::visit_expr(self, expr);
+    }
+}
+
+fn folding_ranges(source: &str, program: &Program) -> Vec<FoldingRange> {
+    let mut folds = Folds {
+        index: LineIndex::new(source),
+        ranges: vec![],
+    };
+    folds.visit_program(program);
+    folds.ranges
+}
+
+/// the spans of the exprs around `offset`, each inside the one before
+struct Selection<'a> {
+    index: LineIndex<'a>,
+    offset: usize,
+    spans: Vec<Range<usize>>,
+}
+
+impl Visit<'_> for Selection<'_> {
+    fn visit_expr(&mut self, expr: &Expr) {
+        let span = self.index.span(&expr.diag);
+        let last = self.spans.last().expect("the file is always a span");
+        if !(span.start <= self.offset && self.offset <= span.end)
+            || span.start < last.start
+            || span.end > last.end
+        {
+            return;
+        }
+        if *last != span {
+            self.spans.push(span);
+        }
+        visit::visit_expr(self, expr);
+    }
+}
+
+/// the spans around `offset`, from the whole file to the innermost expr
+fn selection_range(source: &str, program: &Program, offset: usize) -> SelectionRange {
+    let file = 0..source.len();
+    let mut selection = Selection {
+        index: LineIndex::new(source),
+        offset,
+        spans: vec![file],
+    };
+    selection.visit_program(program);
+    let mut range: Option<SelectionRange> = None;
+    for span in selection.spans {
+        range = Some(SelectionRange {
+            range: selection.index.range(span),
+            parent: range.map(Box::new),
+        });
+    }
+    range.expect("the file is always a span")
+}
+
+/// the options of the `sapfmt.toml` above the document, the indent of the
+/// editor without one
+fn options(uri: &Url, tab_size: u32) -> GlobalOptions {
+    let path = uri.to_file_path().ok();
+    match path.as_deref().and_then(find_config) {
+        Some(config) => load_config(&config).unwrap_or_default(),
+        None => GlobalOptions {
+            indent_size: tab_size,
+            ..Default::default()
+        },
+    }
+}
+
+fn text_edits(source: &str, edits: Vec<TextEdit>) -> Vec<lsp_types::TextEdit> {
+    let index = LineIndex::new(source);
+    edits
+        .into_iter()
+        .map(|edit| lsp_types::TextEdit::new(index.range(edit.range), edit.new_text))
+        .collect()
+}
+
+#[derive(Default)]
+struct Server {
+    documents: HashMap<Url, String>,
+}
+
+impl Server {
+    fn publish_diagnostics(&self, connection: &Connection, uri: Url) -> Result<()> {
+        let diagnostics = match self.documents.get(&uri) {
+            Some(source) => {
+                let index = LineIndex::new(source);
+                check_file(source)
+                    .1
+                    .iter()
+                    .map(|d| diagnostic(&index, d))
+                    .collect()
+            }
+            // a closed document has none
+            None => vec![],
+        };
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        connection.sender.send(notification.into())?;
+        Ok(())
+    }
+
+    /// apply `notification` to the open documents, the uri of the document
+    /// it changed
+    fn notification(
+        &mut self,
+        notification: Notification,
+    ) -> std::result::Result<Option<Url>, serde_json::Error> {
+        let uri = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents
+                    .insert(uri.clone(), params.text_document.text);
+                uri
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                // the sync is full, the last change is the whole text
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents.insert(uri.clone(), change.text);
+                }
+                uri
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                params.text_document.uri
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(uri))
+    }
+
+    /// the result of `request`, `null` for a document that is not open or
+    /// does not parse
+    fn request(&self, request: Request) -> std::result::Result<serde_json::Value, ResponseError> {
+        let params = request.params;
+        let value = match request.method.as_str() {
+            Formatting::METHOD => {
+                let params: DocumentFormattingParams =
+                    serde_json::from_value(params).map_err(invalid_params)?;
+                let uri = params.text_document.uri;
+                self.documents.get(&uri).and_then(|source| {
+                    let options = options(&uri, params.options.tab_size);
+                    let edits = format_range(source, 0..source.len(), &options).ok()?;
+                    Some(serde_json::to_value(text_edits(source, edits)))
+                })
+            }
+            RangeFormatting::METHOD => {
+                let params: DocumentRangeFormattingParams =
+                    serde_json::from_value(params).map_err(invalid_params)?;
+                let uri = params.text_document.uri;
+                self.documents.get(&uri).and_then(|source| {
+                    let index = LineIndex::new(source);
+                    let range = index.offset(params.range.start)..index.offset(params.range.end);
+                    let options = options(&uri, params.options.tab_size);
+                    let edits = format_range(source, range, &options).ok()?;
+                    Some(serde_json::to_value(text_edits(source, edits)))
+                })
+            }
+            DocumentSymbolRequest::METHOD => {
+                let params: DocumentSymbolParams =
+                    serde_json::from_value(params).map_err(invalid_params)?;
+                self.parsed(&params.text_document.uri)
+                    .map(|(source, program)| {
+                        let symbols = document_symbols(source, &program);
+                        serde_json::to_value(DocumentSymbolResponse::Nested(symbols))
+                    })
+            }
+            FoldingRangeRequest::METHOD => {
+                let params: FoldingRangeParams =
+                    serde_json::from_value(params).map_err(invalid_params)?;
+                self.parsed(&params.text_document.uri)
+                    .map(|(source, program)| serde_json::to_value(folding_ranges(source, &program)))
+            }
+            SelectionRangeRequest::METHOD => {
+                let params: SelectionRangeParams =
+                    serde_json::from_value(params).map_err(invalid_params)?;
+                self.parsed(&params.text_document.uri)
+                    .map(|(source, program)| {
+                        let index = LineIndex::new(source);
+                        let ranges: Vec<_> = params
+                            .positions
+                            .iter()
+                            .map(|position| {
+                                selection_range(source, &program, index.offset(*position))
+                            })
+                            .collect();
+                        serde_json::to_value(ranges)
+                    })
+            }
+            _ => {
+                return Err(response_error(
+                    ErrorCode::MethodNotFound,
+                    format!("unsupported request {}", request.method),
+                ));
+            }
+        };
+        let value = value
+            .transpose()
+            .map_err(|error| response_error(ErrorCode::InternalError, error.to_string()))?;
+        Ok(value.unwrap_or(serde_json::Value::Null))
+    }
+
+    fn parsed(&self, uri: &Url) -> Option<(&str, Program)> {
+        let source = self.documents.get(uri)?;
+        Some((source, parse_file(source).ok()?))
+    }
+}
+
+fn response_error(code: ErrorCode, message: String) -> ResponseError {
+    ResponseError {
+        code: code as i32,
+        message,
+        data: None,
+    }
+}
+
+fn invalid_params(error: serde_json::Error) -> ResponseError {
+    response_error(ErrorCode::InvalidParams, error.to_string())
+}
+
+pub fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        document_range_formatting_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        folding_range_provider: Some(true.into()),
+        selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+        ..Default::default()
+    }
+}
+
+/// serve `connection` until the client shuts the server down
+pub fn run(connection: &Connection) -> Result<()> {
+    let params = connection.initialize(serde_json::to_value(capabilities())?)?;
+    let _params: InitializeParams = serde_json::from_value(params)?;
+    let mut server = Server::default();
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                let id: RequestId = request.id.clone();
+                let response = match server.request(request) {
+                    Ok(value) => Response::new_ok(id, value),
+                    Err(error) => Response::new_err(id, error.code, error.message),
+                };
+                connection.sender.send(response.into())?;
+            }
+            Message::Notification(notification) => {
+                let method = notification.method.clone();
+                match server.notification(notification) {
+                    Ok(Some(uri)) => server.publish_diagnostics(connection, uri)?,
+                    Ok(None) => {}
+                    // a notification has no response, a bad one is only logged
+                    Err(error) => eprintln!("error: invalid {} params: {}", method, error),
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use lsp_types::{
+        ClientCapabilities, FormattingOptions, PartialResultParams, TextDocumentIdentifier,
+        TextDocumentItem, WorkDoneProgressParams, notification::Initialized, request::Initialize,
+    };
+    use serde_json::{Value, json};
+
+    use super::*;
+
+    /// a client talking to a server on another thread
+    struct Client {
+        connection: Connection,
+        server: thread::JoinHandle<()>,
+        next_id: i32,
+    }
+
+    impl Client {
+        fn start() -> Self {
+            let (client, server) = Connection::memory();
+            let server = thread::spawn(move || run(&server).unwrap());
+            let mut client = Client {
+                connection: client,
+                server,
+                next_id: 0,
+            };
+            let params = InitializeParams {
+                capabilities: ClientCapabilities::default(),
+                ..Default::default()
+            };
+            let result = client.request(Initialize::METHOD, params);
+            assert_eq!(
+                result["capabilities"]["documentFormattingProvider"],
+                json!(true)
+            );
+            client.notify(Initialized::METHOD, json!({}));
+            client
+        }
+
+        fn notify(&self, method: &str, params: impl serde::Serialize) {
+            let notification = Notification::new(method.to_string(), params);
+            self.connection.sender.send(notification.into()).unwrap();
+        }
+
+        fn request(&mut self, method: &str, params: impl serde::Serialize) -> Value {
+            let response = self.response(method, params);
+            assert!(response.error.is_none(), "{:?}", response.error);
+            response.result.unwrap()
+        }
+
+        fn response(&mut self, method: &str, params: impl serde::Serialize) -> Response {
+            self.next_id += 1;
+            let request = Request::new(self.next_id.into(), method.to_string(), params);
+            self.connection.sender.send(request.into()).unwrap();
+            match self.connection.receiver.recv().unwrap() {
+                Message::Response(response) => {
+                    assert_eq!(response.id, self.next_id.into());
+                    response
+                }
+                message => panic!("expected a response, got {:?}", message),
+            }
+        }
+
+        fn diagnostics(&self) -> PublishDiagnosticsParams {
+            match self.connection.receiver.recv().unwrap() {
+                Message::Notification(notification) => {
+                    assert_eq!(notification.method, PublishDiagnostics::METHOD);
+                    serde_json::from_value(notification.params).unwrap()
+                }
+                message => panic!("expected diagnostics, got {:?}", message),
+            }
+        }
+
+        fn shutdown(mut self) {
+            self.request("shutdown", ());
+            self.notify("exit", ());
+            self.server.join().unwrap();
+        }
+    }
+
+    fn uri() -> Url {
+        Url::parse("untitled:test.sap").unwrap()
+    }
+
+    fn document() -> TextDocumentIdentifier {
+        TextDocumentIdentifier::new(uri())
+    }
+
+    fn open(client: &Client, text: &str) -> PublishDiagnosticsParams {
+        let params = DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(uri(), "sap".to_string(), 1, text.to_string()),
+        };
+        client.notify(DidOpenTextDocument::METHOD, params);
+        client.diagnostics()
+    }
+
+    #[test]
+    fn test_line_index() {
+        let index = LineIndex::new("a\n\u{1F600}b\n");
+        assert_eq!(index.position(6), Position::new(1, 2));
+        assert_eq!(index.position(7), Position::new(1, 3));
+        assert_eq!(index.offset(Position::new(1, 2)), 6);
+        assert_eq!(index.offset(Position::new(1, 9)), 7);
+        assert_eq!(index.offset(Position::new(5, 0)), 8);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let client = Client::start();
+        let published = open(&client, "a = 1\nb = )");
+        assert_eq!(published.uri, uri());
+        let [diagnostic] = &published.diagnostics[..] else {
+            panic!("{:?}", published.diagnostics)
+        };
+        assert_eq!(
+            diagnostic.code,
+            Some(NumberOrString::String("E0001".to_string()))
+        );
+        assert_eq!(diagnostic.range.start, Position::new(1, 4));
+
+        let params = json!({
+            "textDocument": {"uri": uri(), "version": 2},
+            "contentChanges": [{"text": "a = 1\nb = 2"}],
+        });
+        client.notify(DidChangeTextDocument::METHOD, params);
+        assert!(client.diagnostics().diagnostics.is_empty());
+
+        let params = json!({"textDocument": {"uri": uri()}});
+        client.notify(DidCloseTextDocument::METHOD, params);
+        assert!(client.diagnostics().diagnostics.is_empty());
+        client.shutdown();
+    }
+
+    #[test]
+    fn test_bad_messages() {
+        let mut client = Client::start();
+        client.notify(DidOpenTextDocument::METHOD, json!({"textDocument": 1}));
+        // the server is still up and reads the next notification
+        assert!(open(&client, "a = 1").diagnostics.is_empty());
+
+        let response = client.response(Formatting::METHOD, json!({"textDocument": 1}));
+        assert_eq!(
+            response.error.unwrap().code,
+            ErrorCode::InvalidParams as i32
+        );
+        let response = client.response("textDocument/hover", json!({}));
+        assert_eq!(
+            response.error.unwrap().code,
+            ErrorCode::MethodNotFound as i32
+        );
+        client.shutdown();
+    }
+
+    #[test]
+    fn test_formatting() {
+        let mut client = Client::start();
+        open(&client, "a=1\nb=[1,\n2]");
+        let params = DocumentFormattingParams {
+            text_document: document(),
+            options: FormattingOptions {
+                tab_size: 2,
+                insert_spaces: true,
+                ..Default::default()
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+        let edits: Vec<lsp_types::TextEdit> =
+            serde_json::from_value(client.request(Formatting::METHOD, params)).unwrap();
+        assert_eq!(
+            edits,
+            [lsp_types::TextEdit::new(
+                lsp_types::Range::new(Position::new(0, 1), Position::new(2, 2)),
+                " = 1\nb = [1, 2]\n".to_string()
+            )]
+        );
+
+        let params = json!({
+            "textDocument": {"uri": uri()},
+            "range": {"start": {"line": 0, "character": 0}, "end": {"line": 0, "character": 1}},
+            "options": {"tabSize": 2, "insertSpaces": true},
+        });
+        let edits: Vec<lsp_types::TextEdit> =
+            serde_json::from_value(client.request(RangeFormatting::METHOD, params)).unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].new_text, " = ");
+        client.shutdown();
+    }
+
+    #[test]
+    fn test_symbols_folding_and_selection() {
+        let mut client = Client::start();
+        open(
+            &client,
+            "/// add one\n@@export inc = \\x -> {\n  x + 1\n}\nn = 2\no = {\n  k: n\n}\nf n",
+        );
+        let params = DocumentSymbolParams {
+            text_document: document(),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+        let symbols: Vec<DocumentSymbol> =
+            serde_json::from_value(client.request(DocumentSymbolRequest::METHOD, params)).unwrap();
+        let symbols: Vec<_> = symbols
+            .iter()
+            .map(|symbol| (symbol.name.as_str(), symbol.kind, symbol.detail.as_deref()))
+            .collect();
+        assert_eq!(
+            symbols,
+            [
+                ("inc", SymbolKind::FUNCTION, Some("add one")),
+                ("n", SymbolKind::VARIABLE, None),
+                ("o", SymbolKind::VARIABLE, None),
+            ]
+        );
+
+        let params = json!({"textDocument": {"uri": uri()}});
+        let ranges: Vec<FoldingRange> =
+            serde_json::from_value(client.request(FoldingRangeRequest::METHOD, params)).unwrap();
+        let lines: Vec<_> = ranges
+            .iter()
+            .map(|range| (range.start_line, range.end_line))
+            .collect();
+        assert_eq!(lines, [(1, 3), (5, 7)]);
+
+        // on the `1` of `x + 1`
+        let params = json!({
+            "textDocument": {"uri": uri()},
+            "positions": [{"line": 2, "character": 6}],
+        });
+        let ranges: Vec<SelectionRange> =
+            serde_json::from_value(client.request(SelectionRangeRequest::METHOD, params)).unwrap();
+        let mut range = Some(&ranges[0]);
+        let mut spans = vec![];
+        while let Some(selection) = range {
+            spans.push((selection.range.start, selection.range.end));
+            range = selection.parent.as_deref();
+        }
+        assert_eq!(
+            spans,
+            [
+                (Position::new(2, 6), Position::new(2, 7)),
+                (Position::new(2, 2), Position::new(2, 7)),
+                (Position::new(1, 21), Position::new(3, 1)),
+                (Position::new(1, 15), Position::new(3, 1)),
+                (Position::new(1, 9), Position::new(3, 1)),
+                (Position::new(1, 0), Position::new(3, 1)),
+                (Position::new(0, 0), Position::new(8, 3)),
+            ]
+        );
+        client.shutdown();
+    }
+}
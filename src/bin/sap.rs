@@ -0,0 +1,246 @@
+//! `sap`, the parser on the command line, for debugging the grammar
+//!
+//! every subcommand reads a file, or an expression given with `-e`:
+//!
+//! ```text
+//! sap pairs -e "f a b" --rule expr
+//! sap ast main.sap
+//! sap json main.sap
+//! sap diagnostics main.sap
+//! ```
+
+use std::{
+    fmt::Write,
+    fs,
+    io::{self, IsTerminal},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use clap::{Args, Parser, Subcommand};
+use pest::{Parser as _, iterators::Pair};
+use sap_parser::{
+    ParseOptions, Rule, SapParser, check_file_with,
+    diagnostics::{SapDiagnostic, Severity},
+    error::ParseError,
+    parse_expr_with, parse_file_with,
+    report::ReportConfig,
+};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "sap",
+    version,
+    about = "dump what the parser makes of sap source"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// read the integer literals out of the 64 bit range as big integers
+    /// instead of rejecting them
+    #[arg(long, global = true)]
+    promote_ints: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// the pest pair tree, before lowering
+    Pairs {
+        #[command(flatten)]
+        input: Input,
+        /// the rule to parse with, `file` for a file and `single_expr` for
+        /// an expression by default
+        #[arg(long)]
+        rule: Option<String>,
+    },
+    /// the lowered AST, after the church encoding
+    Ast {
+        #[command(flatten)]
+        input: Input,
+    },
+    /// the serde JSON of the AST
+    Json {
+        #[command(flatten)]
+        input: Input,
+    },
+    /// every diagnostic, exit with 1 if there is an error
+    Diagnostics {
+        #[command(flatten)]
+        input: Input,
+    },
+}
+
+#[derive(Debug, Args)]
+#[group(required = true, multiple = false)]
+struct Input {
+    /// the file to read
+    file: Option<PathBuf>,
+    /// an expression to read instead of a file
+    #[arg(short, long)]
+    expr: Option<String>,
+}
+
+impl Input {
+    /// the name of the source in reports, and the source
+    fn read(&self) -> Result<(String, String), String> {
+        match (&self.file, &self.expr) {
+            (_, Some(expr)) => Ok(("<expr>".to_string(), expr.clone())),
+            (Some(file), None) => fs::read_to_string(file)
+                .map(|source| (file.display().to_string(), source))
+                .map_err(|error| format!("{}: {}", file.display(), error)),
+            (None, None) => unreachable!("clap requires one of them"),
+        }
+    }
+}
+
+/// the rule named `name` in `parser.pest`
+fn rule(name: &str) -> Option<Rule> {
+    Rule::all_rules()
+        .iter()
+        .copied()
+        .find(|rule| format!("{:?}", rule) == name)
+}
+
+/// one pair per line, indented by depth, with the text of the leaves
+fn dump_pairs(source: &str, rule: Rule) -> Result<String, ParseError> {
+    fn dump(pair: Pair<'_, Rule>, depth: usize, out: &mut String) {
+        let span = pair.as_span();
+        let _ = write!(
+            out,
+            "{}{:?} {}..{}",
+            "  ".repeat(depth),
+            pair.as_rule(),
+            span.start(),
+            span.end()
+        );
+        let mut inner = pair.clone().into_inner().peekable();
+        if inner.peek().is_none() {
+            let _ = write!(out, " {:?}", span.as_str());
+        }
+        out.push('\n');
+        for pair in inner {
+            dump(pair, depth + 1, out);
+        }
+    }
+    let pairs = SapParser::parse(rule, source)
+        .map_err(|error| ParseError::from_pest_error(error, source))?;
+    let mut out = String::new();
+    for pair in pairs {
+        dump(pair, 0, &mut out);
+    }
+    Ok(out)
+}
+
+fn run(command: Command, options: ParseOptions, report: ReportConfig) -> Result<ExitCode, String> {
+    let render = |error: &ParseError, name: &str, source: &str| {
+        SapDiagnostic::from(error).render(name, source, report)
+    };
+    match command {
+        Command::Pairs { input, rule: name } => {
+            let (name_of_source, source) = input.read()?;
+            let rule = match (&name, &input.expr) {
+                (Some(name), _) => rule(name).ok_or_else(|| format!("no rule `{}`", name))?,
+                (None, Some(_)) => Rule::single_expr,
+                (None, None) => Rule::file,
+            };
+            let pairs = dump_pairs(&source, rule)
+                .map_err(|error| render(&error, &name_of_source, &source))?;
+            print!("{}", pairs);
+        }
+        Command::Ast { input } => {
+            let (name, source) = input.read()?;
+            match &input.expr {
+                Some(_) => {
+                    let expr = parse_expr_with(&source, &options)
+                        .map_err(|e| render(&e, &name, &source))?;
+                    println!("{:#?}", expr);
+                }
+                None => {
+                    let program = parse_file_with(&source, &options)
+                        .map_err(|e| render(&e, &name, &source))?;
+                    println!("{:#?}", program);
+                }
+            }
+        }
+        Command::Json { input } => {
+            let (name, source) = input.read()?;
+            let json = match &input.expr {
+                Some(_) => parse_expr_with(&source, &options)
+                    .map(|expr| serde_json::to_string_pretty(&expr))
+                    .map_err(|e| render(&e, &name, &source))?,
+                None => parse_file_with(&source, &options)
+                    .map(|program| serde_json::to_string_pretty(&program))
+                    .map_err(|e| render(&e, &name, &source))?,
+            };
+            println!("{}", json.map_err(|error| error.to_string())?);
+        }
+        Command::Diagnostics { input } => {
+            let (name, source) = input.read()?;
+            let diagnostics = match &input.expr {
+                Some(_) => parse_expr_with(&source, &options)
+                    .err()
+                    .iter()
+                    .map(SapDiagnostic::from)
+                    .collect(),
+                None => check_file_with(&source, &options).1,
+            };
+            for diagnostic in &diagnostics {
+                eprint!("{}", diagnostic.render(&name, &source, report));
+            }
+            if diagnostics
+                .iter()
+                .any(|diagnostic| diagnostic.severity == Severity::Error)
+            {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let report = ReportConfig {
+        color: io::stderr().is_terminal(),
+    };
+    let options = ParseOptions {
+        promote_ints: cli.promote_ints,
+    };
+    match run(cli.command, options, report) {
+        Ok(code) => code,
+        Err(error) => {
+            eprint!("{}", error);
+            if !error.ends_with('\n') {
+                eprintln!();
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule() {
+        assert_eq!(rule("object_elem_kv"), Some(Rule::object_elem_kv));
+        assert_eq!(rule("nothing"), None);
+    }
+
+    #[test]
+    fn test_dump_pairs() {
+        assert_eq!(
+            dump_pairs("a", Rule::id).unwrap(),
+            "id 0..1\n  normal_id 0..1 \"a\"\n"
+        );
+        assert!(dump_pairs("1", Rule::id).is_err());
+    }
+
+    #[test]
+    fn test_check_file() {
+        let (_, diagnostics) = sap_parser::check_file("a = )\nb = 1 // late\n@@import c");
+        let codes: Vec<_> = diagnostics.iter().map(|d| d.code.as_str()).collect();
+        assert_eq!(codes, ["E0001", "W0001", "E0101"]);
+    }
+}
@@ -0,0 +1,243 @@
+//! `sapfmt`, the formatter on the command line
+//!
+//! formats the given files, and the `.sap` files below the given
+//! directories, in place. Without a path it formats stdin to stdout. Every
+//! file is formatted with the nearest `sapfmt.toml` above it.
+//!
+//! exits with 1 if `--check` finds a file to reformat, with 2 on an error.
+
+use std::{
+    fs,
+    io::{self, IsTerminal, Read},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::Parser;
+use sap_parser::{
+    diagnostics::SapDiagnostic,
+    formatter::{
+        GlobalOptions,
+        config::{load_config, options_for},
+        format,
+        verify::{VerifyError, verify},
+    },
+    report::ReportConfig,
+};
+use similar::TextDiff;
+
+#[derive(Debug, Parser)]
+#[command(name = "sapfmt", version, about = "format sap source files")]
+struct Args {
+    /// files and directories to format, stdin to stdout if none or `-`
+    paths: Vec<PathBuf>,
+    /// write nothing, exit with 1 if a file is not formatted
+    #[arg(long)]
+    check: bool,
+    /// write nothing, print a unified diff of every change
+    #[arg(long)]
+    diff: bool,
+    /// check that the output parses to the same program and formats to
+    /// itself, a failure is a bug of the formatter
+    #[arg(long)]
+    verify: bool,
+    /// the config to use instead of the nearest `sapfmt.toml`
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// the path of the text on stdin, to find its `sapfmt.toml` and name it
+    /// in errors
+    #[arg(long)]
+    stdin_filepath: Option<PathBuf>,
+}
+
+/// what happened to one file
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    Unchanged,
+    Changed,
+    Failed,
+}
+
+/// the files to format, directories are searched for `.sap` files
+fn collect_files(paths: &[PathBuf], out: &mut Vec<PathBuf>) -> io::Result<()> {
+    for path in paths {
+        if path.is_dir() {
+            let mut entries = fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+            entries.sort();
+            let entries: Vec<_> = entries
+                .into_iter()
+                .filter(|entry| entry.is_dir() || entry.extension().is_some_and(|ext| ext == "sap"))
+                .collect();
+            collect_files(&entries, out)?;
+        } else {
+            out.push(path.clone());
+        }
+    }
+    Ok(())
+}
+
+/// `old` to `new` as a unified diff, `name` on both headers
+fn unified_diff(name: &str, old: &str, new: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .header(name, name)
+        .to_string()
+}
+
+struct Formatter {
+    args: Args,
+    report: ReportConfig,
+}
+
+impl Formatter {
+    fn options(&self, path: Option<&Path>) -> Result<GlobalOptions, String> {
+        let options = match (&self.args.config, path) {
+            (Some(config), _) => load_config(config),
+            (None, Some(path)) => options_for(path),
+            (None, None) => Ok(GlobalOptions::default()),
+        };
+        options.map_err(|error| error.to_string())
+    }
+
+    /// format `source`, the text of `name`, print a diff or the reason it
+    /// could not be formatted. `Ok(None)` when it is formatted already
+    fn format(&self, name: &str, path: Option<&Path>, source: &str) -> Result<Option<String>, ()> {
+        let options = self.options(path).map_err(|error| {
+            eprintln!("error: {}", error);
+        })?;
+        let formatted = if self.args.verify {
+            verify(source, &options)
+        } else {
+            format(source, &options).map_err(VerifyError::Parse)
+        };
+        let formatted = formatted.map_err(|error| match error {
+            VerifyError::Parse(error) => {
+                let diagnostic = SapDiagnostic::from(&error);
+                eprint!("{}", diagnostic.render(name, source, self.report));
+            }
+            VerifyError::Bug(bug) => eprintln!("error: {}: {}", name, bug),
+        })?;
+        if formatted == source {
+            return Ok(None);
+        }
+        if self.args.diff {
+            print!("{}", unified_diff(name, source, &formatted));
+        }
+        Ok(Some(formatted))
+    }
+
+    fn file(&self, path: &Path) -> Outcome {
+        let name = path.display().to_string();
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("error: {}: {}", name, error);
+                return Outcome::Failed;
+            }
+        };
+        match self.format(&name, Some(path), &source) {
+            Err(()) => Outcome::Failed,
+            Ok(None) => Outcome::Unchanged,
+            Ok(Some(_)) if self.args.check || self.args.diff => {
+                if !self.args.diff {
+                    eprintln!("would reformat {}", name);
+                }
+                Outcome::Changed
+            }
+            Ok(Some(formatted)) => match fs::write(path, formatted) {
+                Ok(()) => Outcome::Changed,
+                Err(error) => {
+                    eprintln!("error: {}: {}", name, error);
+                    Outcome::Failed
+                }
+            },
+        }
+    }
+
+    /// stdin to stdout, unless checking or diffing
+    fn stdin(&self) -> Outcome {
+        let mut source = String::new();
+        if let Err(error) = io::stdin().read_to_string(&mut source) {
+            eprintln!("error: <stdin>: {}", error);
+            return Outcome::Failed;
+        }
+        let path = self.args.stdin_filepath.as_deref();
+        let name = path.map_or("<stdin>".to_string(), |path| path.display().to_string());
+        let formatted = match self.format(&name, path, &source) {
+            Err(()) => return Outcome::Failed,
+            Ok(formatted) => formatted,
+        };
+        let changed = formatted.is_some();
+        if !self.args.check && !self.args.diff {
+            print!("{}", formatted.as_deref().unwrap_or(&source));
+        }
+        if changed {
+            Outcome::Changed
+        } else {
+            Outcome::Unchanged
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let formatter = Formatter {
+        report: ReportConfig {
+            color: io::stderr().is_terminal(),
+        },
+        args,
+    };
+    let outcomes =
+        if formatter.args.paths.is_empty() || formatter.args.paths == [PathBuf::from("-")] {
+            vec![formatter.stdin()]
+        } else {
+            let mut files = vec![];
+            if let Err(error) = collect_files(&formatter.args.paths, &mut files) {
+                eprintln!("error: {}", error);
+                return ExitCode::from(2);
+            }
+            files.iter().map(|file| formatter.file(file)).collect()
+        };
+    if outcomes.contains(&Outcome::Failed) {
+        ExitCode::from(2)
+    } else if formatter.args.check && outcomes.contains(&Outcome::Changed) {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+#[cfg(test)]
+#[path = "../test_util.rs"]
+mod test_util;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_unified_diff() {
+        assert_eq!(
+            unified_diff("a.sap", "a=1\nb\n", "a = 1\nb\n"),
+            "--- a.sap\n+++ a.sap\n@@ -1,2 +1,2 @@\n-a=1\n+a = 1\n b\n"
+        );
+    }
+
+    #[test]
+    fn test_collect_files() {
+        let dir = TempDir::new("sapfmt");
+        for file in ["a.sap", "b/c.sap", "b/d.txt", "e.sap"] {
+            dir.write(file, "");
+        }
+        let mut files = vec![];
+        collect_files(&[dir.to_path_buf(), dir.join("b/d.txt")], &mut files).unwrap();
+        let expected: Vec<_> = ["a.sap", "b/c.sap", "e.sap", "b/d.txt"]
+            .iter()
+            .map(|file| dir.join(file))
+            .collect();
+        assert_eq!(files, expected);
+    }
+}
@@ -1,9 +1,15 @@
 use core::fmt;
 
 use pest::Span;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Serialize)]
+use crate::{
+    expr::Expr,
+    program::{Program, Stmt},
+    visit::VisitMut,
+};
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Diagnostic {
     pub start_line: usize,
     pub start_col: usize,
@@ -38,6 +44,42 @@ impl Diagnostic {
     }
 }
 
+/// resets every span of a tree to `Diagnostic::test()`
+pub struct EraseSpans;
+
+impl VisitMut for EraseSpans {
+    fn visit_diagnostic_mut(&mut self, node: &mut Diagnostic) {
+        *node = Diagnostic::test();
+    }
+}
+
+/// equality up to spans, to compare ASTs parsed from differently laid out
+/// source
+pub trait SameShape {
+    fn same_shape(&self, other: &Self) -> bool;
+}
+
+macro_rules! same_shape {
+    ($($ty:ty => $visit:ident),* $(,)?) => {
+        $(
+            impl SameShape for $ty {
+                fn same_shape(&self, other: &Self) -> bool {
+                    let (mut lhs, mut rhs) = (self.clone(), other.clone());
+                    EraseSpans.$visit(&mut lhs);
+                    EraseSpans.$visit(&mut rhs);
+                    lhs == rhs
+                }
+            }
+        )*
+    };
+}
+
+same_shape! {
+    Program => visit_program_mut,
+    Stmt => visit_stmt_mut,
+    Expr => visit_expr_mut,
+}
+
 impl PartialEq for Diagnostic {
     fn eq(&self, other: &Self) -> bool {
         if self.end_offset == 0 || other.end_offset == 0 {
@@ -76,6 +118,29 @@ impl Diagnostic {
         }
     }
 
+    /// span of `input[start..end]`, clamped to the input
+    pub fn from_offsets(input: &str, start: usize, end: usize) -> Self {
+        let end = end.min(input.len());
+        let start = start.min(end);
+        match Span::new(input, start, end) {
+            Some(span) => Self::from_span(span),
+            None => Self::test(),
+        }
+    }
+
+    /// the start of a file, for a diagnostic about a file that has no source
+    /// to point into
+    pub fn file_start() -> Self {
+        Diagnostic {
+            start_line: 1,
+            start_col: 1,
+            start_offset: 0,
+            end_line: 1,
+            end_col: 1,
+            end_offset: 0,
+        }
+    }
+
     pub fn test() -> Self {
         Diagnostic {
             start_line: 0,
@@ -87,3 +152,176 @@ impl Diagnostic {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Label {
+    pub diag: Diagnostic,
+    pub message: String,
+}
+
+impl Label {
+    pub fn new(diag: Diagnostic, message: impl Into<String>) -> Self {
+        Self {
+            diag,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Applicability {
+    /// the replacement is definitely what the user meant, tools may apply it
+    MachineApplicable,
+    /// the replacement may change the meaning, a human should check it
+    MaybeIncorrect,
+}
+
+/// replace the text at `diag` with `replacement`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Suggestion {
+    pub message: String,
+    pub diag: Diagnostic,
+    pub replacement: String,
+    pub applicability: Applicability,
+}
+
+/// everything the parser, the preprocessors and lints report goes through
+/// this record, `Diagnostic` is only the span
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SapDiagnostic {
+    pub severity: Severity,
+    /// stable code, `E....` for errors and `W....` for warnings
+    pub code: String,
+    pub message: String,
+    pub primary: Label,
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl SapDiagnostic {
+    pub fn new(
+        severity: Severity,
+        code: impl Into<String>,
+        message: impl Into<String>,
+        primary: Label,
+    ) -> Self {
+        Self {
+            severity,
+            code: code.into(),
+            message: message.into(),
+            primary,
+            secondary: vec![],
+            notes: vec![],
+            suggestions: vec![],
+        }
+    }
+
+    pub fn error(code: impl Into<String>, message: impl Into<String>, primary: Label) -> Self {
+        Self::new(Severity::Error, code, message, primary)
+    }
+
+    pub fn warning(code: impl Into<String>, message: impl Into<String>, primary: Label) -> Self {
+        Self::new(Severity::Warning, code, message, primary)
+    }
+
+    pub fn with_secondary(mut self, label: Label) -> Self {
+        self.secondary.push(label);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_suggestion(mut self, suggestion: Suggestion) -> Self {
+        self.suggestions.push(suggestion);
+        self
+    }
+}
+
+/// apply every machine applicable suggestion to `source`
+///
+/// a suggestion overlapping one that starts earlier is skipped
+pub fn apply_suggestions<'a>(
+    source: &str,
+    diagnostics: impl IntoIterator<Item = &'a SapDiagnostic>,
+) -> String {
+    let mut suggestions: Vec<&Suggestion> = diagnostics
+        .into_iter()
+        .flat_map(|diagnostic| &diagnostic.suggestions)
+        .filter(|suggestion| suggestion.applicability == Applicability::MachineApplicable)
+        .collect();
+    suggestions
+        .sort_by_key(|suggestion| (suggestion.diag.start_offset, suggestion.diag.end_offset));
+
+    let mut out = String::with_capacity(source.len());
+    let mut offset = 0;
+    for suggestion in suggestions {
+        if suggestion.diag.start_offset < offset || suggestion.diag.end_offset > source.len() {
+            continue;
+        }
+        out.push_str(&source[offset..suggestion.diag.start_offset]);
+        out.push_str(&suggestion.replacement);
+        offset = suggestion.diag.end_offset;
+    }
+    out.push_str(&source[offset..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replace(source: &str, start: usize, end: usize, replacement: &str) -> SapDiagnostic {
+        SapDiagnostic::warning(
+            "W0000",
+            "test",
+            Label::new(Diagnostic::from_offsets(source, start, end), ""),
+        )
+        .with_suggestion(Suggestion {
+            message: "replace".to_string(),
+            diag: Diagnostic::from_offsets(source, start, end),
+            replacement: replacement.to_string(),
+            applicability: Applicability::MachineApplicable,
+        })
+    }
+
+    #[test]
+    fn test_same_shape() {
+        let a = crate::parse_file("a = [1, 2]").unwrap();
+        let b = crate::parse_file("a   =\n  [ 1,\n 2 ]").unwrap();
+        assert_ne!(a.stmts[0], b.stmts[0]);
+        assert!(a.same_shape(&b));
+        assert!(!a.same_shape(&crate::parse_file("a = [1, 3]").unwrap()));
+    }
+
+    #[test]
+    fn test_apply_suggestions() {
+        let source = "a = 1; b = 2";
+        let diagnostics = [replace(source, 11, 12, "3"), replace(source, 0, 1, "x")];
+        assert_eq!(apply_suggestions(source, &diagnostics), "x = 1; b = 3");
+    }
+
+    #[test]
+    fn test_apply_suggestions_skips_overlap_and_maybe_incorrect() {
+        let source = "a = 1";
+        let mut maybe = replace(source, 4, 5, "2");
+        maybe.suggestions[0].applicability = Applicability::MaybeIncorrect;
+        let diagnostics = [
+            replace(source, 0, 3, "b ="),
+            replace(source, 2, 5, "= 3"),
+            maybe,
+        ];
+        assert_eq!(apply_suggestions(source, &diagnostics), "b = 1");
+    }
+}
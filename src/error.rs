@@ -0,0 +1,520 @@
+use core::fmt;
+use std::cell::Cell;
+
+use from_pest::FromPest;
+use pest::{
+    error::{ErrorVariant, InputLocation},
+    iterators::{Pair, Pairs},
+};
+use serde::Serialize;
+
+use crate::{
+    ParseOptions, Rule,
+    diagnostics::{Diagnostic, Label, SapDiagnostic},
+    literal::{number::try_parse_int, string::try_handle_escape},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ParseErrorKind {
+    /// the input does not match the grammar
+    Syntax,
+    /// an integer literal does not fit into `i64`
+    IntOverflow,
+    /// an escape sequence does not denote a unicode scalar value
+    InvalidEscape,
+    /// the parse tree could not be lowered into the AST
+    Lowering,
+    /// a float literal has a non decimal part or an exponent out of `i64`
+    InvalidFloat,
+}
+
+impl ParseErrorKind {
+    /// stable code of the kind, see `SapDiagnostic::code`, codes of removed
+    /// kinds are not reused
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseErrorKind::Syntax => "E0001",
+            ParseErrorKind::IntOverflow => "E0002",
+            ParseErrorKind::InvalidEscape => "E0003",
+            ParseErrorKind::Lowering => "E0005",
+            ParseErrorKind::InvalidFloat => "E0006",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub diag: Diagnostic,
+    /// human readable names of what was expected at `diag`
+    pub expected: Vec<String>,
+    /// human readable name of what was found at `diag`
+    pub found: Option<String>,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, diag: Diagnostic) -> Self {
+        Self {
+            kind,
+            diag,
+            expected: vec![],
+            found: None,
+        }
+    }
+
+    pub fn with_expected(mut self, expected: Vec<String>) -> Self {
+        self.expected = expected;
+        self
+    }
+
+    pub fn with_found(mut self, found: impl Into<String>) -> Self {
+        self.found = Some(found.into());
+        self
+    }
+
+    pub fn from_pest_error(error: pest::error::Error<Rule>, input: &str) -> Self {
+        let start = match error.location {
+            InputLocation::Pos(pos) => pos,
+            InputLocation::Span((start, _)) => start,
+        };
+        let (found, end) = found_at(input, start);
+        let diag = Diagnostic::from_offsets(input, start, end);
+        match error.variant {
+            ErrorVariant::ParsingError {
+                positives,
+                negatives,
+            } => {
+                let expected = rule_names(&positives);
+                let found = if negatives.is_empty() {
+                    found
+                } else {
+                    rule_names(&negatives).join(", ")
+                };
+                Self::new(ParseErrorKind::Syntax, diag)
+                    .with_expected(expected)
+                    .with_found(found)
+            }
+            ErrorVariant::CustomError { message } => {
+                Self::new(ParseErrorKind::Syntax, diag).with_found(message)
+            }
+        }
+    }
+
+    /// `pair` did not lower, the error points at the innermost pair inside
+    /// it noted by `lowering_failed`, or at `pair` itself
+    pub fn lowering(pair: &Pair<'_, Rule>) -> Self {
+        let span = pair.as_span();
+        let (span, rule) = match LOWERING_FAILURE.take() {
+            Some((start, end, rule)) if span.start() <= start && end <= span.end() => (
+                pest::Span::new(span.get_input(), start, end).unwrap_or(span),
+                rule,
+            ),
+            _ => (span, pair.as_rule()),
+        };
+        Self::new(ParseErrorKind::Lowering, Diagnostic::from_span(span)).with_found(rule_name(rule))
+    }
+
+    pub fn message(&self) -> String {
+        let found = self.found.as_deref().unwrap_or("nothing");
+        match self.kind {
+            ParseErrorKind::Syntax if self.expected.is_empty() => {
+                format!("unexpected {}", found)
+            }
+            ParseErrorKind::Syntax => {
+                format!("expected {}, found {}", join_or(&self.expected), found)
+            }
+            ParseErrorKind::IntOverflow => {
+                format!("integer literal `{}` does not fit into 64 bits", found)
+            }
+            ParseErrorKind::InvalidEscape => {
+                format!("invalid escape sequence `{}`", found)
+            }
+            ParseErrorKind::Lowering => format!("can not build the AST for {}", found),
+            ParseErrorKind::InvalidFloat => {
+                format!("`{}` is not allowed in a float literal", found)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.diag.start_line,
+            self.diag.start_col,
+            self.message()
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<&ParseError> for SapDiagnostic {
+    fn from(error: &ParseError) -> Self {
+        let label = match error.kind {
+            ParseErrorKind::Syntax => match error.expected.as_slice() {
+                [expected] => format!("expected {}", expected),
+                _ => "unexpected input".to_string(),
+            },
+            ParseErrorKind::IntOverflow => "out of range".to_string(),
+            ParseErrorKind::InvalidEscape => "not a unicode scalar value".to_string(),
+            ParseErrorKind::Lowering => "while lowering this".to_string(),
+            ParseErrorKind::InvalidFloat => "invalid float part".to_string(),
+        };
+        let diagnostic = SapDiagnostic::error(
+            error.kind.code(),
+            error.message(),
+            Label::new(error.diag, label),
+        );
+        match error.kind {
+            ParseErrorKind::IntOverflow => diagnostic
+                .with_note(format!(
+                    "integer literals range from {} to {}",
+                    i64::MIN,
+                    i64::MAX
+                ))
+                .with_note(format!(
+                    "write `{}n` for an integer of any size",
+                    error.found.as_deref().unwrap_or_default()
+                )),
+            ParseErrorKind::InvalidEscape => diagnostic.with_note(
+                "surrogates (`\\uD800` to `\\uDFFF`) and code points above `\\U0010FFFF` are not characters",
+            ),
+            ParseErrorKind::InvalidFloat => diagnostic.with_note(
+                "float literals are decimal, `0b`, `0o` and `0x` are for integers, and the exponent fits into 64 bits",
+            ),
+            _ => diagnostic,
+        }
+    }
+}
+
+thread_local! {
+    /// the start, end and rule of the innermost pair that did not lower
+    /// since `lower` began
+    static LOWERING_FAILURE: Cell<Option<(usize, usize, Rule)>> = const { Cell::new(None) };
+}
+
+/// note that `pair` has the rule of a node but does not lower into it, a
+/// pair noted earlier at the same span is the more inner one
+pub(crate) fn lowering_failed(pair: &Pair<'_, Rule>) {
+    let (start, end) = (pair.as_span().start(), pair.as_span().end());
+    let inner = LOWERING_FAILURE
+        .get()
+        .is_none_or(|(prev_start, prev_end, _)| {
+            start > prev_start || (start == prev_start && end < prev_end)
+        });
+    if inner {
+        LOWERING_FAILURE.set(Some((start, end, pair.as_rule())));
+    }
+}
+
+/// lower `pair` into `T`
+pub(crate) fn lower<'a, T: FromPest<'a, Rule = Rule, FatalError = from_pest::Void>>(
+    pair: Pair<'a, Rule>,
+) -> Result<T, ParseError> {
+    LOWERING_FAILURE.set(None);
+    T::from_pest(&mut Pairs::single(pair.clone())).map_err(|_| ParseError::lowering(&pair))
+}
+
+/// the token at `start` as shown to the user, and where it ends
+fn found_at(input: &str, start: usize) -> (String, usize) {
+    let rest = &input[start.min(input.len())..];
+    let Some(first) = rest.chars().next() else {
+        return (rule_name(Rule::EOI).to_string(), start);
+    };
+    if first == '\n' || first == '\r' {
+        return ("newline".to_string(), start + first.len_utf8());
+    }
+    // a control character, such as a recovery sentinel, is unreadable as is
+    if first.is_control() {
+        let end = start + first.len_utf8();
+        return (format!("`{}`", first.escape_default()), end);
+    }
+    let len = if first.is_alphanumeric() || first == '_' {
+        rest.find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len())
+    } else {
+        first.len_utf8()
+    };
+    (format!("`{}`", &rest[..len]), start + len)
+}
+
+/// pest lists every operator it tried, which is too noisy for a message
+fn group_name(rule: Rule) -> &'static str {
+    let name = format!("{:?}", rule);
+    if name.starts_with("prefix_") {
+        rule_name(Rule::expr)
+    } else if name.starts_with("infix_") || name.starts_with("postfix_") {
+        rule_name(Rule::infix_op)
+    } else {
+        rule_name(rule)
+    }
+}
+
+fn rule_names(rules: &[Rule]) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    // only the recovering parser can match an `error_node`
+    for rule in rules.iter().filter(|rule| **rule != Rule::error_node) {
+        let name = group_name(*rule).to_string();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+fn join_or(names: &[String]) -> String {
+    match names {
+        [] => String::new(),
+        [name] => name.clone(),
+        [init @ .., last] => format!("{} or {}", init.join(", "), last),
+    }
+}
+
+/// human friendly name of a grammar rule, used in error messages
+pub fn rule_name(rule: Rule) -> &'static str {
+    match rule {
+        Rule::EOI => "end of input",
+        Rule::WHITESPACE => "whitespace",
+        Rule::COMMENT => "comment",
+        Rule::next_stmt => "`;` or newline",
+        Rule::start | Rule::r#continue => "identifier character",
+        Rule::normal_id => "identifier",
+        Rule::macro_id => "macro name",
+        Rule::id => "identifier",
+        Rule::prefix_not => "`!`",
+        Rule::prefix_neg => "`-`",
+        Rule::prefix_yield => "`<-`",
+        Rule::prefix_bit_not => "`~`",
+        Rule::infix_add => "`+`",
+        Rule::infix_sub => "`-`",
+        Rule::infix_mul => "`*`",
+        Rule::infix_div => "`/`",
+        Rule::infix_mod => "`%`",
+        Rule::infix_eq => "`==`",
+        Rule::infix_neq => "`!=`",
+        Rule::infix_le => "`<=`",
+        Rule::infix_ge => "`>=`",
+        Rule::infix_lt => "`<`",
+        Rule::infix_gt => "`>`",
+        Rule::infix_and => "`&&`",
+        Rule::infix_or => "`||`",
+        Rule::infix_bit_or => "`|`",
+        Rule::infix_bit_and => "`&`",
+        Rule::infix_bit_xor => "`^`",
+        Rule::infix_bit_shift_l => "`<<`",
+        Rule::infix_bit_shift_r => "`>>`",
+        Rule::infix_extends => "`<:`",
+        Rule::infix_function => "`~function~`",
+        Rule::infix_assign => "`=`",
+        Rule::infix_set => "`:=`",
+        Rule::infix_match_equals => "`?=`",
+        Rule::infix_assign_slot => "`::=`",
+        Rule::infix_assign_yield => "`=<=`",
+        Rule::infix_has_field => "`.?`",
+        Rule::infix_pipe => "`|>`",
+        Rule::infix_find_and_call_with_this => "`$`",
+        Rule::magic_fn_id => "operator function",
+        Rule::prefix_annotative_macro_call => "macro call",
+        Rule::c_params_body => "arguments",
+        Rule::postfix_ml_app_param => "argument",
+        Rule::postfix_index => "index",
+        Rule::postfix_slice => "slice",
+        Rule::postfix_access => "field access",
+        Rule::postfix_length => "`.|`",
+        Rule::postfix_c_app_params => "argument list",
+        Rule::prefix_op => "prefix operator",
+        Rule::infix_op => "operator",
+        Rule::postfix_op => "postfix operator",
+        Rule::postfix_trinary_op => "`? :`",
+        Rule::escaped_string_fragment => "escape sequence",
+        Rule::normal_string
+        | Rule::normal_string_inner
+        | Rule::normal_string_fragment
+        | Rule::string => "string",
+        Rule::raw_string | Rule::raw_string_inner => "raw string",
+        Rule::bin_int | Rule::oct_int | Rule::hex_int | Rule::dec_int | Rule::int => "integer",
+        Rule::bigint => "big integer",
+        Rule::exponent_part => "exponent",
+        Rule::float1 | Rule::float2 | Rule::float3 | Rule::float => "float",
+        Rule::number => "number",
+        Rule::boolean_true => "`true`",
+        Rule::boolean_false => "`false`",
+        Rule::boolean => "boolean",
+        Rule::array_body | Rule::array_literal => "array",
+        Rule::object_key => "object key",
+        Rule::object_elem_kv => "object field",
+        Rule::object_body | Rule::object_literal => "object",
+        Rule::void => "`()`",
+        Rule::slot => "`<>`",
+        Rule::literal => "literal",
+        Rule::compound_literal => "literal",
+        Rule::eclipse_pattern => "`...rest` pattern",
+        Rule::array_pattern_elem | Rule::array_pattern_body | Rule::array_pattern => {
+            "array pattern"
+        }
+        Rule::object_pattern_kv
+        | Rule::object_pattern_elem
+        | Rule::object_pattern_body
+        | Rule::object_pattern => "object pattern",
+        Rule::pattern => "pattern",
+        Rule::implicit_params => "implicit parameters",
+        Rule::guard => "guard",
+        Rule::tr_lambda | Rule::no_param_lambda_expr | Rule::lambda_expr => "lambda",
+        Rule::paren_expr => "parenthesized expression",
+        Rule::block => "block",
+        Rule::hole => "hole",
+        Rule::error_node => "error",
+        Rule::op_expr | Rule::primary | Rule::expr => "expression",
+        Rule::single_expr => "expression",
+        Rule::file | Rule::recovering_file => "file",
+        Rule::recovering_stmt => "statement",
+    }
+}
+
+/// reject what pest accepts but the AST can not represent, before lowering
+pub(crate) fn check_pair(
+    pair: &Pair<'_, Rule>,
+    options: &ParseOptions,
+    errors: &mut Vec<ParseError>,
+) {
+    errors.extend(check_one(pair));
+    if unbounded(pair, options) {
+        return;
+    }
+    for inner in pair.clone().into_inner() {
+        check_pair(&inner, options, errors);
+    }
+}
+
+/// the ints under `pair` are lowered to a `BigInt` or are a part of a float
+/// and can not overflow
+pub(crate) fn unbounded(pair: &Pair<'_, Rule>, options: &ParseOptions) -> bool {
+    match pair.as_rule() {
+        Rule::bigint | Rule::float => true,
+        Rule::int => options.promote_ints,
+        _ => false,
+    }
+}
+
+/// like `check_pair`, without looking at the children of `pair`
+pub(crate) fn check_one(pair: &Pair<'_, Rule>) -> Option<ParseError> {
+    let diag = Diagnostic::from_span(pair.as_span());
+    match pair.as_rule() {
+        Rule::bin_int | Rule::oct_int | Rule::hex_int | Rule::dec_int
+            if try_parse_int(pair.as_str()).is_err() =>
+        {
+            Some(ParseError::new(ParseErrorKind::IntOverflow, diag).with_found(pair.as_str()))
+        }
+        Rule::float => check_float(pair),
+        Rule::escaped_string_fragment if try_handle_escape(pair.as_str()).is_none() => {
+            Some(ParseError::new(ParseErrorKind::InvalidEscape, diag).with_found(pair.as_str()))
+        }
+        _ => None,
+    }
+}
+
+/// the parts of a float are decimal, its exponent fits into `i64`
+fn check_float(float: &Pair<'_, Rule>) -> Option<ParseError> {
+    float.clone().into_inner().flatten().find_map(|part| {
+        let invalid = match part.as_rule() {
+            Rule::bin_int | Rule::oct_int | Rule::hex_int => true,
+            Rule::exponent_part => part.as_str()[1..].replace('_', "").parse::<i64>().is_err(),
+            _ => false,
+        };
+        invalid.then(|| {
+            ParseError::new(
+                ParseErrorKind::InvalidFloat,
+                Diagnostic::from_span(part.as_span()),
+            )
+            .with_found(part.as_str())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_expr;
+
+    #[test]
+    fn test_syntax_error() {
+        let err = parse_expr("a + )").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::Syntax);
+        assert_eq!(err.found.as_deref(), Some("`)`"));
+        assert_eq!(err.diag.start_offset, 4);
+        assert!(err.expected.contains(&"expression".to_string()));
+    }
+
+    /// the error points at the literal that does not lower, not at the
+    /// whole expr
+    #[test]
+    fn test_lowering_error() {
+        use pest::Parser;
+
+        let source = "a + f(1, 1e99999999999999999999)";
+        let pair = crate::SapParser::parse(Rule::expr, source)
+            .unwrap()
+            .next()
+            .unwrap();
+        // `check_pair` rejects the exponent before lowering, skip it
+        let err = lower::<crate::expr::Expr>(pair).unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::Lowering);
+        assert_eq!(err.diag.start_offset, 9);
+        assert_eq!(err.diag.end_offset, source.len() - 1);
+        assert_eq!(err.message(), "can not build the AST for literal");
+    }
+
+    #[test]
+    fn test_trailing_input() {
+        let err = parse_expr("a )").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::Syntax);
+        assert!(err.expected.contains(&"end of input".to_string()));
+        assert_eq!(err.diag.start_offset, 2);
+    }
+
+    #[test]
+    fn test_int_overflow() {
+        let err = parse_expr("0xFFFF_FFFF_FFFF_FFFF_FF").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::IntOverflow);
+        assert_eq!(
+            err.message(),
+            "integer literal `0xFFFF_FFFF_FFFF_FFFF_FF` does not fit into 64 bits"
+        );
+        assert!(parse_expr("0xFFFF_FFFF_FFFF_FFFF_FFn").is_ok());
+        let promote = crate::ParseOptions { promote_ints: true };
+        assert!(crate::parse_expr_with("0xFFFF_FFFF_FFFF_FFFF_FF", &promote).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_float() {
+        let err = parse_expr("0b10_10.0b10").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::InvalidFloat);
+        assert_eq!(err.message(), "`0b10_10` is not allowed in a float literal");
+        assert_eq!(
+            parse_expr("1.0x1").unwrap_err().found.as_deref(),
+            Some("0x1")
+        );
+        let err = parse_expr("1e99999999999999999999").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::InvalidFloat);
+        assert_eq!(err.diag.start_col, 2);
+        // the mantissa is not bounded
+        assert!(parse_expr("99999999999999999999.5").is_ok());
+    }
+
+    #[test]
+    fn test_invalid_escape() {
+        let err = parse_expr(r#""\uD800""#).unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::InvalidEscape);
+        assert_eq!(err.diag.start_offset, 1);
+        assert_eq!(err.diag.end_offset, 7);
+    }
+
+    #[test]
+    fn test_rule_name() {
+        assert_eq!(rule_name(Rule::infix_bit_shift_l), "`<<`");
+        assert_eq!(rule_name(Rule::expr), "expression");
+    }
+}
@@ -1,29 +1,32 @@
 use super::*;
 
 pub fn handle_expr_lift_c_params(expr: Expr, CParamsBody(params): CParamsBody) -> Expr {
-    let Expr { inner: pe, diag } = expr;
-    Expr::CApply(Box::new(Expr { inner: pe, diag }), params.clone(), diag)
+    let diag = expr.diag;
+    Expr::CApply(Box::new(expr), params.clone(), diag)
 }
 
 fn handle_mlapply(expr: Expr, pe: Expr) -> Expr {
-    let Expr { inner: pe, diag } = pe;
-    if let ExprInner::MLApply(c, p) = pe {
+    let diag = pe.diag;
+    if let ExprInner::MLApply(c, p) = pe.inner {
         let mut np = vec![*c];
         np.extend(p);
         Expr::MLApply(Box::new(expr), np, diag)
     } else {
-        Expr::MLApply(Box::new(expr), vec![Expr { inner: pe, diag }], diag)
+        Expr::MLApply(Box::new(expr), vec![pe], diag)
     }
 }
 
 fn handle_infix(expr: Expr, pe: Expr) -> Option<Expr> {
-    let Expr { inner: pe, diag } = pe;
+    let Expr {
+        inner: pe, diag, ..
+    } = pe;
     match pe {
         ExprInner::Infix(
             i,
             box Expr {
                 inner: ExprInner::MLApply(c, p),
                 diag: idiag,
+                ..
             },
             ce,
         ) => {
@@ -47,13 +50,16 @@ fn handle_infix(expr: Expr, pe: Expr) -> Option<Expr> {
 }
 
 fn handle_postfix_trinary(expr: Expr, pe: Expr) -> Option<Expr> {
-    let Expr { inner: pe, diag } = pe;
+    let Expr {
+        inner: pe, diag, ..
+    } = pe;
     match pe {
         ExprInner::Postfix(
             Postfix::Trinary(t),
             box Expr {
                 inner: ExprInner::MLApply(c, p),
                 diag: idiag,
+                ..
             },
         ) => {
             let mut np = vec![*c];
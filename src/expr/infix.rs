@@ -1,9 +1,9 @@
 use from_pest::FromPest;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{Rule, id::Id};
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Infix {
     Add,
     AssignYield,
@@ -32,6 +32,44 @@ pub enum Infix {
     Set,
     MatchEquals,
     AssignSlot,
+    HasField,
+}
+
+impl Infix {
+    /// the rule the operator is parsed from, its key in
+    /// `operator_precedence::LEVELS`
+    pub fn rule(&self) -> Rule {
+        match self {
+            Infix::Add => Rule::infix_add,
+            Infix::AssignYield => Rule::infix_assign_yield,
+            Infix::Sub => Rule::infix_sub,
+            Infix::Mul => Rule::infix_mul,
+            Infix::Div => Rule::infix_div,
+            Infix::Mod => Rule::infix_mod,
+            Infix::Eq => Rule::infix_eq,
+            Infix::Neq => Rule::infix_neq,
+            Infix::Extends => Rule::infix_extends,
+            Infix::Le => Rule::infix_le,
+            Infix::Ge => Rule::infix_ge,
+            Infix::Lt => Rule::infix_lt,
+            Infix::Gt => Rule::infix_gt,
+            Infix::And => Rule::infix_and,
+            Infix::Pipe => Rule::infix_pipe,
+            Infix::FindAndCallWithThis => Rule::infix_find_and_call_with_this,
+            Infix::Or => Rule::infix_or,
+            Infix::BitOr => Rule::infix_bit_or,
+            Infix::BitAnd => Rule::infix_bit_and,
+            Infix::BitXor => Rule::infix_bit_xor,
+            Infix::BitShiftL => Rule::infix_bit_shift_l,
+            Infix::BitShiftR => Rule::infix_bit_shift_r,
+            Infix::Function(_) => Rule::infix_function,
+            Infix::Assign => Rule::infix_assign,
+            Infix::Set => Rule::infix_set,
+            Infix::MatchEquals => Rule::infix_match_equals,
+            Infix::AssignSlot => Rule::infix_assign_slot,
+            Infix::HasField => Rule::infix_has_field,
+        }
+    }
 }
 
 impl FromPest<'_> for Infix {
@@ -42,7 +80,9 @@ impl FromPest<'_> for Infix {
     fn from_pest(
         pest: &mut pest::iterators::Pairs<'_, Self::Rule>,
     ) -> Result<Self, from_pest::ConversionError<Self::FatalError>> {
-        let next = pest.next().unwrap();
+        let Some(next) = pest.next() else {
+            return Err(from_pest::ConversionError::NoMatch);
+        };
         match next.as_rule() {
             Rule::infix_add => Ok(Infix::Add),
             Rule::infix_assign_yield => Ok(Infix::AssignYield),
@@ -74,7 +114,8 @@ impl FromPest<'_> for Infix {
             Rule::infix_match_equals => Ok(Infix::MatchEquals),
             Rule::infix_assign_slot => Ok(Infix::AssignSlot),
             Rule::infix_set => Ok(Infix::Set),
-            _ => unreachable!(),
+            Rule::infix_has_field => Ok(Infix::HasField),
+            _ => Err(from_pest::ConversionError::NoMatch),
         }
     }
 }
@@ -86,8 +127,9 @@ mod tests {
 
     use crate::{
         Rule, SapParser,
-        expr::infix::Infix,
+        expr::{ExprInner, infix::Infix},
         id::{Id, NormalId},
+        parse_expr,
     };
 
     #[test]
@@ -124,4 +166,23 @@ mod tests {
         let infix = Infix::from_pest(&mut pest::iterators::Pairs::single(pair)).unwrap();
         assert_eq!(infix, Infix::Set);
     }
+
+    #[test]
+    fn test_infix_has_field() {
+        let expr = parse_expr("a .? b == c").unwrap();
+        let ExprInner::Infix(Infix::Eq, lhs, _) = expr.inner else {
+            panic!("{:?}", expr.inner);
+        };
+        assert!(matches!(lhs.inner, ExprInner::Infix(Infix::HasField, ..)));
+    }
+
+    #[test]
+    fn test_infix_shift_and_function() {
+        let expr = parse_expr("a ~f~ b << c").unwrap();
+        let ExprInner::Infix(Infix::Function(f), _, rhs) = expr.inner else {
+            panic!("{:?}", expr.inner);
+        };
+        assert_eq!(f.value(), "f");
+        assert!(matches!(rhs.inner, ExprInner::Infix(Infix::BitShiftL, ..)));
+    }
 }
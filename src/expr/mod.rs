@@ -10,22 +10,26 @@ use infix::Infix;
 use pest_ast::FromPest;
 use postfix::{CParamsBody, MlAppParam, Postfix, parse_postfix};
 use prefix::Prefix;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     Rule, ast_with_diagnostic,
     function::LambdaExpr,
-    id::Id,
+    id::{Hole, Id},
     literal::CompoundLiteral,
     operator_precedence::pratt_parser,
     pattern::Pattern,
+    preprocessor::comment::doc_before,
     quotations::{Block, ParenExpr},
+    recovery::ErrorNode,
 };
 
 ast_with_diagnostic! {
     Primary(primary) {
+        Error(error: ErrorNode),
         Block(block: Block),
         LambdaExpr(lambda_expr: LambdaExpr),
+        Hole(hole: Hole),
         ParenExpr(paren_expr: ParenExpr),
         CompoundLiteral(compound_literal: CompoundLiteral),
         Id(id: Id),
@@ -33,15 +37,18 @@ ast_with_diagnostic! {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Expr {
     pub inner: ExprInner,
     pub diag: Diagnostic,
+    /// the `///` block right above the expr
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
 }
 
 impl PartialEq for Expr {
     fn eq(&self, other: &Self) -> bool {
-        self.inner == other.inner
+        self.inner == other.inner && self.doc == other.doc
     }
 }
 
@@ -53,15 +60,22 @@ impl FromPest<'_> for Expr {
     fn from_pest(
         pest: &mut pest::iterators::Pairs<'_, Self::Rule>,
     ) -> Result<Self, from_pest::ConversionError<Self::FatalError>> {
-        let pest = pest.next();
-        if pest.is_none() {
+        // the cursor only moves past a pair that lowers, so a parent sees a
+        // pair it could not lower as extraneous
+        let mut clone = pest.clone();
+        let Some(pair) = clone.next() else {
             return Err(from_pest::ConversionError::NoMatch);
-        }
-        let pest = pest.unwrap();
+        };
 
-        let diag = Diagnostic::from_span(pest.as_span());
-        let inner = ExprInner::from_pest(&mut Pairs::single(pest))?;
-        Ok(Expr { inner, diag })
+        let diag = Diagnostic::from_span(pair.as_span());
+        let doc = doc_before(pair.as_span());
+        let inner = ExprInner::from_pest(&mut Pairs::single(pair.clone()));
+        if inner.is_err() && pair.as_rule() == Rule::expr {
+            crate::error::lowering_failed(&pair);
+        }
+        let inner = inner?;
+        *pest = clone;
+        Ok(Expr { inner, diag, doc })
     }
 }
 
@@ -71,6 +85,7 @@ impl Expr {
         Expr {
             inner: ExprInner::Prefix(prefix, expr),
             diag,
+            doc: None,
         }
     }
 
@@ -79,6 +94,7 @@ impl Expr {
         Expr {
             inner: ExprInner::Postfix(postfix, expr),
             diag,
+            doc: None,
         }
     }
 
@@ -87,6 +103,7 @@ impl Expr {
         Expr {
             inner: ExprInner::Infix(infix, lhs, rhs),
             diag,
+            doc: None,
         }
     }
 
@@ -95,6 +112,7 @@ impl Expr {
         Expr {
             inner: ExprInner::CApply(expr, params),
             diag,
+            doc: None,
         }
     }
 
@@ -103,6 +121,7 @@ impl Expr {
         Expr {
             inner: ExprInner::MLApply(expr, params),
             diag,
+            doc: None,
         }
     }
 
@@ -111,11 +130,12 @@ impl Expr {
         Expr {
             inner: ExprInner::Primary(primary),
             diag,
+            doc: None,
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ExprInner {
     Prefix(Prefix, Box<Expr>),
     Primary(Primary),
@@ -288,4 +308,70 @@ main = _{
 
         println!("{:#?}", expr);
     }
+
+    /// a pair that does not lower stays under the cursor, so the parent
+    /// fails on it instead of dropping it
+    #[test]
+    fn test_no_match_keeps_the_cursor() {
+        let pair = crate::SapParser::parse(Rule::id, "a")
+            .unwrap()
+            .next()
+            .unwrap();
+        let mut pairs = pest::iterators::Pairs::single(pair);
+        assert!(Expr::from_pest(&mut pairs).is_err());
+        assert!(pairs.next().is_some());
+    }
+
+    /// lowering fails on a rule with no AST node, every rule must be in here
+    #[test]
+    fn test_every_rule_lowers() {
+        let corpus = [
+            "x = a + b -\n c * d / e % f == g != h <= i >= j < k > l && m || n | o & p ^ q",
+            "a << b >> c <: d ~f~ e |> g $ h",
+            "x := y =<= z ?= w ::= <>\n<- !-~a",
+            "@@m(1) @@n a .? b",
+            "f(a, b)[1][1:2:3].c.| (g(,)) ?x",
+            "c ? [1, 2] : {k: \"s\\n\", \"k\": r#\"r\"#, j,}",
+            "\\^[a, ...b] ^{k: ^[c], l, ...r} 1 true false () ?i : a -> b",
+            "_{ {a; b} }; (0b1 + 0o7 + 0xF + 1e3 + 1.5E-2 + .5 + 9n)",
+            "(+) (neg) (.?) @id",
+            "x = [] {} \\^[] ^{} -> {k: [], j: {}}; \\x -> { [] }; _{ ^[] }",
+        ];
+        let mut seen = std::collections::HashSet::new();
+        for source in corpus {
+            let file = crate::SapParser::parse(Rule::file, source)
+                .unwrap()
+                .next()
+                .unwrap();
+            seen.extend(file.into_inner().flatten().map(|pair| pair.as_rule()));
+            if let Err(error) = crate::parse_file(source) {
+                panic!("{source:?}: {error:?}");
+            }
+        }
+        // silent rules, the insides of `normal_id` and the entry points
+        // never show up as a pair, `error_node` is only made by recovery
+        let hidden = [
+            Rule::error_node,
+            Rule::recovering_file,
+            Rule::recovering_stmt,
+            Rule::WHITESPACE,
+            Rule::COMMENT,
+            Rule::next_stmt,
+            Rule::start,
+            Rule::r#continue,
+            Rule::prefix_op,
+            Rule::infix_op,
+            Rule::postfix_op,
+            Rule::array_literal,
+            Rule::object_literal,
+            Rule::op_expr,
+            Rule::single_expr,
+            Rule::file,
+        ];
+        let missing: Vec<_> = Rule::all_rules()
+            .iter()
+            .filter(|rule| !seen.contains(rule) && !hidden.contains(rule))
+            .collect();
+        assert_eq!(missing, Vec::<&Rule>::new());
+    }
 }
@@ -1,62 +1,111 @@
 use from_pest::FromPest;
 use pest::iterators::{Pair, Pairs};
 use pest_ast::FromPest;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{Rule, id::Id};
 
 use super::Expr;
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::c_params_body))]
 pub struct CParamsBody(pub Vec<Expr>);
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::postfix_c_app_params))]
 pub struct CAppParams(pub Option<CParamsBody>);
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::postfix_index))]
 pub struct Index {
     pub postfix_index: Box<Expr>,
 }
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
-#[pest_ast(rule(Rule::postfix_slice))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Slice {
     pub start: Option<Box<Expr>>,
     pub end: Option<Box<Expr>>,
     pub step: Option<Box<Expr>>,
 }
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+/// every bound is optional, the `:`s before an expr tell which one it is
+impl FromPest<'_> for Slice {
+    type Rule = Rule;
+
+    type FatalError = from_pest::Void;
+
+    fn from_pest(
+        pest: &mut Pairs<'_, Self::Rule>,
+    ) -> Result<Self, from_pest::ConversionError<Self::FatalError>> {
+        let pair = match pest.peek() {
+            Some(pair) if pair.as_rule() == Rule::postfix_slice => pest.next().unwrap(),
+            _ => return Err(from_pest::ConversionError::NoMatch),
+        };
+        let source = pair.as_str();
+        let offset = pair.as_span().start();
+        let mut bounds = [None, None, None];
+        let mut colons = 0;
+        let mut last = 0;
+        for inner in pair.into_inner() {
+            let start = inner.as_span().start() - offset;
+            colons += source[last..start]
+                .lines()
+                .map(|line| line.split("//").next().unwrap_or("").matches(':').count())
+                .sum::<usize>();
+            last = inner.as_span().end() - offset;
+            let expr = Expr::from_pest(&mut Pairs::single(inner))?;
+            bounds[colons.min(2)] = Some(Box::new(expr));
+        }
+        let [start, end, step] = bounds;
+        Ok(Slice { start, end, step })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::postfix_ml_app_param))]
 pub struct MlAppParam(pub Box<Expr>);
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::postfix_access))]
 pub struct Access {
     pub id: Id,
 }
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::postfix_trinary_op))]
 pub struct Trinary {
     pub true_expr: Box<Expr>,
     pub false_expr: Box<Expr>,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Postfix {
     Trinary(Trinary),
     Slice(Slice),
     Index(Index),
     Access(Access),
+    Length,
     // these two should not be used in the final AST
     CAppParams(CAppParams),
     MlAppParam(MlAppParam),
 }
 
+impl Postfix {
+    /// the rule the operator is parsed from, its key in
+    /// `operator_precedence::LEVELS`
+    pub fn rule(&self) -> Rule {
+        match self {
+            Postfix::Trinary(_) => Rule::postfix_trinary_op,
+            Postfix::Slice(_) => Rule::postfix_slice,
+            Postfix::Index(_) => Rule::postfix_index,
+            Postfix::Access(_) => Rule::postfix_access,
+            Postfix::Length => Rule::postfix_length,
+            Postfix::CAppParams(_) => Rule::postfix_c_app_params,
+            Postfix::MlAppParam(_) => Rule::postfix_ml_app_param,
+        }
+    }
+}
+
 pub fn parse_postfix(
     rule: Pair<Rule>,
 ) -> Result<Postfix, from_pest::ConversionError<from_pest::Void>> {
@@ -67,10 +116,11 @@ pub fn parse_postfix(
         Rule::postfix_slice => Ok(Postfix::Slice(Slice::from_pest(&mut pairs)?)),
         Rule::postfix_index => Ok(Postfix::Index(Index::from_pest(&mut pairs)?)),
         Rule::postfix_access => Ok(Postfix::Access(Access::from_pest(&mut pairs)?)),
+        Rule::postfix_length => Ok(Postfix::Length),
         Rule::postfix_c_app_params => Ok(Postfix::CAppParams(CAppParams::from_pest(&mut pairs)?)),
         Rule::postfix_ml_app_param => Ok(Postfix::MlAppParam(MlAppParam::from_pest(&mut pairs)?)),
 
-        _ => unreachable!(),
+        _ => Err(from_pest::ConversionError::NoMatch),
     }
 }
 
@@ -122,6 +172,29 @@ mod tests {
     //     }
     // }
 
+    #[test]
+    fn test_postfix_slice() {
+        let bounds = |source| {
+            let pair = crate::SapParser::parse(Rule::postfix_slice, source)
+                .unwrap()
+                .next()
+                .unwrap();
+            let mut pairs = pest::iterators::Pairs::single(pair);
+            let slice = super::Slice::from_pest(&mut pairs).unwrap();
+            (
+                slice.start.is_some(),
+                slice.end.is_some(),
+                slice.step.is_some(),
+            )
+        };
+        assert_eq!(bounds("[:2]"), (false, true, false));
+        assert_eq!(bounds("[1:]"), (true, false, false));
+        assert_eq!(bounds("[::3]"), (false, false, true));
+        assert_eq!(bounds("[1::3]"), (true, false, true));
+        assert_eq!(bounds("[:2:3]"), (false, true, true));
+        assert_eq!(bounds("[1:2:3]"), (true, true, true));
+    }
+
     #[test]
     fn test_postfix_access() {
         let pair = crate::SapParser::parse(Rule::postfix_access, ".a")
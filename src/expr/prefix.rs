@@ -1,11 +1,11 @@
 use from_pest::FromPest;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{Rule, id::MacroId};
 
 use super::Expr;
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Prefix {
     Not,
     BitNot,
@@ -14,6 +14,20 @@ pub enum Prefix {
     AnnotativeMacroCall(MacroId, Option<Box<Expr>>),
 }
 
+impl Prefix {
+    /// the rule the operator is parsed from, its key in
+    /// `operator_precedence::LEVELS`
+    pub fn rule(&self) -> Rule {
+        match self {
+            Prefix::Not => Rule::prefix_not,
+            Prefix::BitNot => Rule::prefix_bit_not,
+            Prefix::Neg => Rule::prefix_neg,
+            Prefix::Yield => Rule::prefix_yield,
+            Prefix::AnnotativeMacroCall(..) => Rule::prefix_annotative_macro_call,
+        }
+    }
+}
+
 impl FromPest<'_> for Prefix {
     type Rule = Rule;
 
@@ -22,7 +36,9 @@ impl FromPest<'_> for Prefix {
     fn from_pest(
         pest: &mut pest::iterators::Pairs<'_, Self::Rule>,
     ) -> Result<Self, from_pest::ConversionError<Self::FatalError>> {
-        let next = pest.next().unwrap();
+        let Some(next) = pest.next() else {
+            return Err(from_pest::ConversionError::NoMatch);
+        };
         match next.as_rule() {
             Rule::prefix_not => Ok(Prefix::Not),
             Rule::prefix_bit_not => Ok(Prefix::BitNot),
@@ -31,12 +47,14 @@ impl FromPest<'_> for Prefix {
             Rule::prefix_annotative_macro_call => {
                 let mut pairs = next.into_inner();
                 let macro_id = MacroId::from_pest(&mut pairs)?;
-                let expr = pairs.next().map(|pair| {
-                    Box::new(Expr::from_pest(&mut pest::iterators::Pairs::single(pair)).unwrap())
-                });
+                let expr = pairs
+                    .next()
+                    .map(|pair| Expr::from_pest(&mut pest::iterators::Pairs::single(pair)))
+                    .transpose()?
+                    .map(Box::new);
                 Ok(Prefix::AnnotativeMacroCall(macro_id, expr))
             }
-            _ => unreachable!(),
+            _ => Err(from_pest::ConversionError::NoMatch),
         }
     }
 }
@@ -0,0 +1,291 @@
+//! the `//` comments of the source being formatted, handed out by position
+//! to the list items they belong to
+//!
+//! a comment on the line of an item, after it, trails that item. Comments
+//! on their own lines right above an item lead it, and the ones after the
+//! last item of a list dangle before its closing bracket. Inside an item the
+//! comments right above an expr stay there, the source had a newline there
+//! already. Whatever is left moves above the item, a `///` written `//` so
+//! it documents nothing else, and no comment is ever dropped.
+
+use std::{cell::RefCell, ops::Range};
+
+use crate::{
+    diagnostics::Diagnostic,
+    preprocessor::comment::{self, CommentKind},
+    program::trim_end,
+};
+
+use super::{Context, doc::Doc};
+
+#[derive(Debug)]
+pub struct Comments<'a> {
+    source: &'a str,
+    comments: Vec<Range<usize>>,
+    kinds: Vec<CommentKind>,
+    /// printed already, or printed elsewhere like the `//!` headings
+    claimed: RefCell<Vec<bool>>,
+}
+
+/// a comment line above an item or before a closing bracket
+struct Line {
+    text: String,
+    blank_before: bool,
+}
+
+impl<'a> Comments<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let classified = comment::classify(source);
+        Comments {
+            source,
+            comments: classified
+                .iter()
+                .map(|comment| comment.diag.start_offset..comment.diag.end_offset)
+                .collect(),
+            kinds: classified.iter().map(|comment| comment.kind).collect(),
+            claimed: RefCell::new(
+                classified
+                    .iter()
+                    .map(|comment| comment.kind == CommentKind::DocHeading)
+                    .collect(),
+            ),
+        }
+    }
+
+    /// the comments not printed yet
+    pub fn unclaimed(&self) -> usize {
+        self.claimed
+            .borrow()
+            .iter()
+            .filter(|claimed| !**claimed)
+            .count()
+    }
+
+    /// the span of a node without the whitespace and comment pest leaves
+    /// after it
+    pub fn span(&self, diag: &Diagnostic) -> Range<usize> {
+        let start = diag.start_offset.min(self.source.len());
+        let end = diag.end_offset.clamp(start, self.source.len());
+        start..trim_end(self.source, start, end)
+    }
+
+    fn text(&self, i: usize) -> String {
+        self.source[self.comments[i].clone()].trim_end().to_string()
+    }
+
+    fn claim(&self, i: usize) -> bool {
+        !std::mem::replace(&mut self.claimed.borrow_mut()[i], true)
+    }
+
+    /// nothing but whitespace, comments and `separators` in `range`
+    fn is_empty(&self, range: Range<usize>, separators: &[char]) -> bool {
+        let mut pos = range.start;
+        let gap = |text: &str| {
+            text.trim_matches(|c: char| c.is_whitespace() || separators.contains(&c))
+                .is_empty()
+        };
+        for comment in &self.comments {
+            if comment.start < range.start || comment.end > range.end {
+                continue;
+            }
+            if !gap(&self.source[pos..comment.start]) {
+                return false;
+            }
+            pos = comment.end;
+        }
+        gap(&self.source[pos..range.end])
+    }
+
+    /// an empty line between `from` and `to`, a comment line is not one
+    fn is_blank_line(&self, from: usize, to: usize) -> bool {
+        if from >= to {
+            return false;
+        }
+        let lines: Vec<_> = self.source[from..to].split('\n').collect();
+        lines.len() > 2
+            && lines[1..lines.len() - 1]
+                .iter()
+                .any(|line| line.trim().is_empty())
+    }
+
+    /// the unclaimed comments right above `start`
+    fn leading(&self, start: usize) -> Vec<usize> {
+        let mut leading = vec![];
+        for (i, comment) in self.comments.iter().enumerate().rev() {
+            if comment.end > start {
+                continue;
+            }
+            if !self.is_empty(comment.end..start, &[]) {
+                break;
+            }
+            if !self.claimed.borrow()[i] {
+                leading.push(i);
+            }
+        }
+        leading.reverse();
+        leading
+    }
+
+    /// the unclaimed comment on the line `end` is on, after nothing but
+    /// separators
+    fn trailing(&self, end: usize) -> Option<usize> {
+        let i = self
+            .comments
+            .iter()
+            .position(|comment| comment.start >= end)?;
+        let between = &self.source[end..self.comments[i].start];
+        (!self.claimed.borrow()[i] && between.chars().all(|c| matches!(c, ' ' | '\t' | ',' | ';')))
+            .then_some(i)
+    }
+
+    /// the unclaimed comments after `end`, up to the next token
+    fn dangling(&self, end: usize) -> Vec<usize> {
+        self.comments
+            .iter()
+            .enumerate()
+            .filter(|(_, comment)| comment.start >= end)
+            .take_while(|(_, comment)| self.is_empty(end..comment.start, &[',', ';']))
+            .filter(|(i, _)| !self.claimed.borrow()[*i])
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// the unclaimed comments inside `range`
+    fn inside(&self, range: Range<usize>) -> Vec<usize> {
+        (0..self.comments.len())
+            .filter(|&i| {
+                range.start <= self.comments[i].start
+                    && self.comments[i].end <= range.end
+                    && !self.claimed.borrow()[i]
+            })
+            .collect()
+    }
+
+    /// claim `comments` as lines, `prev` is where the text before them ended
+    fn lines(&self, comments: &[usize], mut prev: usize, blank_lines: bool) -> Vec<Line> {
+        let mut lines = vec![];
+        for &i in comments {
+            if self.claim(i) {
+                lines.push(Line {
+                    text: self.text(i),
+                    blank_before: blank_lines && self.is_blank_line(prev, self.comments[i].start),
+                });
+            }
+            prev = self.comments[i].end;
+        }
+        lines
+    }
+}
+
+/// an item of a list, with its doc and the comments around it
+///
+/// `prev` is where the item before it ended, `None` for the first item. It
+/// returns where this item ends, for the next one
+pub(super) fn commented(
+    context: &Context,
+    diag: &Diagnostic,
+    doc: Option<&str>,
+    prev: Option<usize>,
+    blank_lines: bool,
+    build: impl FnOnce() -> Doc,
+) -> (Doc, usize) {
+    let doc_lines = || doc.map_or(Doc::Nil, |doc| super::doc_comment("///", doc));
+    let Some(comments) = context.comments else {
+        return (Doc::concat([doc_lines(), build()]), diag.end_offset);
+    };
+    let span = comments.span(diag);
+    let mut leading = comments.leading(span.start);
+    // the `///` lines right above the item are its doc
+    if doc.is_some() {
+        while leading
+            .last()
+            .is_some_and(|&i| comments.kinds[i] == CommentKind::StructuralDoc)
+        {
+            let i = leading.pop().unwrap();
+            comments.claim(i);
+        }
+    }
+    let blank_lines = blank_lines && prev.is_some();
+    let start = prev.unwrap_or(span.start);
+    let mut docs = vec![];
+    let first = leading
+        .first()
+        .map_or(span.start, |&i| comments.comments[i].start);
+    if blank_lines && comments.is_blank_line(start, first) {
+        docs.push(Doc::HardLine);
+    }
+    let last = leading.last().map(|&i| comments.comments[i].end);
+    let leading = comments.lines(&leading, start, blank_lines);
+    let node = build();
+    // what the nested lists left inside the item
+    let inside = comments.inside(span.clone());
+    let docs_inside: Vec<_> = inside
+        .iter()
+        .map(|&i| comments.kinds[i] == CommentKind::StructuralDoc)
+        .collect();
+    let mut inside = comments.lines(&inside, span.start, false);
+    for (line, _) in inside.iter_mut().zip(docs_inside).filter(|(_, doc)| *doc) {
+        line.text.remove(2);
+    }
+    for (i, line) in leading.into_iter().chain(inside).enumerate() {
+        if line.blank_before && i > 0 {
+            docs.push(Doc::HardLine);
+        }
+        docs.push(Doc::text(line.text));
+        docs.push(Doc::HardLine);
+    }
+    if let Some(last) = last
+        && blank_lines
+        && comments.is_blank_line(last, span.start)
+    {
+        docs.push(Doc::HardLine);
+    }
+    docs.push(doc_lines());
+    docs.push(node);
+    let mut end = span.end;
+    if let Some(i) = comments.trailing(span.end) {
+        comments.claim(i);
+        docs.push(Doc::LineSuffix(format!(" {}", comments.text(i))));
+        end = comments.comments[i].end;
+    }
+    (Doc::concat(docs), end)
+}
+
+/// the comments after the last item of a list, each on its own line after
+/// a newline
+pub(super) fn dangling(context: &Context, prev: usize, blank_lines: bool) -> Doc {
+    let Some(comments) = context.comments else {
+        return Doc::Nil;
+    };
+    let dangling = comments.dangling(prev);
+    let mut docs = vec![];
+    for line in comments.lines(&dangling, prev, blank_lines) {
+        if line.blank_before {
+            docs.push(Doc::HardLine);
+        }
+        docs.push(Doc::HardLine);
+        docs.push(Doc::text(line.text));
+    }
+    Doc::concat(docs)
+}
+
+/// the comments right above an expr inside an item, each on its own line
+pub(super) fn inner_leading(context: &Context, diag: &Diagnostic) -> Doc {
+    let Some(comments) = context.comments else {
+        return Doc::Nil;
+    };
+    let leading = comments.leading(comments.span(diag).start);
+    Doc::concat(
+        comments
+            .lines(&leading, 0, false)
+            .into_iter()
+            .flat_map(|line| [Doc::text(line.text), Doc::HardLine]),
+    )
+}
+
+/// whether `inner_leading` has comments for the expr
+pub(super) fn has_inner_leading(context: &Context, diag: &Diagnostic) -> bool {
+    context
+        .comments
+        .is_some_and(|comments| !comments.leading(comments.span(diag).start).is_empty())
+}
@@ -0,0 +1,113 @@
+//! `sapfmt.toml`, the formatter options of every file below it
+//!
+//! the keys are the fields of `GlobalOptions`, missing ones keep their
+//! default:
+//!
+//! ```toml
+//! indent_size = 2
+//! screen_width = 80
+//! function_application_style = "ml_style"
+//! always_tailing_comma = true
+//! ```
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::GlobalOptions;
+
+pub const CONFIG_FILE: &str = "sapfmt.toml";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Toml(PathBuf, toml::de::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, error) => write!(f, "{}: {}", path.display(), error),
+            ConfigError::Toml(path, error) => write!(f, "{}: {}", path.display(), error),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+pub fn parse_config(source: &str) -> Result<GlobalOptions, toml::de::Error> {
+    toml::from_str(source)
+}
+
+pub fn load_config(path: &Path) -> Result<GlobalOptions, ConfigError> {
+    let source =
+        fs::read_to_string(path).map_err(|error| ConfigError::Io(path.to_path_buf(), error))?;
+    parse_config(&source).map_err(|error| ConfigError::Toml(path.to_path_buf(), error))
+}
+
+/// the nearest `sapfmt.toml` in the directory of `path` or above it
+pub fn find_config(path: &Path) -> Option<PathBuf> {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let start = if path.is_dir() {
+        path.as_path()
+    } else {
+        path.parent()?
+    };
+    start
+        .ancestors()
+        .map(|dir| dir.join(CONFIG_FILE))
+        .find(|config| config.is_file())
+}
+
+/// the options to format `path` with, the defaults without a config file
+pub fn options_for(path: &Path) -> Result<GlobalOptions, ConfigError> {
+    match find_config(path) {
+        Some(config) => load_config(&config),
+        None => Ok(GlobalOptions::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{formatter::FunctionApplicationStyle, test_util::TempDir};
+
+    #[test]
+    fn test_parse_config() {
+        let options = parse_config(
+            "indent_size = 2\nfunction_application_style = \"ml_style\"\nalways_tailing_comma = true",
+        )
+        .unwrap();
+        assert_eq!(
+            options,
+            GlobalOptions {
+                indent_size: 2,
+                function_application_style: FunctionApplicationStyle::MLStyle,
+                always_tailing_comma: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(parse_config("").unwrap(), GlobalOptions::default());
+        assert!(parse_config("indent = 2").is_err());
+        assert!(parse_config("function_application_style = \"c\"").is_err());
+    }
+
+    #[test]
+    fn test_find_config() {
+        let dir = TempDir::new("config");
+        dir.write(CONFIG_FILE, "screen_width = 60");
+        dir.write("a/b/c.sap", "c");
+
+        let config = find_config(&dir.join("a/b/c.sap")).unwrap();
+        assert_eq!(config, dir.canonicalize().unwrap().join(CONFIG_FILE));
+        let options = options_for(&dir.join("a/b/c.sap")).unwrap();
+        assert_eq!(options.screen_width, 60);
+
+        dir.write(&format!("a/{}", CONFIG_FILE), "screen_width = x");
+        assert!(matches!(
+            options_for(&dir.join("a/b")),
+            Err(ConfigError::Toml(..))
+        ));
+    }
+}
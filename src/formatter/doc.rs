@@ -0,0 +1,235 @@
+//! a Wadler/Oppen style document, every group is laid out flat if it fits
+//! in the rest of the line and broken otherwise
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Doc {
+    Nil,
+    Text(String),
+    /// a space when flat, a newline when broken
+    Line,
+    /// nothing when flat, a newline when broken
+    SoftLine,
+    /// always a newline, the groups around it are broken
+    HardLine,
+    Concat(Vec<Doc>),
+    Group(Box<Doc>),
+    /// indent the lines inside by some columns
+    Nest(usize, Box<Doc>),
+    /// the first doc when the enclosing group is broken, else the second
+    IfBreak(Box<Doc>, Box<Doc>),
+    /// text put off to the end of the line, for a trailing `//` comment,
+    /// the groups around it are broken
+    LineSuffix(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+type Command<'a> = (usize, Mode, &'a Doc);
+
+impl Doc {
+    pub fn text(text: impl Into<String>) -> Doc {
+        Doc::Text(text.into())
+    }
+
+    pub fn concat(docs: impl IntoIterator<Item = Doc>) -> Doc {
+        Doc::Concat(docs.into_iter().collect())
+    }
+
+    pub fn group(doc: Doc) -> Doc {
+        Doc::Group(Box::new(doc))
+    }
+
+    pub fn nest(indent: usize, doc: Doc) -> Doc {
+        Doc::Nest(indent, Box::new(doc))
+    }
+
+    pub fn if_break(broken: Doc, flat: Doc) -> Doc {
+        Doc::IfBreak(Box::new(broken), Box::new(flat))
+    }
+
+    /// `docs` with `separator` between each two
+    pub fn join(docs: impl IntoIterator<Item = Doc>, separator: Doc) -> Doc {
+        let mut out = vec![];
+        for (i, doc) in docs.into_iter().enumerate() {
+            if i > 0 {
+                out.push(separator.clone());
+            }
+            out.push(doc);
+        }
+        Doc::Concat(out)
+    }
+
+    /// lay out in `width` columns, starting at column `indent` which is
+    /// also the indent of every following line
+    pub fn render(&self, width: usize, indent: usize) -> String {
+        let mut out = String::new();
+        let mut suffix = String::new();
+        let mut column = indent;
+        let mut stack: Vec<Command> = vec![(indent, Mode::Break, self)];
+        while let Some((indent, mode, doc)) = stack.pop() {
+            match doc {
+                Doc::Nil => {}
+                Doc::Text(text) => {
+                    out.push_str(text);
+                    column += text.chars().count();
+                }
+                Doc::Concat(docs) => {
+                    stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc)));
+                }
+                Doc::Group(doc) => {
+                    let flat = (indent, Mode::Flat, doc.as_ref());
+                    let mode = if mode == Mode::Flat
+                        || fits(width as isize - column as isize, flat, &stack)
+                    {
+                        Mode::Flat
+                    } else {
+                        Mode::Break
+                    };
+                    stack.push((indent, mode, doc));
+                }
+                Doc::Nest(nest, doc) => stack.push((indent + nest, mode, doc)),
+                Doc::IfBreak(broken, flat) => match mode {
+                    Mode::Break => stack.push((indent, mode, broken)),
+                    Mode::Flat => stack.push((indent, mode, flat)),
+                },
+                Doc::Line if mode == Mode::Flat => {
+                    out.push(' ');
+                    column += 1;
+                }
+                Doc::SoftLine if mode == Mode::Flat => {}
+                Doc::LineSuffix(text) => suffix.push_str(text),
+                Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                    out.push_str(&std::mem::take(&mut suffix));
+                    // no trailing whitespace, blank lines stay empty
+                    out.truncate(out.trim_end_matches(' ').len());
+                    out.push('\n');
+                    out.push_str(&" ".repeat(indent));
+                    column = indent;
+                }
+            }
+        }
+        out.push_str(&suffix);
+        out
+    }
+}
+
+/// whether `next` fits flat in `remaining` columns, up to the next newline
+/// of what follows it
+fn fits(mut remaining: isize, next: Command, rest: &[Command]) -> bool {
+    let mut rest = rest.iter().rev();
+    let mut stack = vec![next];
+    loop {
+        if remaining < 0 {
+            return false;
+        }
+        let (indent, mode, doc) = match stack.pop() {
+            Some(command) => command,
+            None => match rest.next() {
+                Some(&command) => command,
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Nil => {}
+            Doc::Text(text) => remaining -= text.chars().count() as isize,
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc))),
+            Doc::Group(doc) | Doc::Nest(_, doc) => stack.push((indent, mode, doc)),
+            Doc::IfBreak(broken, flat) => match mode {
+                Mode::Break => stack.push((indent, mode, broken)),
+                Mode::Flat => stack.push((indent, mode, flat)),
+            },
+            Doc::Line | Doc::SoftLine if mode == Mode::Break => return true,
+            Doc::Line => remaining -= 1,
+            Doc::SoftLine => {}
+            // a group with a hard line or a line comment can never be flat
+            Doc::HardLine => return mode == Mode::Break,
+            Doc::LineSuffix(_) if mode == Mode::Flat => return false,
+            Doc::LineSuffix(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(elems: &[&str]) -> Doc {
+        Doc::group(Doc::concat([
+            Doc::text("["),
+            Doc::nest(
+                2,
+                Doc::concat([
+                    Doc::SoftLine,
+                    Doc::join(
+                        elems.iter().map(|elem| Doc::text(*elem)),
+                        Doc::concat([Doc::text(","), Doc::Line]),
+                    ),
+                    Doc::if_break(Doc::text(","), Doc::Nil),
+                ]),
+            ),
+            Doc::SoftLine,
+            Doc::text("]"),
+        ]))
+    }
+
+    #[test]
+    fn test_render_group() {
+        let doc = list(&["a", "b", "c"]);
+        assert_eq!(doc.render(9, 0), "[a, b, c]");
+        assert_eq!(doc.render(8, 0), "[\n  a,\n  b,\n  c,\n]");
+        assert_eq!(doc.render(10, 2), "[\n    a,\n    b,\n    c,\n  ]");
+    }
+
+    #[test]
+    fn test_render_nested_groups() {
+        let doc = Doc::concat([list(&["aaaa", "bbbb"]), Doc::text(";")]);
+        // the `;` after the group counts too
+        assert_eq!(doc.render(13, 0), "[aaaa, bbbb];");
+        assert_eq!(doc.render(12, 0), "[\n  aaaa,\n  bbbb,\n];");
+
+        let outer = Doc::group(Doc::concat([
+            Doc::text("f("),
+            Doc::nest(2, Doc::concat([Doc::SoftLine, list(&["a", "b"])])),
+            Doc::SoftLine,
+            Doc::text(")"),
+        ]));
+        assert_eq!(outer.render(10, 0), "f([a, b])");
+        assert_eq!(outer.render(8, 0), "f(\n  [a, b]\n)");
+    }
+
+    #[test]
+    fn test_render_hard_line() {
+        let doc = Doc::group(Doc::concat([
+            Doc::text("{"),
+            Doc::nest(4, Doc::concat([Doc::Line, Doc::text("a"), Doc::HardLine])),
+            Doc::HardLine,
+            Doc::text("}"),
+        ]));
+        assert_eq!(doc.render(80, 0), "{\n    a\n\n}");
+    }
+
+    #[test]
+    fn test_render_line_suffix() {
+        let doc = Doc::group(Doc::concat([
+            Doc::text("["),
+            Doc::nest(
+                2,
+                Doc::concat([
+                    Doc::SoftLine,
+                    Doc::text("a"),
+                    Doc::LineSuffix(" // a".to_string()),
+                    Doc::text(","),
+                    Doc::Line,
+                    Doc::text("b"),
+                ]),
+            ),
+            Doc::SoftLine,
+            Doc::text("]"),
+        ]));
+        assert_eq!(doc.render(80, 0), "[\n  a, // a\n  b\n]");
+    }
+}
@@ -0,0 +1,327 @@
+use crate::{
+    expr::{
+        Expr, ExprInner, Inner, Primary,
+        infix::Infix,
+        postfix::{CAppParams, Postfix},
+        prefix::Prefix,
+    },
+    id::{Hole, Id},
+};
+
+use super::{
+    Context, FunctionApplicationStyle, GlobalOptions, ToDoc, comments, doc::Doc, items, list, nest,
+};
+
+impl ToDoc for Id {
+    fn to_doc(&self, _context: &Context, _options: &GlobalOptions) -> Doc {
+        Doc::text(self.value())
+    }
+}
+
+impl ToDoc for Hole {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        Doc::concat([Doc::text("?"), self.id.to_doc(context, options)])
+    }
+}
+
+impl ToDoc for Prefix {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        match self {
+            Prefix::Not => Doc::text("!"),
+            Prefix::BitNot => Doc::text("~"),
+            Prefix::Neg => Doc::text("-"),
+            Prefix::Yield => Doc::text("<-"),
+            Prefix::AnnotativeMacroCall(macro_id, arg) => match arg {
+                Some(arg) => Doc::concat([
+                    Doc::text(format!("@{}(", macro_id.value)),
+                    arg.to_doc(context, options),
+                    Doc::text(") "),
+                ]),
+                None => Doc::text(format!("@{} ", macro_id.value)),
+            },
+        }
+    }
+}
+
+impl ToDoc for Infix {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        let op = match self {
+            Infix::Add => "+",
+            Infix::AssignYield => "=<=",
+            Infix::Sub => "-",
+            Infix::Mul => "*",
+            Infix::Div => "/",
+            Infix::Mod => "%",
+            Infix::Eq => "==",
+            Infix::Neq => "!=",
+            Infix::Extends => "<:",
+            Infix::Le => "<=",
+            Infix::Ge => ">=",
+            Infix::Lt => "<",
+            Infix::Gt => ">",
+            Infix::And => "&&",
+            Infix::Pipe => "|>",
+            Infix::FindAndCallWithThis => "$",
+            Infix::Or => "||",
+            Infix::BitOr => "|",
+            Infix::BitAnd => "&",
+            Infix::BitXor => "^",
+            Infix::BitShiftL => "<<",
+            Infix::BitShiftR => ">>",
+            Infix::Function(id) => {
+                return Doc::concat([Doc::text("~"), id.to_doc(context, options), Doc::text("~")]);
+            }
+            Infix::Assign => "=",
+            Infix::Set => ":=",
+            Infix::MatchEquals => "?=",
+            Infix::AssignSlot => "::=",
+            Infix::HasField => ".?",
+        };
+        Doc::text(op)
+    }
+}
+
+/// the postfix without its operand
+impl ToDoc for Postfix {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        match self {
+            // with its operand it is laid out by `trinary`
+            Postfix::Trinary(trinary) => Doc::concat([
+                Doc::text(" ? "),
+                trinary.true_expr.to_doc(context, options),
+                Doc::text(" : "),
+                trinary.false_expr.to_doc(context, options),
+            ]),
+            Postfix::Slice(slice) => {
+                let bound = |expr: &Option<Box<Expr>>| {
+                    expr.as_ref()
+                        .map_or(Doc::Nil, |expr| expr.to_doc(context, options))
+                };
+                let mut docs = vec![
+                    Doc::text("["),
+                    bound(&slice.start),
+                    Doc::text(":"),
+                    bound(&slice.end),
+                ];
+                if let Some(step) = &slice.step {
+                    docs.push(Doc::text(":"));
+                    docs.push(step.to_doc(context, options));
+                }
+                docs.push(Doc::text("]"));
+                Doc::concat(docs)
+            }
+            Postfix::Index(index) => Doc::concat([
+                Doc::text("["),
+                index.postfix_index.to_doc(context, options),
+                Doc::text("]"),
+            ]),
+            Postfix::Access(access) => {
+                Doc::concat([Doc::text("."), access.id.to_doc(context, options)])
+            }
+            Postfix::Length => Doc::text(".|"),
+            Postfix::CAppParams(CAppParams(params)) => {
+                let params = params.as_ref().map_or(&[][..], |params| &params.0[..]);
+                c_params(params, context, options)
+            }
+            Postfix::MlAppParam(param) => {
+                Doc::concat([Doc::text(" "), param.0.to_doc(context, options)])
+            }
+        }
+    }
+}
+
+/// `(a, b)`, `(,)` for no parameter
+fn c_params(params: &[Expr], context: &Context, options: &GlobalOptions) -> Doc {
+    if params.is_empty() {
+        return Doc::text("(,)");
+    }
+    // the grammar only allows a trailing comma after two or more parameters
+    let options = GlobalOptions {
+        always_tailing_comma: options.always_tailing_comma && params.len() > 1,
+        ..options.clone()
+    };
+    let params = params
+        .iter()
+        .map(|param| (&param.diag, param.doc.as_deref(), param));
+    list("(", items(params, false, context, &options), ")", &options)
+}
+
+/// `cond ? t : f`, broken after `?` and `:`
+fn trinary(
+    cond: &Expr,
+    true_expr: &Expr,
+    false_expr: &Expr,
+    context: &Context,
+    options: &GlobalOptions,
+) -> Doc {
+    Doc::group(Doc::concat([
+        cond.to_doc(context, options),
+        Doc::text(" ?"),
+        nest(
+            options,
+            Doc::concat([
+                Doc::Line,
+                true_expr.to_doc(context, options),
+                Doc::text(" :"),
+                Doc::Line,
+                false_expr.to_doc(context, options),
+            ]),
+        ),
+    ]))
+}
+
+/// a bracketed rhs stays on the line of its operator, `f = \x -> {` and
+/// `a = [`, and breaks inside itself
+fn hugs(expr: &Expr) -> bool {
+    match &expr.inner {
+        ExprInner::CApply(_, params) => !params.is_empty(),
+        ExprInner::Primary(primary) => !matches!(primary.inner, Inner::Id(_) | Inner::Error(_)),
+        _ => false,
+    }
+}
+
+/// the rhs of an assignment starts on its line, whatever its layout
+fn is_assign(infix: &Infix) -> bool {
+    matches!(
+        infix,
+        Infix::Assign | Infix::AssignYield | Infix::Set | Infix::MatchEquals | Infix::AssignSlot
+    )
+}
+
+/// every operand of `a op b op c`, whichever way it associates
+fn operands<'a>(expr: &'a Expr, infix: &Infix, out: &mut Vec<&'a Expr>) {
+    match &expr.inner {
+        ExprInner::Infix(op, lhs, rhs) if op == infix => {
+            operands(lhs, infix, out);
+            operands(rhs, infix, out);
+        }
+        _ => out.push(expr),
+    }
+}
+
+/// `a op b op c` as one group, so a `|>` chain breaks after every `|>` or
+/// not at all
+fn infix_chain(
+    infix: &Infix,
+    lhs: &Expr,
+    rhs: &Expr,
+    context: &Context,
+    options: &GlobalOptions,
+) -> Doc {
+    let mut all = vec![];
+    operands(lhs, infix, &mut all);
+    operands(rhs, infix, &mut all);
+    let (first, operands) = all.split_first().unwrap();
+
+    let op = infix.to_doc(context, options);
+    // a comment above the rhs needs the break after the operator
+    if let [rhs] = operands
+        && (hugs(rhs) || is_assign(infix))
+        && !comments::has_inner_leading(context, &rhs.diag)
+    {
+        return Doc::concat([
+            first.to_doc(context, options),
+            Doc::text(" "),
+            op,
+            Doc::text(" "),
+            rhs.to_doc(context, options),
+        ]);
+    }
+    let rest = operands.iter().map(|operand| {
+        Doc::concat([
+            Doc::text(" "),
+            op.clone(),
+            Doc::Line,
+            operand.to_doc(context, options),
+        ])
+    });
+    Doc::group(Doc::concat([
+        first.to_doc(context, options),
+        nest(options, Doc::concat(rest)),
+    ]))
+}
+
+/// the comments above an expr inside an item stay there
+impl ToDoc for Expr {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        // before the operands, which may start where the expr does
+        let comments = comments::inner_leading(context, &self.diag);
+        Doc::concat([comments, expr_doc(self, context, options)])
+    }
+}
+
+fn expr_doc(this: &Expr, context: &Context, options: &GlobalOptions) -> Doc {
+    match &this.inner {
+        ExprInner::Prefix(prefix, expr) => Doc::concat([
+            prefix.to_doc(context, options),
+            expr.to_doc(context, options),
+        ]),
+        ExprInner::Primary(primary) => primary.to_doc(context, options),
+        ExprInner::Postfix(Postfix::Trinary(t), cond) => {
+            trinary(cond, &t.true_expr, &t.false_expr, context, options)
+        }
+        ExprInner::Postfix(postfix, expr) => Doc::concat([
+            expr.to_doc(context, options),
+            postfix.to_doc(context, options),
+        ]),
+        // `a - b` parses as `a (-b)`, only a newline after `-` keeps it infix
+        ExprInner::Infix(Infix::Sub, lhs, rhs) => Doc::concat([
+            lhs.to_doc(context, options),
+            Doc::text(" -"),
+            nest(
+                options,
+                Doc::concat([Doc::HardLine, rhs.to_doc(context, options)]),
+            ),
+        ]),
+        ExprInner::Infix(infix, lhs, rhs) => infix_chain(infix, lhs, rhs, context, options),
+        ExprInner::CApply(expr, params) => Doc::concat([
+            expr.to_doc(context, options),
+            c_params(params, context, options),
+        ]),
+        // `f(x)` in C style, see `rewrite`
+        ExprInner::MLApply(expr, params)
+            if options.function_application_style == FunctionApplicationStyle::CStyle
+                && matches!(
+                    &params[..],
+                    [Expr {
+                        inner: ExprInner::Primary(Primary {
+                            inner: Inner::ParenExpr(_),
+                            ..
+                        }),
+                        ..
+                    }]
+                ) =>
+        {
+            Doc::concat([
+                expr.to_doc(context, options),
+                params[0].to_doc(context, options),
+            ])
+        }
+        // no newline is allowed between the parameters, only inside them
+        ExprInner::MLApply(expr, params) => {
+            let mut docs = vec![expr.to_doc(context, options)];
+            for param in params {
+                docs.push(Doc::text(" "));
+                docs.push(param.to_doc(context, options));
+            }
+            Doc::concat(docs)
+        }
+    }
+}
+
+impl ToDoc for Primary {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        match &self.inner {
+            // the source of a broken region is not kept, programs with
+            // errors are not formatted
+            Inner::Error(_) => Doc::Nil,
+            Inner::Block(block) => block.to_doc(context, options),
+            Inner::LambdaExpr(lambda_expr) => lambda_expr.to_doc(context, options),
+            Inner::Hole(hole) => hole.to_doc(context, options),
+            Inner::ParenExpr(paren_expr) => paren_expr.to_doc(context, options),
+            Inner::CompoundLiteral(compound_literal) => compound_literal.to_doc(context, options),
+            Inner::Id(id) => id.to_doc(context, options),
+            Inner::Pattern(pattern) => pattern.to_doc(context, options),
+        }
+    }
+}
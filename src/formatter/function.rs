@@ -0,0 +1,111 @@
+use crate::{
+    expr::Expr,
+    function::{Guard, ImplicitParams, LambdaExpr, NoParamLambdaExpr, TrLambda},
+    quotations::{Block, ParenExpr},
+};
+
+use super::{Context, GlobalOptions, ToDoc, doc::Doc, items, nest};
+
+/// `open a; b }` on one line, or every statement on its own line
+fn stmts(open: &str, exprs: &[Expr], context: &Context, options: &GlobalOptions) -> Doc {
+    let exprs = exprs
+        .iter()
+        .map(|expr| (&expr.diag, expr.doc.as_deref(), expr));
+    let (exprs, dangling) = items(exprs, true, context, options);
+    Doc::group(Doc::concat([
+        Doc::text(open),
+        nest(
+            options,
+            Doc::concat([
+                Doc::Line,
+                Doc::join(
+                    exprs,
+                    Doc::concat([Doc::if_break(Doc::Nil, Doc::text(";")), Doc::Line]),
+                ),
+                dangling,
+            ]),
+        ),
+        Doc::Line,
+        Doc::text("}"),
+    ]))
+}
+
+impl ToDoc for Block {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        stmts("{", &self.exprs, context, options)
+    }
+}
+
+impl ToDoc for NoParamLambdaExpr {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        stmts("_{", &self.body, context, options)
+    }
+}
+
+impl ToDoc for ParenExpr {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        Doc::concat([
+            Doc::text("("),
+            self.expr.to_doc(context, options),
+            Doc::text(")"),
+        ])
+    }
+}
+
+impl ToDoc for ImplicitParams {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        let params = self
+            .params
+            .iter()
+            .map(|param| param.to_doc(context, options));
+        Doc::concat([Doc::text("?"), Doc::join(params, Doc::text(" "))])
+    }
+}
+
+impl ToDoc for Guard {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        Doc::concat([Doc::text(": "), self.expr.to_doc(context, options)])
+    }
+}
+
+impl ToDoc for TrLambda {
+    /// `\a b ? c : guard -> body`, no newline is allowed before the body
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        let mut parts: Vec<_> = self
+            .patterns
+            .iter()
+            .map(|pattern| pattern.to_doc(context, options))
+            .collect();
+        parts.extend(
+            self.implicit_params
+                .iter()
+                .map(|params| params.to_doc(context, options)),
+        );
+        parts.extend(
+            self.guard
+                .iter()
+                .map(|guard| guard.to_doc(context, options)),
+        );
+        let head = if parts.is_empty() {
+            Doc::text("\\-> ")
+        } else {
+            Doc::concat([
+                Doc::text("\\"),
+                Doc::join(parts, Doc::text(" ")),
+                Doc::text(" -> "),
+            ])
+        };
+        Doc::concat([head, self.body.to_doc(context, options)])
+    }
+}
+
+impl ToDoc for LambdaExpr {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        match self {
+            LambdaExpr::TrLambda(tr_lambda) => tr_lambda.to_doc(context, options),
+            LambdaExpr::NoParamLambdaExpr(no_param_lambda_expr) => {
+                no_param_lambda_expr.to_doc(context, options)
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use crate::literal::{
+    Boolean, CompoundLiteral, Inner, Literal, Slot, Void,
+    array::ArrayBody,
+    number::{BigInt, Float, Int, SapNumber},
+    object::{ObjectBody, ObjectElemKv, ObjectKey},
+    string::{NormalString, RawString, SapString},
+};
+
+use super::{Context, GlobalOptions, ToDoc, doc::Doc, items, list};
+
+impl ToDoc for Boolean {
+    fn to_doc(&self, _context: &Context, _options: &GlobalOptions) -> Doc {
+        Doc::text(self.value.to_string())
+    }
+}
+
+impl ToDoc for Void {
+    fn to_doc(&self, _context: &Context, _options: &GlobalOptions) -> Doc {
+        Doc::text("()")
+    }
+}
+
+impl ToDoc for Slot {
+    fn to_doc(&self, _context: &Context, _options: &GlobalOptions) -> Doc {
+        Doc::text("<>")
+    }
+}
+
+impl ToDoc for NormalString {
+    fn to_doc(&self, _context: &Context, _options: &GlobalOptions) -> Doc {
+        Doc::Text(self.to_source())
+    }
+}
+
+impl ToDoc for RawString {
+    fn to_doc(&self, _context: &Context, _options: &GlobalOptions) -> Doc {
+        Doc::Text(self.to_source())
+    }
+}
+
+impl ToDoc for SapString {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        match self {
+            SapString::NormalString(normal_string) => normal_string.to_doc(context, options),
+            SapString::RawString(raw_string) => raw_string.to_doc(context, options),
+        }
+    }
+}
+
+impl ToDoc for Int {
+    fn to_doc(&self, _context: &Context, _options: &GlobalOptions) -> Doc {
+        Doc::text(self.value().to_string())
+    }
+}
+
+/// a promoted literal gets its `n`, so it reads back without promotion
+impl ToDoc for BigInt {
+    fn to_doc(&self, _context: &Context, _options: &GlobalOptions) -> Doc {
+        Doc::text(format!("{}n", self.value))
+    }
+}
+
+/// a float is decimal, it is kept as written
+impl ToDoc for Float {
+    fn to_doc(&self, _context: &Context, _options: &GlobalOptions) -> Doc {
+        Doc::text(self.lexeme())
+    }
+}
+
+impl ToDoc for SapNumber {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        match self {
+            SapNumber::Float(float) => float.to_doc(context, options),
+            SapNumber::BigInt(big_int) => big_int.to_doc(context, options),
+            SapNumber::Int(int) => int.to_doc(context, options),
+        }
+    }
+}
+
+impl ToDoc for Literal {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        match &self.inner {
+            Inner::Boolean(boolean) => boolean.to_doc(context, options),
+            Inner::Void(void) => void.to_doc(context, options),
+            Inner::String(string) => string.to_doc(context, options),
+            Inner::Number(number) => number.to_doc(context, options),
+            Inner::Slot(slot) => slot.to_doc(context, options),
+        }
+    }
+}
+
+impl ToDoc for ArrayBody {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        let elems = self
+            .elems
+            .iter()
+            .map(|elem| (&elem.diag, elem.doc.as_deref(), elem));
+        list("[", items(elems, false, context, options), "]", options)
+    }
+}
+
+impl ToDoc for ObjectKey {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        match self {
+            ObjectKey::Id(id) => id.to_doc(context, options),
+            ObjectKey::String(string) => string.to_doc(context, options),
+        }
+    }
+}
+
+impl ToDoc for ObjectElemKv {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        let key = self.key.to_doc(context, options);
+        match &self.value {
+            Some(value) => Doc::concat([key, Doc::text(": "), value.to_doc(context, options)]),
+            None => key,
+        }
+    }
+}
+
+impl ToDoc for ObjectBody {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        let elems = self
+            .body
+            .iter()
+            .map(|elem| (&elem.diag, elem.doc.as_deref(), elem));
+        list("{", items(elems, false, context, options), "}", options)
+    }
+}
+
+impl ToDoc for CompoundLiteral {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        match self {
+            CompoundLiteral::ArrayLiteral(Some(array_body)) => array_body.to_doc(context, options),
+            CompoundLiteral::ObjectLiteral(Some(object_body)) => {
+                object_body.to_doc(context, options)
+            }
+            CompoundLiteral::ArrayLiteral(None) => Doc::text("[]"),
+            CompoundLiteral::ObjectLiteral(None) => Doc::text("{}"),
+            CompoundLiteral::Literal(literal) => literal.to_doc(context, options),
+        }
+    }
+}
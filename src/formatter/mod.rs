@@ -0,0 +1,435 @@
+pub mod comments;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod doc;
+mod expr;
+mod function;
+mod literal;
+mod pattern;
+pub mod range;
+pub mod rewrite;
+#[cfg(feature = "verify")]
+pub mod verify;
+
+use crate::{
+    ParseError,
+    diagnostics::{Diagnostic, SameShape},
+    parse_file,
+    program::Program,
+};
+
+use comments::Comments;
+use doc::Doc;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FunctionApplicationStyle {
+    CStyle,
+    #[serde(rename = "ml_style")]
+    MLStyle,
+    /// leave every application as written
+    Preserve,
+}
+
+/// also the keys of `sapfmt.toml`, see `config`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GlobalOptions {
+    pub indent_size: u32,
+    pub screen_width: u32,
+
+    pub function_application_style: FunctionApplicationStyle,
+
+    /// transform small postfix_index `a["b"]` to `a.b`
+    pub small_postfix_index_to_postfix_access: bool,
+
+    /// transform small block `\x -> { x }` to `\x -> x`
+    pub small_block_to_inline: bool,
+
+    /// transform lambda with out parameter to no_parameter lambda
+    /// `\->{ x }` to `_{ x }`
+    pub lambda_no_parameter: bool,
+
+    /// [1,2,3] -> [1,2,3,]
+    /// {a:1,b:2} -> {a:1,b:2,}
+    pub always_tailing_comma: bool,
+}
+
+impl Default for GlobalOptions {
+    fn default() -> Self {
+        Self {
+            indent_size: 4,
+            screen_width: 100,
+            function_application_style: FunctionApplicationStyle::Preserve,
+            small_postfix_index_to_postfix_access: false,
+            small_block_to_inline: false,
+            lambda_no_parameter: false,
+            always_tailing_comma: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Context<'a> {
+    pub indent_level: u32,
+    /// the comments of the source, none are printed without it
+    pub comments: Option<&'a Comments<'a>>,
+}
+
+impl<'a> Context<'a> {
+    pub fn indented(&self) -> Context<'a> {
+        Context {
+            indent_level: self.indent_level + 1,
+            ..*self
+        }
+    }
+
+    pub fn indent(&self, options: &GlobalOptions) -> String {
+        " ".repeat((self.indent_level * options.indent_size) as usize)
+    }
+}
+
+pub trait PrettyPrint {
+    fn pretty_print(&self, context: &Context, options: &GlobalOptions) -> String;
+    /// for list, object, block, if, op,
+    /// return None if it can not be multiline
+    fn multiline_pretty_print(
+        &self,
+        _context: &Context,
+        _options: &GlobalOptions,
+    ) -> Option<String> {
+        None
+    }
+}
+
+/// the layout of a node, `PrettyPrint` renders it at `screen_width`
+pub trait ToDoc {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc;
+}
+
+impl<T: ToDoc + ?Sized> PrettyPrint for T {
+    fn pretty_print(&self, context: &Context, options: &GlobalOptions) -> String {
+        self.to_doc(context, options)
+            .render(options.screen_width as usize, context.indent(options).len())
+    }
+
+    /// every group broken
+    fn multiline_pretty_print(&self, context: &Context, options: &GlobalOptions) -> Option<String> {
+        let out = self
+            .to_doc(context, options)
+            .render(0, context.indent(options).len());
+        out.contains('\n').then_some(out)
+    }
+}
+
+/// format a whole file
+pub fn format(source: &str, options: &GlobalOptions) -> Result<String, ParseError> {
+    let mut program = parse_file(source)?;
+    rewrite::rewrite(&mut program, options);
+    let comments = Comments::new(source);
+    let context = Context {
+        comments: Some(&comments),
+        ..Default::default()
+    };
+    Ok(program.pretty_print(&context, options))
+}
+
+/// the programs have the same doc headings and statements, wherever they are
+/// in the source and whatever separates them
+pub fn same_ast(a: &Program, b: &Program) -> bool {
+    a.doc_headings == b.doc_headings
+        && a.stmts.len() == b.stmts.len()
+        && a.stmts
+            .iter()
+            .zip(&b.stmts)
+            .all(|(a, b)| a.expr.same_shape(&b.expr))
+}
+
+fn nest(options: &GlobalOptions, doc: Doc) -> Doc {
+    Doc::nest(options.indent_size as usize, doc)
+}
+
+/// every line of `doc` prefixed by `marker`, each followed by a newline
+fn doc_comment(marker: &str, doc: &str) -> Doc {
+    Doc::concat(doc.lines().map(|line| {
+        let line = if line.is_empty() {
+            marker.to_string()
+        } else {
+            format!("{} {}", marker, line)
+        };
+        Doc::concat([Doc::text(line), Doc::HardLine])
+    }))
+}
+
+/// the elems of a list or block with their docs and comments, and the
+/// comments after the last one
+///
+/// with `blank_lines` a blank line between two elems in the source is kept
+fn items<'a, T: ToDoc + 'a>(
+    elems: impl IntoIterator<Item = (&'a Diagnostic, Option<&'a str>, &'a T)>,
+    blank_lines: bool,
+    context: &Context,
+    options: &GlobalOptions,
+) -> (Vec<Doc>, Doc) {
+    let mut prev = None;
+    let mut docs = vec![];
+    for (diag, doc, node) in elems {
+        let (doc, end) = comments::commented(context, diag, doc, prev, blank_lines, || {
+            node.to_doc(context, options)
+        });
+        docs.push(doc);
+        prev = Some(end);
+    }
+    let dangling = prev.map_or(Doc::Nil, |prev| {
+        comments::dangling(context, prev, blank_lines)
+    });
+    (docs, dangling)
+}
+
+/// `open elem, elem close`, or every elem on its own line if it does not fit
+///
+/// `dangling` goes after the last elem, it breaks the list if not empty
+fn list(
+    open: &str,
+    (elems, dangling): (Vec<Doc>, Doc),
+    close: &str,
+    options: &GlobalOptions,
+) -> Doc {
+    let comma = if options.always_tailing_comma && !elems.is_empty() {
+        Doc::text(",")
+    } else {
+        Doc::Nil
+    };
+    Doc::group(Doc::concat([
+        Doc::text(open),
+        nest(
+            options,
+            Doc::concat([
+                Doc::SoftLine,
+                Doc::join(elems, Doc::concat([Doc::text(","), Doc::Line])),
+                comma,
+                dangling,
+            ]),
+        ),
+        Doc::SoftLine,
+        Doc::text(close),
+    ]))
+}
+
+/// the `//!` headings and the rest of the file, a blank line between them
+impl ToDoc for Program {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        let mut blocks: Vec<_> = self
+            .doc_headings
+            .iter()
+            .map(|heading| doc_comment("//!", heading))
+            .collect();
+        let stmts = self
+            .stmts
+            .iter()
+            .map(|stmt| (&stmt.expr.diag, stmt.expr.doc.as_deref(), &stmt.expr));
+        let (stmts, dangling) = items(stmts, true, context, options);
+        if !stmts.is_empty() {
+            blocks.push(Doc::concat([
+                Doc::join(stmts, Doc::HardLine),
+                dangling,
+                Doc::HardLine,
+            ]));
+        } else if let Doc::Concat(lines) = comments::dangling(context, 0, true) {
+            // all comments of the file, with no newline before the first
+            let lines: Vec<_> = lines
+                .into_iter()
+                .skip_while(|line| matches!(line, Doc::HardLine))
+                .collect();
+            if !lines.is_empty() {
+                blocks.push(Doc::concat([Doc::concat(lines), Doc::HardLine]));
+            }
+        }
+        Doc::join(blocks, Doc::HardLine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preprocessor::comment::classify;
+
+    fn options() -> GlobalOptions {
+        GlobalOptions {
+            indent_size: 2,
+            screen_width: 40,
+            ..Default::default()
+        }
+    }
+
+    /// format, check that the output reparses to the same program and
+    /// formats to itself
+    fn check(source: &str, options: &GlobalOptions) -> String {
+        let out = format(source, options).unwrap_or_else(|e| panic!("{}\n{}", e, source));
+        let before = parse_file(source).unwrap();
+        let after = parse_file(&out).unwrap_or_else(|e| panic!("{}\n{}", e, out));
+        assert!(
+            same_ast(&before, &after),
+            "{}\n{:#?}\n{:#?}",
+            out,
+            before,
+            after
+        );
+        assert_eq!(format(&out, options).unwrap(), out);
+        // no comment is dropped or printed twice
+        assert_eq!(
+            classify(&out).len(),
+            classify(source).len(),
+            "{}\n{}",
+            source,
+            out
+        );
+        out
+    }
+
+    #[test]
+    fn test_format_corpus() {
+        let corpus = [
+            "a = 1; b = 0x10 + 2 * 3; c = 0xFFFF_FFFF_FFFF_FFFF_FFn",
+            "f a b\ng(1, 2)\nh(,)\nk (1) ~max~ 2",
+            "x = !a && -b || ~c\ny = <-z",
+            "@@export f = \\x y ? i : x > 0 -> x\ng = \\-> 1",
+            "a.b[1][1:2][::3][:]\nc ? d : e",
+            "xs |> map \\x -> x * 2 |> filter \\x -> x > 1",
+            "s = \"a\\n\\\"b\\\\\" + r#\"c\"d\"#",
+            "n = [1.5, 1.05, 2e-3, .5, 1_0.0_5E+2, true, false, ()]",
+            "o = {a: 1, \"b c\": [1, 2], d}",
+            "^[a, ...b] = xs\n^{c: ^[d], ...e} = y",
+            "_{ a; b }\n{ c\n d }",
+            "f = \\^{a, b: 1} 2 \"s\" -> { a }",
+            "(+)\n@x",
+            "x = [] {} {k: [], j: 1}\n\\^[] ^{} -> { [] }; _{ ^[] }",
+        ];
+        for source in corpus {
+            check(source, &options());
+        }
+    }
+
+    #[test]
+    fn test_format_breaks_long_lines() {
+        let source = "result = some_function(argument_one, argument_two, argument_three)";
+        assert_eq!(
+            check(source, &options()),
+            "result = some_function(\n  argument_one,\n  argument_two,\n  argument_three\n)\n"
+        );
+        let source = "o = {first: 1, second: [1, 2, 3], third: 3333333}";
+        assert_eq!(
+            check(source, &options()),
+            "o = {\n  first: 1,\n  second: [1, 2, 3],\n  third: 3333333\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_format_pipe_and_trinary_chains() {
+        let source = "out = xs |> map double |> filter (\\x -> x > 1) |> sum";
+        assert_eq!(
+            check(source, &options()),
+            "out = xs |>\n  map double |>\n  filter (\\x -> x > 1) |>\n  sum\n"
+        );
+        let source = "sign = n > 0 ? positive : n < 0 ? negative : zero";
+        assert_eq!(
+            check(source, &options()),
+            "sign = n > 0 ?\n  positive :\n  n < 0 ? negative : zero\n"
+        );
+        let source = "a = [[1111111111, 2222222222], [3333333333, 4444444444]]";
+        assert_eq!(
+            check(source, &options()),
+            "a = [\n  [1111111111, 2222222222],\n  [3333333333, 4444444444]\n]\n"
+        );
+    }
+
+    #[test]
+    fn test_format_indent_and_trailing_comma() {
+        let options = GlobalOptions {
+            indent_size: 4,
+            screen_width: 20,
+            always_tailing_comma: true,
+            ..Default::default()
+        };
+        assert_eq!(check("[1, 2]", &options), "[1, 2,]\n");
+        assert_eq!(
+            check("f = \\x -> { a = x; a }", &options),
+            "f = \\x -> {\n    a = x\n    a\n}\n"
+        );
+        assert_eq!(
+            check("c ? aaaaaaaaaa : bbbbbbbbbbb", &options),
+            "c ?\n    aaaaaaaaaa :\n    bbbbbbbbbbb\n"
+        );
+    }
+
+    #[test]
+    fn test_format_docs() {
+        let source = "//! title\n/// doc\na = {\n/// inner\n b }; o = {\n/// key\nk: 1}";
+        assert_eq!(
+            check(source, &options()),
+            "//! title\n\n/// doc\na = {\n  /// inner\n  b\n}\no = {\n  /// key\n  k: 1\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_format_comments() {
+        let source = "// leading\na = 1 // trailing\n\n\n// about b\n\nb = {\n// first\n x = 1 // x\n\n y // y\n // end\n}\n// end of file";
+        assert_eq!(
+            check(source, &options()),
+            "// leading\na = 1 // trailing\n\n// about b\n\nb = {\n  // first\n  x = 1 // x\n\n  y // y\n  // end\n}\n// end of file\n"
+        );
+        let source = "o = {\n// key\nk: 1, // one\nm: 2\n// after m\n}";
+        assert_eq!(
+            check(source, &options()),
+            "o = {\n  // key\n  k: 1, // one\n  m: 2\n  // after m\n}\n"
+        );
+        let source = "r = f(a, // first\n// before b\nb)";
+        assert_eq!(
+            check(source, &options()),
+            "r = f(\n  a, // first\n  // before b\n  b\n)\n"
+        );
+    }
+
+    #[test]
+    fn test_format_comments_inside_expr() {
+        // a comment above an operand stays there
+        let source = "c = // inner\n 2\n/// doc\nd = c ? // why\n 1 : 2";
+        assert_eq!(
+            check(source, &options()),
+            "c =\n  // inner\n  2\n/// doc\nd = c ?\n  // why\n  1 :\n  2\n"
+        );
+        let source = "r = a +\n    // explain b\n    b";
+        assert_eq!(check(source, &options()), "r = a +\n  // explain b\n  b\n");
+        // the `///` documents the operand, not the statement
+        let source = "x = c ?\n    /// yes\n    a : b";
+        assert_eq!(
+            check(source, &options()),
+            "x = c ?\n  /// yes\n  a :\n  b\n"
+        );
+        // one with no expr below it moves above as a `//`
+        let source = "f = \\^[a,\n/// x\nb] -> a";
+        assert_eq!(check(source, &options()), "// x\nf = \\^[a, b] -> a\n");
+        let source = "^{\n/// a\na: 1, // one\n...r} = x";
+        assert_eq!(
+            check(source, &options()),
+            "^{\n  /// a\n  a: 1, // one\n  ...r\n} = x\n"
+        );
+        let source = "// only\n\n// comments";
+        assert_eq!(check(source, &options()), "// only\n\n// comments\n");
+    }
+
+    #[test]
+    fn test_format_headings_without_stmts() {
+        let cases = [
+            ("//! h\n// c\n", "//! h\n\n// c\n"),
+            ("//! h\n/// d\n", "//! h\n\n/// d\n"),
+            ("//! h\n", "//! h\n"),
+            ("//! a\n\n//! b", "//! a\n\n//! b\n"),
+            ("\n\n// c\n\n// d", "// c\n\n// d\n"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(check(source, &options()), expected, "{source:?}");
+        }
+    }
+}
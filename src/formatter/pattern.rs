@@ -0,0 +1,74 @@
+use crate::pattern::{
+    EclipsePattern, Pattern,
+    array::{ArrayPattern, ArrayPatternElem},
+    object::{ObjectPattern, ObjectPatternElem, ObjectPatternKv},
+};
+
+use super::{Context, GlobalOptions, ToDoc, doc::Doc, items, list};
+
+impl ToDoc for EclipsePattern {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        Doc::concat([Doc::text("..."), self.value.to_doc(context, options)])
+    }
+}
+
+impl ToDoc for ArrayPatternElem {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        match self {
+            ArrayPatternElem::EclipsePattern(eclipse) => eclipse.to_doc(context, options),
+            ArrayPatternElem::Pattern(pattern) => pattern.to_doc(context, options),
+        }
+    }
+}
+
+impl ToDoc for ArrayPattern {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        let elems = self
+            .elems()
+            .iter()
+            .map(|elem| elem.to_doc(context, options))
+            .collect();
+        list("^[", (elems, Doc::Nil), "]", options)
+    }
+}
+
+impl ToDoc for ObjectPatternKv {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        let key = self.key.to_doc(context, options);
+        match &self.value {
+            Some(value) => Doc::concat([key, Doc::text(": "), value.to_doc(context, options)]),
+            None => key,
+        }
+    }
+}
+
+/// the elem without its doc, see `ObjectPattern`
+impl ToDoc for ObjectPatternElem {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        match self {
+            ObjectPatternElem::ObjectPatternKv(kv) => kv.to_doc(context, options),
+            ObjectPatternElem::EclipsePattern(eclipse) => eclipse.to_doc(context, options),
+        }
+    }
+}
+
+impl ToDoc for ObjectPattern {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        let elems = self.elems().iter().map(|elem| match elem {
+            ObjectPatternElem::ObjectPatternKv(kv) => (&kv.diag, kv.doc.as_deref(), elem),
+            ObjectPatternElem::EclipsePattern(eclipse) => (&eclipse.diag, None, elem),
+        });
+        list("^{", items(elems, false, context, options), "}", options)
+    }
+}
+
+impl ToDoc for Pattern {
+    fn to_doc(&self, context: &Context, options: &GlobalOptions) -> Doc {
+        match self {
+            Pattern::Id(id) => id.to_doc(context, options),
+            Pattern::Literal(literal) => literal.to_doc(context, options),
+            Pattern::ArrayPattern(array_pattern) => array_pattern.to_doc(context, options),
+            Pattern::ObjectPattern(object_pattern) => object_pattern.to_doc(context, options),
+        }
+    }
+}
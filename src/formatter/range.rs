@@ -0,0 +1,200 @@
+//! formatting a selection, as edits of the source rather than a new file
+
+use std::ops::Range;
+
+use crate::{
+    ParseError, diagnostics::Diagnostic, parse_file, parse_file_recovering, program::Program,
+};
+
+use super::{GlobalOptions, format};
+
+/// replace `range` of the source, byte offsets, with `new_text`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub new_text: String,
+}
+
+/// apply `edits`, sorted and not overlapping, to `source`
+pub fn apply_edits(source: &str, edits: &[TextEdit]) -> String {
+    let mut out = String::new();
+    let mut pos = 0;
+    for edit in edits {
+        out.push_str(&source[pos..edit.range.start]);
+        out.push_str(&edit.new_text);
+        pos = edit.range.end;
+    }
+    out.push_str(&source[pos..]);
+    out
+}
+
+/// where the text of statement `i` starts: after the line the statement
+/// before it ends on, or at the statement if it shares that line. The
+/// comments above a statement belong to it, the one after it on its line
+/// to the statement before
+fn boundary(source: &str, program: &Program, i: usize) -> usize {
+    let (Some(prev), Some(stmt)) = (
+        i.checked_sub(1).and_then(|i| program.stmts.get(i)),
+        program.stmts.get(i),
+    ) else {
+        return if i == 0 { 0 } else { source.len() };
+    };
+    let between = &source[prev.diag.end_offset..stmt.diag.start_offset];
+    match between.find('\n') {
+        Some(newline) => prev.diag.end_offset + newline + 1,
+        None => stmt.diag.start_offset,
+    }
+}
+
+/// the edits formatting the statements that overlap `range`, sorted
+///
+/// the file is parsed with recovery, so a broken statement outside the
+/// selection is left alone. Only the text of the selected statements, with
+/// their comments, is formatted, together with the statement before them
+/// which decides the blank lines above the first one
+pub fn format_range(
+    source: &str,
+    range: Range<usize>,
+    options: &GlobalOptions,
+) -> Result<Vec<TextEdit>, ParseError> {
+    let (program, errors) = parse_file_recovering(source);
+    let overlapping: Vec<_> = (0..program.stmts.len())
+        .filter(|&i| {
+            let diag = &program.stmts[i].diag;
+            // an empty range is a cursor, it selects the statement it is in
+            diag.start_offset < range.end.max(range.start + 1) && range.start <= diag.end_offset
+        })
+        .collect();
+    let (Some(&first), Some(&last)) = (overlapping.first(), overlapping.last()) else {
+        return Ok(vec![]);
+    };
+    let old = boundary(source, &program, first)..boundary(source, &program, last + 1);
+    let broken = |range: &Range<usize>| {
+        errors.iter().find(|error| {
+            error.diag.start_offset < range.end && range.start <= error.diag.start_offset
+        })
+    };
+    if let Some(error) = broken(&old) {
+        return Err(error.clone());
+    }
+    let context = first
+        .checked_sub(1)
+        .map(|prev| boundary(source, &program, prev))
+        .filter(|&start| broken(&(start..old.start)).is_none());
+    let start = context.unwrap_or(old.start);
+    let formatted = format(&source[start..old.end], options).map_err(|mut error| {
+        error.diag = Diagnostic::from_offsets(
+            source,
+            start + error.diag.start_offset,
+            start + error.diag.end_offset,
+        );
+        error
+    })?;
+    let formatted_program = parse_file(&formatted)?;
+    let skip = usize::from(context.is_some());
+    // formatting never adds or removes a statement, see `verify`
+    let (old, new) = if formatted_program.stmts.len() == last + 1 - first + skip {
+        (
+            old,
+            boundary(&formatted, &formatted_program, skip)..formatted.len(),
+        )
+    } else {
+        (start..old.end, 0..formatted.len())
+    };
+    Ok(minimal_edit(source, old, &formatted[new])
+        .into_iter()
+        .collect())
+}
+
+/// the edit replacing `range` of `source` with `new_text`, without the text
+/// they start or end with both
+fn minimal_edit(source: &str, range: Range<usize>, new_text: &str) -> Option<TextEdit> {
+    let old_text = &source[range.clone()];
+    if old_text == new_text {
+        return None;
+    }
+    let prefix: usize = old_text
+        .chars()
+        .zip(new_text.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum();
+    let suffix: usize = old_text[prefix..]
+        .chars()
+        .rev()
+        .zip(new_text[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum();
+    Some(TextEdit {
+        range: range.start + prefix..range.end - suffix,
+        new_text: new_text[prefix..new_text.len() - suffix].to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format_selection(source: &str, selected: &str) -> String {
+        let start = source.find(selected).unwrap();
+        let edits = format_range(
+            source,
+            start..start + selected.len(),
+            &GlobalOptions::default(),
+        )
+        .unwrap();
+        apply_edits(source, &edits)
+    }
+
+    #[test]
+    fn test_format_range() {
+        let source = "a=1\nb=[1,\n2]\nc=3\n";
+        assert_eq!(format_selection(source, "b"), "a=1\nb = [1, 2]\nc=3\n");
+        assert_eq!(
+            format_selection(source, "2]\nc"),
+            "a=1\nb = [1, 2]\nc = 3\n"
+        );
+        assert_eq!(
+            format_selection(source, source),
+            "a = 1\nb = [1, 2]\nc = 3\n"
+        );
+        assert_eq!(format_selection("a=1; b=2", "b"), "a=1; b = 2\n");
+        // a cursor is an empty range
+        let start = source.find('c').unwrap();
+        let edits = format_range(source, start..start, &GlobalOptions::default()).unwrap();
+        assert_eq!(
+            edits,
+            [TextEdit {
+                range: start + 1..start + 2,
+                new_text: " = ".to_string()
+            }]
+        );
+        // formatted already
+        let edits = format_range("a = 1\nb=2", 0..1, &GlobalOptions::default()).unwrap();
+        assert!(edits.is_empty());
+    }
+
+    #[test]
+    fn test_format_range_keeps_comments() {
+        let source = "// a\na=1 // one\n\n\n// b\nb=2";
+        assert_eq!(
+            format_selection(source, "a=1"),
+            "// a\na = 1 // one\n\n\n// b\nb=2"
+        );
+        assert_eq!(
+            format_selection(source, "b=2"),
+            "// a\na=1 // one\n\n// b\nb = 2\n"
+        );
+    }
+
+    #[test]
+    fn test_format_range_around_broken_statement() {
+        let source = "a=1\nb=)\nc=3\n";
+        assert_eq!(format_selection(source, "c"), "a=1\nb=)\nc = 3\n");
+        assert_eq!(format_selection(source, "a"), "a = 1\nb=)\nc=3\n");
+        let start = source.find('b').unwrap();
+        let error = format_range(source, start..start, &GlobalOptions::default()).unwrap_err();
+        assert_eq!(error.diag.start_line, 2);
+    }
+}
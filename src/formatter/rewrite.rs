@@ -0,0 +1,514 @@
+//! AST to AST rewrites run before printing, every rewrite keeps the meaning
+//! of the program: parentheses are decided from `operator_precedence::LEVELS`
+//! and a rewrite is refused where no parentheses would help. A statement is
+//! only rewritten where it prints to something that parses back to the
+//! rewritten tree, a rewrite the statement around it would read otherwise,
+//! like `f x y` to `f(x, y)` in `a + f x y == z`, is left out
+
+use pest::Parser;
+
+use crate::{
+    Rule, SapParser,
+    diagnostics::SameShape,
+    expr::{
+        Expr, ExprInner, Inner, Primary,
+        infix::Infix,
+        postfix::{Access, Postfix},
+    },
+    function::{LambdaExpr, NoParamLambdaExpr, TrLambda},
+    id::{Id, NormalId},
+    literal::{self, CompoundLiteral, Literal},
+    parse_expr,
+    program::Program,
+    quotations::ParenExpr,
+    unparse::ml_param_needs_paren,
+    visit::{self, VisitMut},
+};
+
+use super::{Context, FunctionApplicationStyle, GlobalOptions, PrettyPrint};
+
+/// run every rewrite `options` asks for
+pub fn rewrite(program: &mut Program, options: &GlobalOptions) {
+    if options.function_application_style != FunctionApplicationStyle::Preserve {
+        function_application_style(program, options);
+    }
+    if options.small_postfix_index_to_postfix_access {
+        small_postfix_index_to_postfix_access(program, options);
+    }
+    // before inlining, which would leave no block to turn into `_{`
+    if options.lambda_no_parameter {
+        lambda_no_parameter(program, options);
+    }
+    if options.small_block_to_inline {
+        small_block_to_inline(program, options);
+    }
+}
+
+/// `f(a, b)` to `f a b` and back, as `function_application_style` says
+pub fn function_application_style(program: &mut Program, options: &GlobalOptions) {
+    for_each_rewrite(
+        program,
+        options,
+        |expr| application_style(expr, options),
+        paren_tight_operands,
+    );
+}
+
+/// `a["b"]` to `a.b` when the key is an id
+pub fn small_postfix_index_to_postfix_access(program: &mut Program, options: &GlobalOptions) {
+    for_each_rewrite(
+        program,
+        options,
+        |expr| {
+            let ExprInner::Postfix(Postfix::Index(index), _) = &expr.inner else {
+                return false;
+            };
+            let Some(id) = string_key(&index.postfix_index) else {
+                return false;
+            };
+            if let ExprInner::Postfix(postfix, _) = &mut expr.inner {
+                *postfix = Postfix::Access(Access {
+                    id: Id::NormalId(NormalId { value: id }),
+                });
+            }
+            true
+        },
+        |_| {},
+    );
+}
+
+/// `\x -> { x }` to `\x -> x`
+pub fn small_block_to_inline(program: &mut Program, options: &GlobalOptions) {
+    for_each_rewrite(
+        program,
+        options,
+        |expr| {
+            let Some(LambdaExpr::TrLambda(tr_lambda)) = lambda_mut(expr) else {
+                return false;
+            };
+            let Some(stmt) = small_block(&tr_lambda.body) else {
+                return false;
+            };
+            *tr_lambda.body = stmt.clone();
+            true
+        },
+        |_| {},
+    );
+}
+
+/// `\-> { x }` to `_{ x }`
+pub fn lambda_no_parameter(program: &mut Program, options: &GlobalOptions) {
+    for_each_rewrite(
+        program,
+        options,
+        |expr| {
+            let Some(lambda) = lambda_mut(expr) else {
+                return false;
+            };
+            let LambdaExpr::TrLambda(TrLambda {
+                patterns,
+                implicit_params: None,
+                guard: None,
+                body:
+                    box Expr {
+                        inner:
+                            ExprInner::Primary(Primary {
+                                inner: Inner::Block(block),
+                                ..
+                            }),
+                        doc: None,
+                        ..
+                    },
+            }) = lambda
+            else {
+                return false;
+            };
+            if !patterns.is_empty() {
+                return false;
+            }
+            *lambda = LambdaExpr::NoParamLambdaExpr(NoParamLambdaExpr {
+                body: std::mem::take(&mut block.exprs),
+            });
+            true
+        },
+        |_| {},
+    );
+}
+
+/// calls its closure on every expr, children before their parent
+struct PostOrder<F>(F);
+
+impl<F: FnMut(&mut Expr)> VisitMut for PostOrder<F> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        visit::visit_expr_mut(self, expr);
+        (self.0)(expr);
+    }
+}
+
+/// run `rewrite` on every expr, it returns whether it changed the expr, and
+/// `fixup` after it on every expr, to parenthesize what a rewrite of a child
+/// asks for. A statement the rewrites would make read otherwise is rewritten
+/// again one expr at a time, leaving out the rewrites that do
+fn for_each_rewrite(
+    program: &mut Program,
+    options: &GlobalOptions,
+    mut rewrite: impl FnMut(&mut Expr) -> bool,
+    mut fixup: impl FnMut(&mut Expr),
+) {
+    for stmt in &mut program.stmts {
+        let mut rewritten = stmt.expr.clone();
+        let mut changed = false;
+        PostOrder(|expr: &mut Expr| {
+            changed |= rewrite(expr);
+            fixup(expr);
+        })
+        .visit_expr_mut(&mut rewritten);
+        if !changed {
+            continue;
+        }
+        if reparses(&rewritten, options) {
+            stmt.expr = rewritten;
+            continue;
+        }
+        // the index in post order of the next expr to rewrite
+        let mut next = 0;
+        loop {
+            let mut rewritten = stmt.expr.clone();
+            let (mut index, mut changed) = (0, false);
+            PostOrder(|expr: &mut Expr| {
+                if index == next {
+                    changed = rewrite(expr);
+                }
+                fixup(expr);
+                index += 1;
+            })
+            .visit_expr_mut(&mut rewritten);
+            if next >= index {
+                break;
+            }
+            next += 1;
+            if changed && reparses(&rewritten, options) {
+                // the exprs the rewrite added or removed come before `next`
+                next = next + count(&mut rewritten) - count(&mut stmt.expr);
+                stmt.expr = rewritten;
+            }
+        }
+    }
+}
+
+/// the exprs `PostOrder` calls its closure on
+fn count(expr: &mut Expr) -> usize {
+    let mut count = 0;
+    PostOrder(|_: &mut Expr| count += 1).visit_expr_mut(expr);
+    count
+}
+
+fn lambda_mut(expr: &mut Expr) -> Option<&mut LambdaExpr> {
+    match &mut expr.inner {
+        ExprInner::Primary(Primary {
+            inner: Inner::LambdaExpr(lambda),
+            ..
+        }) => Some(lambda),
+        _ => None,
+    }
+}
+
+/// the key of `["b"]` if it can be written `.b`
+fn string_key(expr: &Expr) -> Option<String> {
+    let ExprInner::Primary(Primary {
+        inner:
+            Inner::CompoundLiteral(CompoundLiteral::Literal(Literal {
+                inner: literal::Inner::String(string),
+                ..
+            })),
+        ..
+    }) = &expr.inner
+    else {
+        return None;
+    };
+    let key = string.value();
+    let id = SapParser::parse(Rule::normal_id, &key).ok()?.next()?;
+    (id.as_str() == key).then_some(key)
+}
+
+/// the only statement of a block, if it is not a binding which would
+/// leave the block
+fn small_block(expr: &Expr) -> Option<&Expr> {
+    let ExprInner::Primary(Primary {
+        inner: Inner::Block(block),
+        ..
+    }) = &expr.inner
+    else {
+        return None;
+    };
+    match &block.exprs[..] {
+        [stmt] if stmt.doc.is_none() && !is_binding(stmt) => Some(stmt),
+        _ => None,
+    }
+}
+
+fn is_binding(expr: &Expr) -> bool {
+    matches!(
+        &expr.inner,
+        ExprInner::Infix(
+            Infix::Assign
+                | Infix::AssignYield
+                | Infix::Set
+                | Infix::MatchEquals
+                | Infix::AssignSlot,
+            ..
+        )
+    )
+}
+
+/// `expr` prints to something that parses back to itself
+fn reparses(expr: &Expr, options: &GlobalOptions) -> bool {
+    let source = expr.pretty_print(&Context::default(), options);
+    let expected = Expr {
+        doc: None,
+        ..expr.clone()
+    };
+    parse_expr(&source).is_ok_and(|reparsed| reparsed.same_shape(&expected))
+}
+
+fn paren(expr: Expr) -> Expr {
+    let diag = expr.diag;
+    let primary = Primary::ParenExpr(
+        ParenExpr {
+            expr: Box::new(expr),
+        },
+        diag,
+    );
+    Expr::Primary(primary, diag)
+}
+
+fn unparen(expr: Expr) -> Expr {
+    match expr.inner {
+        ExprInner::Primary(Primary {
+            inner: Inner::ParenExpr(paren_expr),
+            ..
+        }) if expr.doc.is_none() => *paren_expr.expr,
+        _ => expr,
+    }
+}
+
+fn is_paren(expr: &Expr) -> bool {
+    matches!(
+        &expr.inner,
+        ExprInner::Primary(Primary {
+            inner: Inner::ParenExpr(_),
+            ..
+        })
+    )
+}
+
+/// an application that is the operand of a postfix or of another
+/// application needs parentheses, `(f a b).c`
+fn paren_tight_operands(expr: &mut Expr) {
+    let tight: Vec<&mut Expr> = match &mut expr.inner {
+        ExprInner::Postfix(Postfix::Trinary(_), _) => vec![],
+        ExprInner::Postfix(_, operand) => vec![operand],
+        ExprInner::CApply(callee, _) => vec![callee],
+        ExprInner::MLApply(callee, params) => [callee.as_mut()].into_iter().chain(params).collect(),
+        _ => vec![],
+    };
+    for operand in tight {
+        if matches!(operand.inner, ExprInner::MLApply(..)) {
+            *operand = paren(operand.clone());
+        }
+    }
+}
+
+/// in C style an application of one param is written `f(x)`, which is an ML
+/// application of `(x)`
+fn application_style(expr: &mut Expr, options: &GlobalOptions) -> bool {
+    match (&mut expr.inner, options.function_application_style) {
+        // an ML param has no line of its own to keep a doc on
+        (ExprInner::CApply(callee, params), FunctionApplicationStyle::MLStyle)
+            if params.len() > 1 && params.iter().all(|param| param.doc.is_none()) =>
+        {
+            let last = params.len() - 1;
+            let params = std::mem::take(params)
+                .into_iter()
+                .enumerate()
+                .map(|(i, param)| {
+                    let param = unparen(param);
+                    if ml_param_needs_paren(&param, i == last) {
+                        paren(param)
+                    } else {
+                        param
+                    }
+                })
+                .collect();
+            expr.inner = ExprInner::MLApply(callee.clone(), params);
+            true
+        }
+        (ExprInner::MLApply(callee, params), FunctionApplicationStyle::CStyle)
+            if params.len() > 1 =>
+        {
+            let params = std::mem::take(params).into_iter().map(unparen).collect();
+            expr.inner = ExprInner::CApply(callee.clone(), params);
+            true
+        }
+        (ExprInner::MLApply(_, params), FunctionApplicationStyle::CStyle) => {
+            match &mut params[..] {
+                [param] if !is_paren(param) => {
+                    *param = paren(param.clone());
+                    true
+                }
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{formatter::format, parse_file};
+
+    fn options(style: FunctionApplicationStyle) -> GlobalOptions {
+        GlobalOptions {
+            function_application_style: style,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_c_to_ml_style() {
+        let options = options(FunctionApplicationStyle::MLStyle);
+        let cases = [
+            ("f(a, b)", "f a b\n"),
+            ("f(a + 1, g(x, y), -c)", "f (a + 1) (g x y) -c\n"),
+            ("f(c ? d : e, \\x -> x)", "f (c ? d : e) \\x -> x\n"),
+            ("f(\\x -> x, b)", "f (\\x -> x) b\n"),
+            ("f(a, b).c + f(a, b)[0]", "(f a b).c + (f a b)[0]\n"),
+            ("f(a, b)(c, d)", "(f a b) c d\n"),
+            ("f(,)", "f(,)\n"),
+            ("f([1], b[0])", "f ([1]) b[0]\n"),
+            ("f(a, <- b)", "f a <-b\n"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(format(source, &options).unwrap(), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_ml_to_c_style() {
+        let options = options(FunctionApplicationStyle::CStyle);
+        let cases = [
+            ("f a b", "f(a, b)\n"),
+            ("f (a + 1) (g x y)", "f(a + 1, g(x, y))\n"),
+            ("f x", "f(x)\n"),
+            ("f (x)", "f(x)\n"),
+            ("f a b + 1", "f(a, b) + 1\n"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(format(source, &options).unwrap(), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_refuse_style_read_otherwise_by_parent() {
+        // an ML application takes the rest of the expr and lowering splits
+        // it at the outermost infix, `a + f x y == z` is `a + ((f x y) == z)`
+        use FunctionApplicationStyle::{CStyle, MLStyle};
+        let cases = [
+            (CStyle, "a + f x y == z", "a + f x y == z\n"),
+            (CStyle, "ok && f x y = z", "ok && f x y = z\n"),
+            (CStyle, "a + f x y == g u v", "a + f x y == g(u, v)\n"),
+            (
+                CStyle,
+                "x = [h a b, a + f x y == z]",
+                "x = [h(a, b), a + f x y == z]\n",
+            ),
+            (MLStyle, "f(a, b) << c == d", "f(a, b) << c == d\n"),
+            (
+                MLStyle,
+                "g(1, 2) + f(a, b) << c == d",
+                "g(1, 2) + f(a, b) << c == d\n",
+            ),
+        ];
+        for (style, source, expected) in cases {
+            assert_eq!(
+                format(source, &options(style)).unwrap(),
+                expected,
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_style_round_trip() {
+        let source = "f (a + 1) b (g x y)\n";
+        let c = format(source, &options(FunctionApplicationStyle::CStyle)).unwrap();
+        let ml = format(&c, &options(FunctionApplicationStyle::MLStyle)).unwrap();
+        assert_eq!(ml, source);
+    }
+
+    #[test]
+    fn test_refuse_ml_param_with_doc() {
+        // an ML param has no line of its own to keep the doc on
+        let options = options(FunctionApplicationStyle::MLStyle);
+        let source = "f(\n/// doc\na, b)";
+        let mut program = parse_file(source).unwrap();
+        let before = program.clone();
+        rewrite(&mut program, &options);
+        assert!(program.stmts[0].expr.same_shape(&before.stmts[0].expr));
+        assert_eq!(
+            format(source, &options).unwrap(),
+            "f(\n    /// doc\n    a,\n    b\n)\n"
+        );
+    }
+
+    /// `source` after only `pass`, printed with the default options
+    fn apply(source: &str, pass: fn(&mut Program, &GlobalOptions)) -> String {
+        let options = GlobalOptions::default();
+        let mut program = parse_file(source).unwrap();
+        pass(&mut program, &options);
+        program.pretty_print(&Context::default(), &options)
+    }
+
+    #[test]
+    fn test_small_postfix_index_to_postfix_access() {
+        let pass = small_postfix_index_to_postfix_access;
+        assert_eq!(apply("a[\"b\"]", pass), "a.b\n");
+        assert_eq!(apply("a[\"b\"][\"c_1\"][0]", pass), "a.b.c_1[0]\n");
+        assert_eq!(apply("a[r\"b\"]", pass), "a.b\n");
+        assert_eq!(apply("a[\"b c\"]", pass), "a[\"b c\"]\n");
+        assert_eq!(apply("a[\"1b\"]", pass), "a[\"1b\"]\n");
+        assert_eq!(apply("a[b]", pass), "a[b]\n");
+    }
+
+    #[test]
+    fn test_small_block_to_inline() {
+        let pass = small_block_to_inline;
+        assert_eq!(apply("\\x -> { x }", pass), "\\x -> x\n");
+        assert_eq!(apply("\\x -> { x + 1 }", pass), "\\x -> x + 1\n");
+        assert_eq!(apply("\\x -> { \\y -> { y } }", pass), "\\x -> \\y -> y\n");
+        assert_eq!(apply("\\x -> { a; b }", pass), "\\x -> { a; b }\n");
+        assert_eq!(apply("\\x -> { a = x }", pass), "\\x -> { a = x }\n");
+        assert_eq!(apply("{ x }", pass), "{ x }\n");
+    }
+
+    #[test]
+    fn test_lambda_no_parameter() {
+        let pass = lambda_no_parameter;
+        assert_eq!(apply("\\-> { x }", pass), "_{ x }\n");
+        assert_eq!(apply("f = \\-> { a; b }", pass), "f = _{ a; b }\n");
+        assert_eq!(apply("\\-> x", pass), "\\-> x\n");
+        assert_eq!(apply("\\x -> { x }", pass), "\\x -> { x }\n");
+    }
+
+    #[test]
+    fn test_rewrite_order() {
+        let options = GlobalOptions {
+            small_block_to_inline: true,
+            lambda_no_parameter: true,
+            ..Default::default()
+        };
+        assert_eq!(format("\\-> { x }", &options).unwrap(), "_{ x }\n");
+        assert_eq!(format("\\y -> { x }", &options).unwrap(), "\\y -> x\n");
+    }
+}
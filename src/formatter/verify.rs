@@ -0,0 +1,193 @@
+//! formatting with a check of its own output: the output has to parse to
+//! the program of the source after the rewrites `GlobalOptions` asks for and
+//! format to itself
+//!
+//! a failed check is a bug of the formatter, reported with the snippet it
+//! went wrong on
+
+use std::fmt;
+
+use similar::TextDiff;
+
+use crate::{ParseError, parse_file, program::Program};
+
+use super::{GlobalOptions, format, rewrite::rewrite, same_ast};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatBug {
+    /// the output does not parse, `snippet` is the line of the error
+    Reparse {
+        error: Box<ParseError>,
+        snippet: String,
+    },
+    /// the output parses to another program, `snippet` is the first
+    /// statement that changed, `formatted` what it became
+    AstChanged { snippet: String, formatted: String },
+    /// formatting the output changes it again, `snippet` is the diff
+    NotIdempotent { snippet: String },
+}
+
+impl fmt::Display for FormatBug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatBug::Reparse { error, snippet } => write!(
+                f,
+                "formatter bug: the output does not parse, {}:\n{}",
+                error.message(),
+                snippet
+            ),
+            FormatBug::AstChanged { snippet, formatted } => write!(
+                f,
+                "formatter bug: the output is another program, this:\n{}\nbecame:\n{}",
+                snippet, formatted
+            ),
+            FormatBug::NotIdempotent { snippet } => write!(
+                f,
+                "formatter bug: formatting the output changes it again:\n{}",
+                snippet
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyError {
+    /// the source itself does not parse, not a bug
+    Parse(ParseError),
+    Bug(FormatBug),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::Parse(error) => write!(f, "{}", error.message()),
+            VerifyError::Bug(bug) => write!(f, "{}", bug),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// format twice and check both outputs, the formatted source if they pass
+pub fn verify(source: &str, options: &GlobalOptions) -> Result<String, VerifyError> {
+    let mut program = parse_file(source).map_err(VerifyError::Parse)?;
+    rewrite(&mut program, options);
+    let first = format(source, options).map_err(VerifyError::Parse)?;
+    let second = match format(&first, options) {
+        Ok(second) => second,
+        // reported by `check` below
+        Err(_) => first.clone(),
+    };
+    check(source, &program, &first, &second).map_err(VerifyError::Bug)?;
+    Ok(first)
+}
+
+/// `first` formats `source`, `second` formats `first`, `program` is the
+/// rewritten program of `source`
+fn check(source: &str, program: &Program, first: &str, second: &str) -> Result<(), FormatBug> {
+    let formatted = parse_file(first).map_err(|error| FormatBug::Reparse {
+        snippet: first
+            .lines()
+            .nth(error.diag.start_line.saturating_sub(1))
+            .unwrap_or_default()
+            .to_string(),
+        error: Box::new(error),
+    })?;
+    if !same_ast(program, &formatted) {
+        let changed = program
+            .stmts
+            .iter()
+            .zip(&formatted.stmts)
+            .position(|(a, b)| !same_ast(&single(program, a), &single(&formatted, b)))
+            .unwrap_or(program.stmts.len().min(formatted.stmts.len()));
+        let text = |source: &str, program: &Program| {
+            program.stmts.get(changed).map_or_else(
+                || "<end of file>".to_string(),
+                |stmt| source[stmt.diag.start_offset..stmt.diag.end_offset].to_string(),
+            )
+        };
+        return Err(FormatBug::AstChanged {
+            snippet: text(source, program),
+            formatted: text(first, &formatted),
+        });
+    }
+    if first != second {
+        let snippet = TextDiff::from_lines(first, second)
+            .unified_diff()
+            .context_radius(1)
+            .header("formatted", "formatted twice")
+            .to_string();
+        return Err(FormatBug::NotIdempotent { snippet });
+    }
+    Ok(())
+}
+
+/// the program of the one statement, to compare it alone
+fn single(program: &Program, stmt: &crate::program::Stmt) -> Program {
+    Program {
+        stmts: vec![stmt.clone()],
+        doc_headings: vec![],
+        diag: program.diag,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatter::FunctionApplicationStyle;
+
+    #[test]
+    fn test_verify() {
+        let options = GlobalOptions::default();
+        assert_eq!(verify("a=1", &options).unwrap(), "a = 1\n");
+        assert!(matches!(
+            verify("a=(", &options),
+            Err(VerifyError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_rewrites() {
+        let options = GlobalOptions {
+            function_application_style: FunctionApplicationStyle::CStyle,
+            small_postfix_index_to_postfix_access: true,
+            small_block_to_inline: true,
+            lambda_no_parameter: true,
+            ..Default::default()
+        };
+        assert_eq!(verify("f x", &options).unwrap(), "f(x)\n");
+        assert_eq!(
+            verify("g = \\-> { a[\"b\"] }\nh = \\x -> { f x y }", &options).unwrap(),
+            "g = _{ a.b }\nh = \\x -> f(x, y)\n"
+        );
+        let options = GlobalOptions {
+            function_application_style: FunctionApplicationStyle::MLStyle,
+            ..Default::default()
+        };
+        assert_eq!(verify("f(a, b)", &options).unwrap(), "f a b\n");
+    }
+
+    #[test]
+    fn test_check_reports_bugs() {
+        let source = "a = 1\nb = 2\n";
+        let program = parse_file(source).unwrap();
+        assert_eq!(check(source, &program, source, source), Ok(()));
+        assert!(matches!(
+            check(source, &program, "a = 1\nb = (\n", source),
+            Err(FormatBug::Reparse { snippet, .. }) if snippet == "b = ("
+        ));
+        assert_eq!(
+            check(source, &program, "a = 1\nb = 3\n", source),
+            Err(FormatBug::AstChanged {
+                snippet: "b = 2".to_string(),
+                formatted: "b = 3".to_string()
+            })
+        );
+        let Err(FormatBug::NotIdempotent { snippet }) =
+            check(source, &program, source, "a = 1\n\nb = 2\n")
+        else {
+            panic!()
+        };
+        assert!(snippet.contains("+\n b = 2"), "{}", snippet);
+    }
+}
@@ -1,30 +1,30 @@
 use pest_ast::FromPest;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{Rule, span_to_string};
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::macro_id))]
 pub struct MacroId {
     #[pest_ast(outer(with(span_to_string)))]
     pub value: String,
 }
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::normal_id))]
 pub struct NormalId {
     #[pest_ast(outer(with(span_to_string)))]
     pub value: String,
 }
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::magic_fn_id))]
 pub struct MagicFnId {
     #[pest_ast(outer(with(span_to_string)))]
     pub value: String,
 }
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::id))]
 pub enum Id {
     NormalId(NormalId),
@@ -32,6 +32,13 @@ pub enum Id {
     MagicFnId(MagicFnId),
 }
 
+/// a `?id` placeholder in an application
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
+#[pest_ast(rule(Rule::hole))]
+pub struct Hole {
+    pub id: Id,
+}
+
 impl Id {
     pub fn value(&self) -> String {
         match self {
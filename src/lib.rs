@@ -4,17 +4,31 @@ pub mod formatter;
 pub mod preprocessor;
 
 pub mod diagnostics;
+pub mod error;
 pub mod expr;
 pub mod function;
 pub mod id;
 pub mod literal;
 pub mod operator_precedence;
 pub mod pattern;
+pub mod program;
 pub mod quotations;
+pub mod recovery;
+pub mod report;
+pub mod unparse;
+pub mod visit;
 
+#[cfg(test)]
+mod test_util;
+
+use error::{ParseError, ParseErrorKind, check_pair};
 use from_pest::FromPest;
+use literal::number::PromoteInts;
 use pest::Parser;
 use pest_derive::Parser;
+use visit::VisitMut;
+
+pub use recovery::{parse_file_recovering, parse_file_recovering_with};
 
 pub fn span_to_string(span: pest::Span) -> String {
     span.as_str().to_string()
@@ -24,13 +38,95 @@ pub fn span_to_string(span: pest::Span) -> String {
 #[grammar = "parser.pest"]
 pub struct SapParser;
 
-pub fn parse_expr(input: &str) -> Result<expr::Expr, from_pest::ConversionError<from_pest::Void>> {
-    let pair = crate::SapParser::parse(Rule::expr, input)
-        .unwrap()
+/// what the `_with` parsers accept beyond the language, nothing by default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// read the plain integer literals out of the `i64` range as a `BigInt`
+    /// instead of rejecting them
+    pub promote_ints: bool,
+}
+
+pub fn parse_expr(input: &str) -> Result<expr::Expr, ParseError> {
+    parse_expr_with(input, &ParseOptions::default())
+}
+
+pub fn parse_expr_with(input: &str, options: &ParseOptions) -> Result<expr::Expr, ParseError> {
+    let pair = crate::SapParser::parse(Rule::single_expr, input)
+        .map_err(|e| ParseError::from_pest_error(e, input))?
         .next()
-        .unwrap();
-    let mut pairs = pest::iterators::Pairs::single(pair);
-    expr::Expr::from_pest(&mut pairs)
+        .and_then(|single_expr| single_expr.into_inner().next());
+    let Some(pair) = pair else {
+        return Err(ParseError::new(
+            ParseErrorKind::Syntax,
+            diagnostics::Diagnostic::from_offsets(input, 0, input.len()),
+        ));
+    };
+    let mut expr = lower(pair, options)?;
+    if options.promote_ints {
+        PromoteInts.visit_expr_mut(&mut expr);
+    }
+    Ok(expr)
+}
+
+pub fn parse_file(input: &str) -> Result<program::Program, ParseError> {
+    parse_file_with(input, &ParseOptions::default())
+}
+
+pub fn parse_file_with(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<program::Program, ParseError> {
+    let pair = crate::SapParser::parse(Rule::file, input)
+        .map_err(|e| ParseError::from_pest_error(e, input))?
+        .next();
+    let Some(pair) = pair else {
+        return Err(ParseError::new(
+            ParseErrorKind::Syntax,
+            diagnostics::Diagnostic::from_offsets(input, 0, input.len()),
+        ));
+    };
+    let mut program = lower(pair, options)?;
+    if options.promote_ints {
+        PromoteInts.visit_program_mut(&mut program);
+    }
+    Ok(program)
+}
+
+/// every diagnostic of a file, sorted by position: the parse errors, the
+/// comment lints, the escaped holes, the slot misuses and the module errors,
+/// together with the partial program
+pub fn check_file(input: &str) -> (program::Program, Vec<diagnostics::SapDiagnostic>) {
+    check_file_with(input, &ParseOptions::default())
+}
+
+pub fn check_file_with(
+    input: &str,
+    options: &ParseOptions,
+) -> (program::Program, Vec<diagnostics::SapDiagnostic>) {
+    let (program, errors) = parse_file_recovering_with(input, options);
+    let mut diagnostics: Vec<_> = errors
+        .iter()
+        .map(diagnostics::SapDiagnostic::from)
+        .collect();
+    diagnostics.extend(preprocessor::comment::preprocess(input).diagnostics);
+    diagnostics.extend(preprocessor::hole::desugar(program.clone()).1);
+    diagnostics.extend(preprocessor::slot::validate(&program));
+    diagnostics.extend(preprocessor::module_system::collect(&program).2);
+    diagnostics.sort_by_key(|diagnostic| diagnostic.primary.diag.start_offset);
+    (program, diagnostics)
+}
+
+fn lower<'a, T: FromPest<'a, Rule = Rule, FatalError = from_pest::Void>>(
+    pair: pest::iterators::Pair<'a, Rule>,
+    options: &ParseOptions,
+) -> Result<T, ParseError> {
+    let mut errors = vec![];
+    check_pair(&pair, options, &mut errors);
+    if let Some(error) = errors.into_iter().next() {
+        return Err(error);
+    }
+
+    error::lower(pair)
 }
 
 #[macro_export]
@@ -42,7 +138,7 @@ macro_rules! ast_with_diagnostic {
         use from_pest::FromPest;
         use pest::iterators::Pairs;
 
-        #[derive(Debug, Clone, PartialEq, Serialize)]
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
         pub struct $name {
             pub inner: Inner,
             pub diag: Diagnostic,
@@ -68,19 +164,25 @@ macro_rules! ast_with_diagnostic {
             fn from_pest(
                 pest: &mut pest::iterators::Pairs<'_, Self::Rule>,
             ) -> Result<Self, from_pest::ConversionError<Self::FatalError>> {
-                let pest = pest.next();
-                if pest.is_none() {
+                // the cursor only moves past a pair that lowers, so a parent
+                // sees a pair it could not lower as extraneous
+                let mut clone = pest.clone();
+                let Some(pair) = clone.next() else {
                     return Err(from_pest::ConversionError::NoMatch);
-                }
-                let pest = pest.unwrap();
+                };
 
-                let diag = Diagnostic::from_span(pest.as_span());
-                let inner = Inner::from_pest(&mut Pairs::single(pest))?;
+                let diag = Diagnostic::from_span(pair.as_span());
+                let inner = Inner::from_pest(&mut Pairs::single(pair.clone()));
+                if inner.is_err() && pair.as_rule() == Rule::$rule {
+                    $crate::error::lowering_failed(&pair);
+                }
+                let inner = inner?;
+                *pest = clone;
                 Ok($name { inner, diag })
             }
         }
 
-        #[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+        #[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
         #[pest_ast(rule(Rule::$rule))]
         pub enum Inner {
             $(
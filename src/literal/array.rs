@@ -1,9 +1,9 @@
 use pest_ast::FromPest;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{Rule, diagnostics::Diagnostic, expr::Expr};
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::array_body))]
 pub struct ArrayBody {
     #[pest_ast(outer(with(Diagnostic::from_span)))]
@@ -7,7 +7,7 @@ use array::ArrayBody;
 use number::SapNumber;
 use object::ObjectBody;
 use pest_ast::FromPest;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use string::SapString;
 
 use crate::{Rule, ast_with_diagnostic};
@@ -21,13 +21,32 @@ fn parse_bool(span: pest::Span) -> bool {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, FromPest)]
-#[pest_ast(rule(Rule::boolean))]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Boolean {
-    #[pest_ast(outer(with(parse_bool)))]
     pub value: bool,
 }
 
+/// the derive leaves the inner `boolean_true`/`boolean_false` unconsumed
+impl from_pest::FromPest<'_> for Boolean {
+    type Rule = Rule;
+
+    type FatalError = from_pest::Void;
+
+    fn from_pest(
+        pest: &mut pest::iterators::Pairs<'_, Self::Rule>,
+    ) -> Result<Self, from_pest::ConversionError<Self::FatalError>> {
+        match pest.peek() {
+            Some(pair) if pair.as_rule() == Rule::boolean => {
+                let pair = pest.next().unwrap();
+                Ok(Boolean {
+                    value: parse_bool(pair.as_span()),
+                })
+            }
+            _ => Err(from_pest::ConversionError::NoMatch),
+        }
+    }
+}
+
 impl Serialize for Boolean {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -37,10 +56,24 @@ impl Serialize for Boolean {
     }
 }
 
-#[derive(Debug, Clone, FromPest, PartialEq, Serialize)]
+impl<'de> Deserialize<'de> for Boolean {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        bool::deserialize(deserializer).map(|value| Boolean { value })
+    }
+}
+
+#[derive(Debug, Clone, FromPest, PartialEq, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::void))]
 pub struct Void;
 
+/// `<>`, a binding initialised later with `::=`
+#[derive(Debug, Clone, FromPest, PartialEq, Serialize, Deserialize)]
+#[pest_ast(rule(Rule::slot))]
+pub struct Slot;
+
 // #[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
 // #[pest_ast(rule(Rule::literal))]
 // pub enum Literal {
@@ -56,17 +89,57 @@ ast_with_diagnostic! {
         Void(void: Void),
         String(string: SapString),
         Number(number: SapNumber),
+        Slot(slot: Slot),
     }
 }
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
-#[pest_ast(rule(Rule::compound_literal))]
+/// `None` is the empty `[]` or `{}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum CompoundLiteral {
-    ArrayLiteral(ArrayBody),
-    ObjectLiteral(ObjectBody),
+    ArrayLiteral(Option<ArrayBody>),
+    ObjectLiteral(Option<ObjectBody>),
     Literal(Literal),
 }
 
+/// `[]` and `{}` have no inner pair, the bracket tells them apart
+impl from_pest::FromPest<'_> for CompoundLiteral {
+    type Rule = Rule;
+
+    type FatalError = from_pest::Void;
+
+    fn from_pest(
+        pest: &mut pest::iterators::Pairs<'_, Self::Rule>,
+    ) -> Result<Self, from_pest::ConversionError<Self::FatalError>> {
+        let mut clone = pest.clone();
+        let pair = match clone.next() {
+            Some(pair) if pair.as_rule() == Rule::compound_literal => pair,
+            _ => return Err(from_pest::ConversionError::NoMatch),
+        };
+        let bracket = pair.as_str().starts_with('[');
+        let mut inner = pair.clone().into_inner();
+        let this = match inner.peek().map(|pair| pair.as_rule()) {
+            Some(Rule::array_body) => ArrayBody::from_pest(&mut inner)
+                .map(|body| CompoundLiteral::ArrayLiteral(Some(body))),
+            Some(Rule::object_body) => ObjectBody::from_pest(&mut inner)
+                .map(|body| CompoundLiteral::ObjectLiteral(Some(body))),
+            Some(_) => Literal::from_pest(&mut inner).map(CompoundLiteral::Literal),
+            None if bracket => Ok(CompoundLiteral::ArrayLiteral(None)),
+            None => Ok(CompoundLiteral::ObjectLiteral(None)),
+        };
+        let this = this.and_then(|this| match inner.next() {
+            Some(_) => Err(from_pest::ConversionError::Extraneous {
+                current_node: "CompoundLiteral",
+            }),
+            None => Ok(this),
+        });
+        match this {
+            Ok(_) => *pest = clone,
+            Err(_) => crate::error::lowering_failed(&pair),
+        }
+        this
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use from_pest::FromPest;
@@ -74,6 +147,19 @@ mod tests {
 
     use crate::Rule;
 
+    #[test]
+    fn test_boolean() {
+        for (source, value) in [("true", true), ("false", false)] {
+            let pair = crate::SapParser::parse(Rule::boolean, source)
+                .unwrap()
+                .next()
+                .unwrap();
+            let mut pairs = pest::iterators::Pairs::single(pair);
+            let boolean = super::Boolean::from_pest(&mut pairs).unwrap();
+            assert_eq!(boolean.value, value);
+        }
+    }
+
     #[test]
     fn test_void() {
         let pair = crate::SapParser::parse(Rule::void, "()")
@@ -84,4 +170,27 @@ mod tests {
         let void = super::Void::from_pest(&mut pairs).unwrap();
         assert_eq!(void, super::Void);
     }
+
+    #[test]
+    fn test_empty_compound_literal() {
+        for (source, array) in [
+            ("[]", true),
+            ("[\n]", true),
+            ("{}", false),
+            ("{ , }", false),
+        ] {
+            let pair = crate::SapParser::parse(Rule::compound_literal, source)
+                .unwrap()
+                .next()
+                .unwrap();
+            let mut pairs = pest::iterators::Pairs::single(pair);
+            let literal = super::CompoundLiteral::from_pest(&mut pairs).unwrap();
+            let expected = if array {
+                super::CompoundLiteral::ArrayLiteral(None)
+            } else {
+                super::CompoundLiteral::ObjectLiteral(None)
+            };
+            assert_eq!(literal, expected, "{source:?}");
+        }
+    }
 }
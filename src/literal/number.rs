@@ -1,24 +1,39 @@
+use std::num::ParseIntError;
+
 use from_pest::FromPest;
+use num::{BigRational, Num, Zero};
+use pest::Parser;
 use pest_ast::FromPest;
-use serde::Serialize;
+use serde::{Deserialize, Serialize, de};
 
-use crate::Rule;
+use crate::{
+    ParseOptions, Rule, SapParser,
+    literal::{self, Literal},
+    span_to_string,
+    visit::VisitMut,
+};
 
-fn helper_parse_int(str: &str) -> i64 {
+pub fn try_parse_int(str: &str) -> Result<i64, ParseIntError> {
     let str = str.replace("_", "");
     if str.len() > 2 {
         let prefix = &str[0..2];
         match prefix {
-            "0x" | "0X" => i64::from_str_radix(&str[2..], 16).unwrap(),
-            "0b" | "0B" => i64::from_str_radix(&str[2..], 2).unwrap(),
-            "0o" | "0O" => i64::from_str_radix(&str[2..], 8).unwrap(),
-            _ => str.parse::<i64>().unwrap(),
+            "0x" | "0X" => i64::from_str_radix(&str[2..], 16),
+            "0b" | "0B" => i64::from_str_radix(&str[2..], 2),
+            "0o" | "0O" => i64::from_str_radix(&str[2..], 8),
+            _ => str.parse::<i64>(),
         }
     } else {
-        str.parse::<i64>().unwrap()
+        str.parse::<i64>()
     }
 }
 
+/// out of range literals are rejected by `error::check_pair` before lowering,
+/// or promoted by `PromoteInts` after it
+fn helper_parse_int(str: &str) -> i64 {
+    try_parse_int(str).unwrap_or_default()
+}
+
 fn parse_int(span: pest::Span) -> i64 {
     let str = span.as_str();
     helper_parse_int(str)
@@ -29,6 +44,8 @@ fn parse_int(span: pest::Span) -> i64 {
 pub struct BinInt {
     #[pest_ast(outer(with(parse_int)))]
     pub value: i64,
+    #[pest_ast(outer(with(span_to_string)))]
+    pub lexeme: String,
 }
 
 #[derive(Debug, Clone, FromPest)]
@@ -36,6 +53,8 @@ pub struct BinInt {
 pub struct OctInt {
     #[pest_ast(outer(with(parse_int)))]
     pub value: i64,
+    #[pest_ast(outer(with(span_to_string)))]
+    pub lexeme: String,
 }
 
 #[derive(Debug, Clone, FromPest)]
@@ -43,6 +62,8 @@ pub struct OctInt {
 pub struct HexInt {
     #[pest_ast(outer(with(parse_int)))]
     pub value: i64,
+    #[pest_ast(outer(with(span_to_string)))]
+    pub lexeme: String,
 }
 
 #[derive(Debug, Clone, FromPest)]
@@ -50,6 +71,8 @@ pub struct HexInt {
 pub struct DecInt {
     #[pest_ast(outer(with(parse_int)))]
     pub value: i64,
+    #[pest_ast(outer(with(span_to_string)))]
+    pub lexeme: String,
 }
 
 #[derive(Debug, Clone, FromPest)]
@@ -70,6 +93,48 @@ impl Int {
             Int::DecInt(dec_int) => dec_int.value,
         }
     }
+
+    pub fn radix(&self) -> u32 {
+        match self {
+            Int::BinInt(_) => 2,
+            Int::OctInt(_) => 8,
+            Int::HexInt(_) => 16,
+            Int::DecInt(_) => 10,
+        }
+    }
+
+    /// the literal as written, with its prefix and `_`s
+    pub fn lexeme(&self) -> &str {
+        match self {
+            Int::BinInt(bin_int) => &bin_int.lexeme,
+            Int::OctInt(oct_int) => &oct_int.lexeme,
+            Int::HexInt(hex_int) => &hex_int.lexeme,
+            Int::DecInt(dec_int) => &dec_int.lexeme,
+        }
+    }
+}
+
+/// parse `lexeme` back into the node it was serialized from
+pub(super) fn from_lexeme<T>(rule: Rule, lexeme: &str) -> Result<T, String>
+where
+    T: for<'a> FromPest<'a, Rule = Rule, FatalError = from_pest::Void>,
+{
+    let pair = SapParser::parse(rule, lexeme)
+        .ok()
+        .and_then(|mut pairs| pairs.next())
+        .filter(|pair| pair.as_str() == lexeme)
+        .ok_or_else(|| format!("invalid {:?} `{}`", rule, lexeme))?;
+    crate::lower(pair, &ParseOptions::default()).map_err(|e| e.to_string())
+}
+
+/// the serialized form of an `Int`, the lexeme is what is read back, the
+/// value is for the readers of the serialized tree
+#[derive(Serialize, Deserialize)]
+struct IntRepr {
+    radix: u32,
+    lexeme: String,
+    #[serde(skip_deserializing)]
+    value: i64,
 }
 
 impl Serialize for Int {
@@ -77,27 +142,66 @@ impl Serialize for Int {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_i64(self.value())
+        IntRepr {
+            radix: self.radix(),
+            lexeme: self.lexeme().to_string(),
+            value: self.value(),
+        }
+        .serialize(serializer)
     }
 }
 
-fn parse_exponent_part(span: pest::Span) -> i64 {
-    let str = span.as_str();
-    if str.find('-').is_some() {
-        -helper_parse_int(&str[2..])
-    } else if str.find('+').is_some() {
-        helper_parse_int(&str[2..])
-    } else {
-        helper_parse_int(&str[1..])
+impl<'de> Deserialize<'de> for Int {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let repr = IntRepr::deserialize(deserializer)?;
+        let int: Int = from_lexeme(Rule::int, &repr.lexeme).map_err(de::Error::custom)?;
+        if int.radix() != repr.radix {
+            return Err(de::Error::custom(format!(
+                "`{}` is not in radix {}",
+                repr.lexeme, repr.radix
+            )));
+        }
+        Ok(int)
     }
 }
 
+/// an integer literal of any size, written with an `n` suffix or promoted
+/// from a plain one with `ParseOptions::promote_ints`
 #[derive(Debug, Clone)]
-pub struct ExponentPart {
-    pub value: i64,
+pub struct BigInt {
+    pub value: num::BigInt,
+    pub radix: u32,
+    /// the literal as written, with its `n` if it has one
+    pub lexeme: String,
+}
+
+impl BigInt {
+    /// `None` if `lexeme` is not an integer literal
+    pub fn from_lexeme(lexeme: &str) -> Option<Self> {
+        let digits = lexeme.strip_suffix('n').unwrap_or(lexeme).replace('_', "");
+        let (radix, digits) = match digits.get(..2) {
+            Some("0x" | "0X") => (16, &digits[2..]),
+            Some("0b" | "0B") => (2, &digits[2..]),
+            Some("0o" | "0O") => (8, &digits[2..]),
+            _ => (10, &digits[..]),
+        };
+        // `from_str_radix` also takes a sign
+        if !digits.chars().all(|c| c.is_digit(radix)) {
+            return None;
+        }
+        let value = num::BigInt::from_str_radix(digits, radix).ok()?;
+        Some(BigInt {
+            value,
+            radix,
+            lexeme: lexeme.to_string(),
+        })
+    }
 }
 
-impl FromPest<'_> for ExponentPart {
+impl FromPest<'_> for BigInt {
     type Rule = Rule;
 
     type FatalError = from_pest::Void;
@@ -105,100 +209,214 @@ impl FromPest<'_> for ExponentPart {
     fn from_pest(
         pest: &mut pest::iterators::Pairs<'_, Self::Rule>,
     ) -> Result<Self, from_pest::ConversionError<Self::FatalError>> {
-        let pair = if let Some(p) = pest.next() {
-            p
-        } else {
-            return Err(from_pest::ConversionError::NoMatch);
-        };
-        let value = parse_exponent_part(pair.as_span());
-        Ok(ExponentPart { value })
+        match pest.peek() {
+            Some(pair) if pair.as_rule() == Rule::bigint => {
+                let pair = pest.next().unwrap();
+                BigInt::from_lexeme(pair.as_str()).ok_or(from_pest::ConversionError::NoMatch)
+            }
+            _ => Err(from_pest::ConversionError::NoMatch),
+        }
     }
 }
 
-#[derive(Debug, Clone, FromPest)]
-#[pest_ast(rule(Rule::float1))]
-pub struct Float1 {
-    pub value: Int,
-    pub exponent_part: ExponentPart,
-}
-
-#[derive(Debug, Clone, FromPest)]
-#[pest_ast(rule(Rule::float2))]
-pub struct Float2 {
-    pub value: Int,
-    pub sub: Int,
-    pub exponent_part: Option<ExponentPart>,
+/// like `IntRepr`, the value is a decimal string as JSON numbers are not
+/// big enough
+#[derive(Serialize, Deserialize)]
+struct BigIntRepr {
+    radix: u32,
+    lexeme: String,
+    #[serde(skip_deserializing)]
+    value: String,
 }
 
-#[derive(Debug, Clone, FromPest)]
-#[pest_ast(rule(Rule::float3))]
-pub struct Float3 {
-    pub sub: Int,
-    pub exponent_part: Option<ExponentPart>,
+impl Serialize for BigInt {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        BigIntRepr {
+            radix: self.radix,
+            lexeme: self.lexeme.clone(),
+            value: self.value.to_string(),
+        }
+        .serialize(serializer)
+    }
 }
 
-#[derive(Debug, Clone, FromPest)]
-#[pest_ast(rule(Rule::float))]
-pub enum Float {
-    Float1(Float1),
-    Float2(Float2),
-    Float3(Float3),
+impl<'de> Deserialize<'de> for BigInt {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let repr = BigIntRepr::deserialize(deserializer)?;
+        let big_int = BigInt::from_lexeme(&repr.lexeme)
+            .filter(|big_int| big_int.radix == repr.radix)
+            .ok_or_else(|| {
+                de::Error::custom(format!(
+                    "`{}` is not an integer in radix {}",
+                    repr.lexeme, repr.radix
+                ))
+            })?;
+        Ok(big_int)
+    }
 }
 
-pub fn digits(n: f64) -> f64 {
-    n.log10().floor() + 1.0
+/// a float literal, written in decimal only: `0x1.8` and `1e0b1` are
+/// rejected by `error::check_pair`, so is an exponent out of the `i64` range
+#[derive(Debug, Clone)]
+pub struct Float {
+    lexeme: String,
+    value: f64,
 }
 
 impl Float {
+    /// `None` if `lexeme` is not a decimal float literal
+    pub fn from_lexeme(lexeme: &str) -> Option<Self> {
+        float_parts(lexeme)?;
+        // std rounds to the nearest `f64`, past its range is an infinity
+        let value = lexeme.replace('_', "").parse().ok()?;
+        Some(Float {
+            lexeme: lexeme.to_string(),
+            value,
+        })
+    }
+
+    /// the nearest `f64` to the literal
     pub fn value(&self) -> f64 {
-        match self {
-            Float::Float1(float1) => {
-                let value = float1.value.value() as f64;
-                let exponent = float1.exponent_part.value as f64;
-                value * 10_f64.powf(exponent)
-            }
-            Float::Float2(float2) => {
-                let value = float2.value.value() as f64;
-                let sub = float2.sub.value() as f64;
-                let sub = sub / 10_f64.powf(digits(sub));
-                let exponent = float2
-                    .exponent_part
-                    .clone()
-                    .unwrap_or(ExponentPart { value: 0 })
-                    .value as f64;
-                value + sub * 10_f64.powf(exponent)
+        self.value
+    }
+
+    /// the literal as written
+    pub fn lexeme(&self) -> &str {
+        &self.lexeme
+    }
+
+    /// the exact value of the literal, `None` when the exponent is so far
+    /// out of the range of `f64` that `value` is `0` or an infinity, where
+    /// the exact value could take more memory than there is
+    pub fn exact(&self) -> Option<BigRational> {
+        let (int, fraction, exponent) = float_parts(&self.lexeme)?;
+        let mantissa =
+            num::BigInt::from_str_radix(&format!("0{}{}", int, fraction), 10).unwrap_or_default();
+        if mantissa.is_zero() {
+            return Some(BigRational::zero());
+        }
+        // `f64` spans about 10^-324 to 10^308, a literal needs its digits more
+        let digits = (int.len() + fraction.len()) as u64;
+        if exponent.unsigned_abs() > 400 + digits {
+            return None;
+        }
+        let exponent = exponent.checked_sub(fraction.len() as i64)?;
+        let scale = num::pow(num::BigInt::from(10), exponent.unsigned_abs() as usize);
+        if exponent < 0 {
+            Some(BigRational::new(mantissa, scale))
+        } else {
+            Some(BigRational::from_integer(mantissa * scale))
+        }
+    }
+}
+
+/// the digits before and after the point and the exponent of a decimal
+/// float literal
+fn float_parts(lexeme: &str) -> Option<(String, String, i64)> {
+    let lexeme = lexeme.replace('_', "");
+    let decimal = |digits: &str| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit());
+    let (mantissa, exponent) = match lexeme.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (&lexeme[..], None),
+    };
+    let (int, fraction) = match mantissa.split_once('.') {
+        Some((int, fraction)) if decimal(fraction) && (int.is_empty() || decimal(int)) => {
+            (int, fraction)
+        }
+        None if decimal(mantissa) && exponent.is_some() => (mantissa, ""),
+        _ => return None,
+    };
+    let exponent = match exponent {
+        Some(exponent) => {
+            let digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+            if !decimal(digits) {
+                return None;
             }
-            Float::Float3(float3) => {
-                // 0.sub * 10^exponent
-                let sub = float3.sub.value() as f64;
-                let sub = sub / 10_f64.powf(digits(sub));
-                let exponent = float3
-                    .exponent_part
-                    .clone()
-                    .unwrap_or(ExponentPart { value: 0 })
-                    .value as f64;
-                sub * 10_f64.powf(exponent)
+            exponent.parse().ok()?
+        }
+        None => 0,
+    };
+    Some((int.to_string(), fraction.to_string(), exponent))
+}
+
+impl FromPest<'_> for Float {
+    type Rule = Rule;
+
+    type FatalError = from_pest::Void;
+
+    fn from_pest(
+        pest: &mut pest::iterators::Pairs<'_, Self::Rule>,
+    ) -> Result<Self, from_pest::ConversionError<Self::FatalError>> {
+        match pest.peek() {
+            Some(pair) if pair.as_rule() == Rule::float => {
+                let pair = pest.next().unwrap();
+                Float::from_lexeme(pair.as_str()).ok_or(from_pest::ConversionError::NoMatch)
             }
+            _ => Err(from_pest::ConversionError::NoMatch),
         }
     }
 }
 
+/// like `IntRepr`, an overflowing value is `null` in JSON
+#[derive(Serialize, Deserialize)]
+struct FloatRepr {
+    lexeme: String,
+    #[serde(skip_deserializing)]
+    value: f64,
+}
+
 impl Serialize for Float {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        serializer.serialize_f64(self.value())
+        FloatRepr {
+            lexeme: self.lexeme().to_string(),
+            value: self.value(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Float {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let repr = FloatRepr::deserialize(deserializer)?;
+        from_lexeme(Rule::float, &repr.lexeme).map_err(de::Error::custom)
     }
 }
 
-#[derive(Debug, Clone, FromPest, Serialize)]
+#[derive(Debug, Clone, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::number))]
 pub enum SapNumber {
     Float(Float),
+    BigInt(BigInt),
     Int(Int),
 }
 
+/// turns the plain ints out of the `i64` range into a `BigInt`, for
+/// `ParseOptions::promote_ints`
+pub(crate) struct PromoteInts;
+
+impl VisitMut for PromoteInts {
+    fn visit_literal_mut(&mut self, node: &mut Literal) {
+        if let literal::Inner::Number(SapNumber::Int(int)) = &node.inner
+            && try_parse_int(int.lexeme()).is_err()
+            && let Some(big_int) = BigInt::from_lexeme(int.lexeme())
+        {
+            node.inner = literal::Inner::Number(SapNumber::BigInt(big_int));
+        }
+    }
+}
+
 impl PartialEq for SapNumber {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
@@ -206,6 +424,9 @@ impl PartialEq for SapNumber {
                 float1.value() == float2.value()
             }
             (SapNumber::Int(int1), SapNumber::Int(int2)) => int1.value() == int2.value(),
+            (SapNumber::BigInt(big_int1), SapNumber::BigInt(big_int2)) => {
+                big_int1.value == big_int2.value
+            }
             _ => false,
         }
     }
@@ -278,62 +499,141 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_exponent_part() {
-        let pair = SapParser::parse(Rule::exponent_part, "e-10")
+    fn float(source: &str) -> Option<crate::literal::number::Float> {
+        let pair = SapParser::parse(Rule::float, source)
             .unwrap()
             .next()
             .unwrap();
-        let mut pairs = Pairs::single(pair.clone());
-        let exponent_part = crate::literal::number::ExponentPart::from_pest(&mut pairs).unwrap();
-        assert_eq!(exponent_part.value, -10);
+        crate::literal::number::Float::from_pest(&mut Pairs::single(pair)).ok()
     }
 
     #[test]
-    fn test_float1() {
-        let pair = SapParser::parse(Rule::float1, "0b10_10e-10")
-            .unwrap()
-            .next()
-            .unwrap();
-        let mut pairs = Pairs::single(pair.clone());
-        let float1 = crate::literal::number::Float1::from_pest(&mut pairs).unwrap();
-        assert_eq!(float1.value.value(), 10);
-        assert_eq!(float1.exponent_part.value, -10);
+    fn test_float() {
+        assert_eq!(float("1.05").unwrap().value(), 1.05);
+        assert_eq!(float("1_000.000_1E-2").unwrap().value(), 10.000001);
+        assert_eq!(float(".5e1").unwrap().value(), 5.0);
+        assert_eq!(float("2e+3").unwrap().value(), 2000.0);
+        // rounded once, not rebuilt from its parts
+        assert_eq!(float("0.1e-300").unwrap().value(), 1e-301);
+        assert_eq!(
+            float("123456789012345678901234567890.0").unwrap().value(),
+            1.2345678901234568e29
+        );
+        assert_eq!(float("1e400").unwrap().value(), f64::INFINITY);
+        assert_eq!(float("1.05").unwrap().lexeme(), "1.05");
     }
 
     #[test]
-    fn test_float2() {
-        let pair = SapParser::parse(Rule::float2, "0b10_10.0b10e-0b10_10")
-            .unwrap()
-            .next()
-            .unwrap();
-        let mut pairs = Pairs::single(pair.clone());
-        let float2 = crate::literal::number::Float2::from_pest(&mut pairs).unwrap();
-        assert_eq!(float2.value.value(), 10);
-        assert_eq!(float2.sub.value(), 2);
-        assert_eq!(float2.exponent_part.unwrap().value, -10);
+    fn test_float_exact() {
+        use num::{BigInt, BigRational};
+
+        let ratio = |n: i64, d: i64| BigRational::new(BigInt::from(n), BigInt::from(d));
+        let exact = |source| float(source).unwrap().exact();
+        assert_eq!(exact("1.05"), Some(ratio(21, 20)));
+        assert_eq!(exact(".5e-1"), Some(ratio(1, 20)));
+        assert_eq!(exact("1_2.5e2"), Some(ratio(1250, 1)));
+        assert_eq!(exact("0.1"), Some(ratio(1, 10)));
+        assert_eq!(exact("0.0e99999999"), Some(ratio(0, 1)));
+        // in range of an `f64` only thanks to its digits
+        let tiny = format!("{}e-400", "1".repeat(300));
+        assert!(exact(&tiny).is_some());
     }
 
     #[test]
-    fn test_float3() {
-        let pair = SapParser::parse(Rule::float3, ".0b10e-10")
-            .unwrap()
-            .next()
-            .unwrap();
-        let mut pairs = Pairs::single(pair.clone());
-        let float3 = crate::literal::number::Float3::from_pest(&mut pairs).unwrap();
-        assert_eq!(float3.sub.value(), 2);
-        assert_eq!(float3.exponent_part.unwrap().value, -10);
+    fn test_float_extreme_exponent() {
+        for (source, value) in [
+            ("0.5e-9223372036854775808", 0.0),
+            ("1e-9223372036854775808", 0.0),
+            ("1e9223372036854775807", f64::INFINITY),
+            ("1e500", f64::INFINITY),
+        ] {
+            assert!(crate::parse_expr(source).is_ok());
+            let float = float(source).unwrap();
+            assert_eq!(float.value(), value);
+            assert_eq!(float.exact(), None);
+        }
     }
 
     #[test]
-    fn test_float() {
-        let pair = SapParser::parse(Rule::float, "0b10_10e-10")
+    fn test_non_decimal_float() {
+        assert!(float("0b10_10.0b10").is_none());
+        assert!(float("1.0x1").is_none());
+        assert!(float("1e0o7").is_none());
+        assert!(float("1e99999999999999999999").is_none());
+        assert!(crate::literal::number::Float::from_lexeme("1").is_none());
+        assert!(crate::literal::number::Float::from_lexeme("-1.5").is_none());
+    }
+
+    #[test]
+    fn test_serde_lexeme() {
+        let pair = SapParser::parse(Rule::number, "0x1_F")
             .unwrap()
             .next()
             .unwrap();
-        let mut pairs = Pairs::single(pair.clone());
-        let float = crate::literal::number::Float::from_pest(&mut pairs).unwrap();
-        assert_eq!(float.value(), 0.000000001);
+        let number =
+            crate::literal::number::SapNumber::from_pest(&mut Pairs::single(pair)).unwrap();
+        let json = serde_json::to_string(&number).unwrap();
+        assert_eq!(json, r#"{"Int":{"radix":16,"lexeme":"0x1_F","value":31}}"#);
+        let back: crate::literal::number::SapNumber = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&back).unwrap(), json);
+
+        let float: crate::literal::number::Float =
+            serde_json::from_str(r#"{"lexeme":"1.50E+2"}"#).unwrap();
+        assert_eq!(float.lexeme(), "1.50E+2");
+        assert!(
+            serde_json::from_str::<crate::literal::number::Int>(r#"{"radix":2,"lexeme":"0x1"}"#)
+                .is_err()
+        );
+        assert!(
+            serde_json::from_str::<crate::literal::number::Int>(r#"{"radix":10,"lexeme":"1 + 1"}"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_big_int() {
+        use crate::{ParseOptions, literal::number::SapNumber, parse_expr, parse_expr_with};
+
+        let number = |source| {
+            let pair = SapParser::parse(Rule::number, source)
+                .unwrap()
+                .next()
+                .unwrap();
+            SapNumber::from_pest(&mut Pairs::single(pair)).unwrap()
+        };
+        let SapNumber::BigInt(big_int) = number("0xFFFF_FFFF_FFFF_FFFF_FFn") else {
+            panic!("not a big int");
+        };
+        assert_eq!(big_int.value.to_string(), "4722366482869645213695");
+        assert_eq!(big_int.radix, 16);
+        assert_eq!(big_int.lexeme, "0xFFFF_FFFF_FFFF_FFFF_FFn");
+        assert!(matches!(number("12n"), SapNumber::BigInt(_)));
+        assert!(matches!(number("12"), SapNumber::Int(_)));
+
+        // only the plain ints out of range are promoted, in patterns too
+        let promote = ParseOptions { promote_ints: true };
+        let json = |source| serde_json::to_string(&parse_expr_with(source, &promote).unwrap());
+        let promoted = json("[9223372036854775808, 9223372036854775807]").unwrap();
+        assert_eq!(promoted.matches("\"BigInt\"").count(), 1);
+        assert!(
+            json("\\0x1_0000_0000_0000_0000 -> 1")
+                .unwrap()
+                .contains("\"BigInt\"")
+        );
+        assert!(parse_expr("9223372036854775808").is_err());
+
+        let json = serde_json::to_string(&number("0b1_0n")).unwrap();
+        assert_eq!(
+            json,
+            r#"{"BigInt":{"radix":2,"lexeme":"0b1_0n","value":"2"}}"#
+        );
+        let back: SapNumber = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, number("0b1_0n"));
+        assert!(
+            serde_json::from_str::<crate::literal::number::BigInt>(
+                r#"{"radix":10,"lexeme":"-1n"}"#
+            )
+            .is_err()
+        );
     }
 }
@@ -1,11 +1,11 @@
 use pest_ast::FromPest;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{Rule, diagnostics::Diagnostic, expr::Expr, id::Id};
+use crate::{Rule, diagnostics::Diagnostic, expr::Expr, id::Id, preprocessor::comment::doc_before};
 
 use super::string::SapString;
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::object_key))]
 pub enum ObjectKey {
     Id(Id),
@@ -21,14 +21,20 @@ impl ObjectKey {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::object_elem_kv))]
 pub struct ObjectElemKv {
+    #[pest_ast(outer(with(Diagnostic::from_span)))]
+    pub diag: Diagnostic,
     pub key: ObjectKey,
     pub value: Option<Expr>,
+    /// the `///` block right above the element
+    #[pest_ast(outer(with(doc_before)))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::object_body))]
 pub struct ObjectBody {
     #[pest_ast(outer(with(Diagnostic::from_span)))]
@@ -86,4 +92,16 @@ mod tests {
         let object_body = super::ObjectBody::from_pest(&mut pairs).unwrap();
         assert_eq!(object_body.body.len(), 2);
     }
+
+    #[test]
+    fn test_object_elem_doc() {
+        let pair = crate::SapParser::parse(Rule::object_body, "a: 1,\n/// b\n/// doc\nb")
+            .unwrap()
+            .next()
+            .unwrap();
+        let mut pairs = pest::iterators::Pairs::single(pair);
+        let object_body = super::ObjectBody::from_pest(&mut pairs).unwrap();
+        assert_eq!(object_body.body[0].doc, None);
+        assert_eq!(object_body.body[1].doc.as_deref(), Some("b\ndoc"));
+    }
 }
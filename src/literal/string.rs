@@ -1,61 +1,53 @@
 // use crate::Rule;
 // use pest::iterators::{Pair, Pairs};
 use regex::{Captures, Regex};
-use serde::Serialize;
+use serde::{Deserialize, Serialize, de};
 
-fn handle_special_escape(str: String) -> String {
+fn replace_escape(str: &str, regex: &Regex, radix: u32) -> Option<String> {
+    let mut valid = true;
+    let res = regex
+        .replace_all(str, |cap: &Captures| {
+            let digits = &cap[1];
+            match u32::from_str_radix(digits, radix)
+                .ok()
+                .and_then(char::from_u32)
+            {
+                Some(char) => format!("{}", char),
+                None => {
+                    valid = false;
+                    String::new()
+                }
+            }
+        })
+        .to_string();
+    valid.then_some(res)
+}
+
+fn handle_special_escape(str: String) -> Option<String> {
     // hex 8 digit
     let regex_pattern4 = Regex::new(r"\\U([0-9a-fA-F]{8})").unwrap();
     if regex_pattern4.is_match(&str) {
-        return regex_pattern4
-            .replace_all(&str, |cap: &Captures| {
-                let hex = &cap[1];
-                let hex_int = u32::from_str_radix(hex, 16).unwrap();
-                let char = char::from_u32(hex_int).unwrap();
-                format!("{}", char)
-            })
-            .to_string();
+        return replace_escape(&str, &regex_pattern4, 16);
     }
 
     // hex 4 digit
     let regex_pattern3 = Regex::new(r"\\u([0-9a-fA-F]{4})").unwrap();
     if regex_pattern3.is_match(&str) {
-        return regex_pattern3
-            .replace_all(&str, |cap: &Captures| {
-                let hex = &cap[1];
-                let hex_int = u32::from_str_radix(hex, 16).unwrap();
-                let char = char::from_u32(hex_int).unwrap();
-                format!("{}", char)
-            })
-            .to_string();
+        return replace_escape(&str, &regex_pattern3, 16);
     }
 
     // hex 2 digit
     let regex_pattern2 = Regex::new(r"\\x([0-9a-fA-F]{2})").unwrap();
     if regex_pattern2.is_match(&str) {
-        return regex_pattern2
-            .replace_all(&str, |cap: &Captures| {
-                let hex = &cap[1];
-                let hex_int = u32::from_str_radix(hex, 16).unwrap();
-                let char = char::from_u32(hex_int).unwrap();
-                format!("{}", char)
-            })
-            .to_string();
+        return replace_escape(&str, &regex_pattern2, 16);
     }
 
     // ascii_oct_digit
     let regex_pattern1 = Regex::new(r"\\([0-7]{1,3})").unwrap();
     if regex_pattern1.is_match(&str) {
-        regex_pattern1
-            .replace_all(&str, |cap: &Captures| {
-                let oct = &cap[1];
-                let oct_int = u32::from_str_radix(oct, 8).unwrap();
-                let char = char::from_u32(oct_int).unwrap();
-                format!("{}", char)
-            })
-            .to_string()
+        replace_escape(&str, &regex_pattern1, 8)
     } else {
-        str
+        Some(str)
     }
 }
 
@@ -73,10 +65,16 @@ fn handle_c_escape(str: &str) -> String {
         .replace(r#"\""#, "\"")
 }
 
+/// `None` if an escape does not denote a unicode scalar value, e.g. `\uD800`
+pub fn try_handle_escape(str: &str) -> Option<String> {
+    let c_escaped = handle_c_escape(str);
+    let res = handle_special_escape(c_escaped)?;
+    Some(res.replace("\\r", r"\"))
+}
+
+/// invalid escapes are rejected by `error::check_pair` before lowering
 fn handle_escape(str: String) -> String {
-    let c_escaped = handle_c_escape(&str);
-    let res = handle_special_escape(c_escaped);
-    res.replace("\\r", r"\")
+    try_handle_escape(&str).unwrap_or(str)
 }
 
 #[derive(Debug, Clone, FromPest)]
@@ -84,10 +82,14 @@ fn handle_escape(str: String) -> String {
 pub struct EscapedStringFragment {
     #[pest_ast(outer(with(span_to_string), with(handle_escape)))]
     pub value: String,
+    /// the escape sequence as written
+    #[pest_ast(outer(with(span_to_string)))]
+    pub lexeme: String,
 }
 
 use pest_ast::FromPest;
 
+use super::number::from_lexeme;
 use crate::Rule;
 use crate::span_to_string;
 
@@ -122,6 +124,29 @@ impl NormalString {
             .collect::<Vec<String>>()
             .join("")
     }
+
+    /// the quoted literal, the fragments written as they were
+    pub fn to_source(&self) -> String {
+        let mut out = "\"".to_string();
+        for inner in &self.inner {
+            match inner {
+                NormalStringInner::NormalStringFragment(fragment) => out.push_str(&fragment.value),
+                NormalStringInner::EscapedStringFragment(fragment) => {
+                    out.push_str(&fragment.lexeme)
+                }
+            }
+        }
+        out.push('"');
+        out
+    }
+}
+
+/// like `IntRepr`, the lexeme is what is read back
+#[derive(Serialize, Deserialize)]
+struct NormalStringRepr {
+    lexeme: String,
+    #[serde(skip_deserializing)]
+    value: String,
 }
 
 impl Serialize for NormalString {
@@ -129,16 +154,21 @@ impl Serialize for NormalString {
     where
         S: serde::Serializer,
     {
-        let inner = self
-            .inner
-            .iter()
-            .map(|inner| match inner {
-                NormalStringInner::NormalStringFragment(fragment) => fragment.value.clone(),
-                NormalStringInner::EscapedStringFragment(fragment) => fragment.value.clone(),
-            })
-            .collect::<Vec<String>>()
-            .join("");
-        serializer.serialize_str(&inner)
+        NormalStringRepr {
+            lexeme: self.to_source(),
+            value: self.value(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for NormalString {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let repr = NormalStringRepr::deserialize(deserializer)?;
+        from_lexeme(Rule::normal_string, &repr.lexeme).map_err(de::Error::custom)
     }
 }
 
@@ -155,6 +185,18 @@ pub struct RawString {
     pub inner: RawStringInner,
 }
 
+impl RawString {
+    /// `r"..."` with as many `#`s as the value needs
+    pub fn to_source(&self) -> String {
+        let value = &self.inner.value;
+        let mut hashes = String::new();
+        while value.contains(&format!("\"{}", hashes)) {
+            hashes.push('#');
+        }
+        format!("r{}\"{}\"{}", hashes, value, hashes)
+    }
+}
+
 impl Serialize for RawString {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -164,7 +206,19 @@ impl Serialize for RawString {
     }
 }
 
-#[derive(Debug, Clone, FromPest, Serialize)]
+impl<'de> Deserialize<'de> for RawString {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        Ok(RawString {
+            inner: RawStringInner { value },
+        })
+    }
+}
+
+#[derive(Debug, Clone, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::string))]
 pub enum SapString {
     NormalString(NormalString),
@@ -231,6 +285,25 @@ mod tests {
         println!("{:?}", normal_string);
     }
 
+    #[test]
+    fn test_normal_string_serde_lexeme() {
+        let source = r#""\x65\u00e9\n\"""#;
+        let pair = SapParser::parse(Rule::normal_string, source)
+            .unwrap()
+            .next()
+            .unwrap();
+        let normal_string = NormalString::from_pest(&mut Pairs::single(pair)).unwrap();
+        let json = serde_json::to_string(&normal_string).unwrap();
+        assert_eq!(
+            json,
+            r#"{"lexeme":"\"\\x65\\u00e9\\n\\\"\"","value":"eé\n\""}"#
+        );
+        let back: NormalString = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.to_source(), source);
+        assert_eq!(back.value(), "eé\n\"");
+        assert!(serde_json::from_str::<NormalString>(r#"{"lexeme":"\"a\" + 1"}"#).is_err());
+    }
+
     #[test]
     fn test_parse_raw_string() {
         let str = r###"r##"hello
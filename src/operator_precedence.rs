@@ -4,66 +4,218 @@ use pest::pratt_parser::{Assoc, Op, PrattParser};
 
 use crate::Rule;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Affix {
+    Prefix,
+    Infix(Assoc),
+    Postfix,
+}
+
+use Affix::{Infix, Postfix, Prefix};
+
 // precedence the higher the weaker
+/// the operators of `pratt_parser`, one entry per level from the weakest, the
+/// unparser and the formatter parenthesize by the same levels
+pub const LEVELS: &[&[(Rule, Affix)]] = &[
+    // finally
+    &[(Rule::prefix_annotative_macro_call, Prefix)],
+    // level 18 assign related
+    &[
+        (Rule::infix_assign, Infix(Assoc::Right)),
+        (Rule::infix_set, Infix(Assoc::Right)),
+        (Rule::infix_assign_yield, Infix(Assoc::Right)),
+        (Rule::infix_assign_slot, Infix(Assoc::Right)),
+    ],
+    // level 17 _ ? _ : _
+    &[(Rule::postfix_trinary_op, Postfix)],
+    &[(Rule::infix_match_equals, Infix(Assoc::Right))],
+    // lelve 16 _ ($ |>) _
+    &[
+        (Rule::infix_pipe, Infix(Assoc::Left)),
+        (Rule::infix_find_and_call_with_this, Infix(Assoc::Left)),
+    ],
+    // level 15 _ ~id~ _
+    &[(Rule::infix_function, Infix(Assoc::Left))],
+    // level 14 <- _
+    &[(Rule::prefix_yield, Prefix)],
+    // level 13 _ <: _
+    &[(Rule::infix_extends, Infix(Assoc::Right))],
+    // level 12 _ || _
+    &[(Rule::infix_or, Infix(Assoc::Left))],
+    // level 11 _ && _
+    &[(Rule::infix_and, Infix(Assoc::Left))],
+    // level 10 _ | _
+    &[(Rule::infix_bit_or, Infix(Assoc::Left))],
+    // level 9 _ ^ _
+    &[(Rule::infix_bit_xor, Infix(Assoc::Left))],
+    // level 8 _ & _
+    &[(Rule::infix_bit_and, Infix(Assoc::Left))],
+    // level 7 _ (== !=) _
+    &[
+        (Rule::infix_eq, Infix(Assoc::Left)),
+        (Rule::infix_neq, Infix(Assoc::Left)),
+    ],
+    // level 6 _ (< > <= >= .?) _
+    &[
+        (Rule::infix_lt, Infix(Assoc::Left)),
+        (Rule::infix_gt, Infix(Assoc::Left)),
+        (Rule::infix_le, Infix(Assoc::Left)),
+        (Rule::infix_ge, Infix(Assoc::Left)),
+        (Rule::infix_has_field, Infix(Assoc::Left)),
+    ],
+    // level 5 _ (<< >>) _
+    &[
+        (Rule::infix_bit_shift_l, Infix(Assoc::Left)),
+        (Rule::infix_bit_shift_r, Infix(Assoc::Left)),
+    ],
+    // level 4 _ (+ -) _
+    &[
+        (Rule::infix_add, Infix(Assoc::Left)),
+        (Rule::infix_sub, Infix(Assoc::Left)),
+    ],
+    // level3 _ (* / %) _
+    &[
+        (Rule::infix_mul, Infix(Assoc::Left)),
+        (Rule::infix_div, Infix(Assoc::Left)),
+        (Rule::infix_mod, Infix(Assoc::Left)),
+    ],
+    // level 2 (- ! ~) _
+    &[
+        (Rule::prefix_not, Prefix),
+        (Rule::prefix_neg, Prefix),
+        (Rule::prefix_bit_not, Prefix),
+    ],
+    // level 1 postfixes
+    &[(Rule::postfix_ml_app_param, Postfix)],
+    &[
+        (Rule::postfix_slice, Postfix),
+        (Rule::postfix_index, Postfix),
+        (Rule::postfix_access, Postfix),
+        (Rule::postfix_length, Postfix),
+        (Rule::postfix_c_app_params, Postfix),
+    ],
+];
+
+/// the level of the operator parsed by `rule`, from 1 for the weakest to
+/// `LEVELS.len()`, and its affix
+pub fn level(rule: Rule) -> Option<(u8, Affix)> {
+    LEVELS.iter().zip(1..).find_map(|(ops, level)| {
+        ops.iter()
+            .find(|(op, _)| *op == rule)
+            .map(|&(_, affix)| (level, affix))
+    })
+}
+
 static PRATT_PARSER: OnceLock<PrattParser<Rule>> = OnceLock::new();
 
 pub fn pratt_parser() -> &'static PrattParser<Rule> {
     PRATT_PARSER.get_or_init(|| {
-        PrattParser::new()
-            // finally
-            .op(Op::prefix(Rule::prefix_annotative_macro_call))
-            // level 18 assign related
-            .op(Op::infix(Rule::infix_assign, Assoc::Right)
-                | Op::infix(Rule::infix_set, Assoc::Right)
-                | Op::infix(Rule::infix_assign_yield, Assoc::Right)
-                | Op::infix(Rule::infix_assign_slot, Assoc::Right))
-            // level 17 _ ? _ : _
-            .op(Op::postfix(Rule::postfix_trinary_op))
-            .op(Op::infix(Rule::infix_match_equals, Assoc::Right))
-            // lelve 16 _ ($ |>) _
-            .op(Op::infix(Rule::infix_pipe, Assoc::Left)
-                | Op::infix(Rule::infix_find_and_call_with_this, Assoc::Left))
-            // level 15 _ ~id~ _
-            .op(Op::infix(Rule::infix_function, Assoc::Left))
-            // level 14 <- _
-            .op(Op::prefix(Rule::prefix_yield))
-            // level 13 _ <: _
-            .op(Op::infix(Rule::infix_extends, Assoc::Right))
-            // level 12 _ || _
-            .op(Op::infix(Rule::infix_or, Assoc::Left))
-            // level 11 _ && _
-            .op(Op::infix(Rule::infix_and, Assoc::Left))
-            // level 10 _ | _
-            .op(Op::infix(Rule::infix_bit_or, Assoc::Left))
-            // level 9 _ ^ _
-            .op(Op::infix(Rule::infix_bit_xor, Assoc::Left))
-            // level 8 _ & _
-            .op(Op::infix(Rule::infix_bit_and, Assoc::Left))
-            // level 7 _ (== !=) _
-            .op(Op::infix(Rule::infix_eq, Assoc::Left) | Op::infix(Rule::infix_neq, Assoc::Left))
-            // level 6 _ (< > <= >=) _
-            .op(Op::infix(Rule::infix_lt, Assoc::Left)
-                | Op::infix(Rule::infix_gt, Assoc::Left)
-                | Op::infix(Rule::infix_le, Assoc::Left)
-                | Op::infix(Rule::infix_ge, Assoc::Left))
-            // level 5 _ (<< >>) _
-            .op(Op::infix(Rule::infix_bit_shift_l, Assoc::Left)
-                | Op::infix(Rule::infix_bit_shift_r, Assoc::Left))
-            // level 4 _ (+ -) _
-            .op(Op::infix(Rule::infix_add, Assoc::Left) | Op::infix(Rule::infix_sub, Assoc::Left))
-            // level3 _ (* / %) _
-            .op(Op::infix(Rule::infix_mul, Assoc::Left)
-                | Op::infix(Rule::infix_div, Assoc::Left)
-                | Op::infix(Rule::infix_mod, Assoc::Left))
-            // level 2 (- ! ~) _
-            .op(Op::prefix(Rule::prefix_not)
-                | Op::prefix(Rule::prefix_neg)
-                | Op::prefix(Rule::prefix_bit_not))
-            // level 1 postfixes
-            .op(Op::postfix(Rule::postfix_ml_app_param))
-            .op(Op::postfix(Rule::postfix_slice)
-                | Op::postfix(Rule::postfix_index)
-                | Op::postfix(Rule::postfix_access)
-                | Op::postfix(Rule::postfix_c_app_params))
+        LEVELS.iter().fold(PrattParser::new(), |pratt, ops| {
+            let op = ops
+                .iter()
+                .map(|&(rule, affix)| match affix {
+                    Prefix => Op::prefix(rule),
+                    Infix(assoc) => Op::infix(rule, assoc),
+                    Postfix => Op::postfix(rule),
+                })
+                .reduce(|a, b| a | b)
+                .expect("a level has an operator");
+            pratt.op(op)
+        })
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::{
+        expr::{
+            infix::Infix as InfixOp, postfix::Postfix as PostfixOp, prefix::Prefix as PrefixOp,
+        },
+        parse_expr,
+        visit::{self, Visit},
+    };
+
+    #[derive(Default)]
+    struct Operators(Vec<(Rule, Affix)>);
+
+    impl Visit<'_> for Operators {
+        fn visit_prefix(&mut self, node: &PrefixOp) {
+            self.0.push((node.rule(), Prefix));
+            visit::visit_prefix(self, node);
+        }
+        fn visit_infix(&mut self, node: &InfixOp) {
+            self.0.push((node.rule(), Infix(Assoc::Left)));
+            visit::visit_infix(self, node);
+        }
+        fn visit_postfix(&mut self, node: &PostfixOp) {
+            self.0.push((node.rule(), Postfix));
+            visit::visit_postfix(self, node);
+        }
+    }
+
+    #[test]
+    fn test_every_operator_has_a_level() {
+        let sources = [
+            "@@m a",
+            "a = b",
+            "a := b",
+            "a =<= b",
+            "a ::= b",
+            "c ? a : b",
+            "a ?= b",
+            "a |> b",
+            "a $ b",
+            "a ~f~ b",
+            "<- a",
+            "a <: b",
+            "a || b",
+            "a && b",
+            "a | b",
+            "a ^ b",
+            "a & b",
+            "a == b",
+            "a != b",
+            "a < b",
+            "a > b",
+            "a <= b",
+            "a >= b",
+            "a .? b",
+            "a << b",
+            "a >> b",
+            "a + b",
+            "a -\nb",
+            "a * b",
+            "a / b",
+            "a % b",
+            "!a",
+            "-a",
+            "~a",
+            "a[b:c]",
+            "a[b]",
+            "a.b",
+            "a.|",
+        ];
+        let mut operators = Operators::default();
+        for source in sources {
+            operators.visit_expr(&parse_expr(source).unwrap());
+        }
+        for &(rule, affix) in &operators.0 {
+            let (_, level_affix) = level(rule).unwrap_or_else(|| panic!("{rule:?}"));
+            match (affix, level_affix) {
+                (Infix(_), Infix(_)) | (Prefix, Prefix) | (Postfix, Postfix) => {}
+                _ => panic!("{rule:?} is {affix:?} but {level_affix:?} in LEVELS"),
+            }
+        }
+        // the application postfixes are lowered to `CApply` and `MLApply`
+        let lowered: BTreeSet<_> = operators.0.iter().map(|(rule, _)| *rule).collect();
+        let table: BTreeSet<_> = LEVELS
+            .iter()
+            .flat_map(|ops| ops.iter().map(|(rule, _)| *rule))
+            .filter(|rule| ![Rule::postfix_c_app_params, Rule::postfix_ml_app_param].contains(rule))
+            .collect();
+        assert_eq!(lowered, table);
+    }
+}
@@ -1,29 +1,36 @@
 use pest_ast::FromPest;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{Rule, diagnostics::Diagnostic};
 
 use super::{EclipsePattern, Pattern};
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::array_pattern_elem))]
 pub enum ArrayPatternElem {
     EclipsePattern(EclipsePattern),
     Pattern(Pattern),
 }
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::array_pattern_body))]
 pub struct ArrayPatternBody {
     pub elems: Vec<ArrayPatternElem>,
 }
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::array_pattern))]
 pub struct ArrayPattern {
     #[pest_ast(outer(with(Diagnostic::from_span)))]
     pub diag: Diagnostic,
-    pub body: ArrayPatternBody,
+    /// `None` is the empty `^[]`
+    pub body: Option<ArrayPatternBody>,
+}
+
+impl ArrayPattern {
+    pub fn elems(&self) -> &[ArrayPatternElem] {
+        self.body.as_ref().map_or(&[], |body| &body.elems)
+    }
 }
 
 #[cfg(test)]
@@ -98,9 +105,9 @@ mod tests {
         .unwrap();
         let array_pattern =
             ArrayPattern::from_pest(&mut pest::iterators::Pairs::single(pair)).unwrap();
-        assert_eq!(array_pattern.body.elems.len(), 2);
+        assert_eq!(array_pattern.elems().len(), 2);
         assert_eq!(
-            array_pattern.body.elems[0],
+            array_pattern.elems()[0],
             ArrayPatternElem::EclipsePattern(EclipsePattern {
                 value: Id::NormalId(NormalId {
                     value: "a".to_string()
@@ -109,7 +116,7 @@ mod tests {
             })
         );
         assert_eq!(
-            array_pattern.body.elems[1],
+            array_pattern.elems()[1],
             ArrayPatternElem::EclipsePattern(EclipsePattern {
                 value: Id::NormalId(NormalId {
                     value: "b".to_string()
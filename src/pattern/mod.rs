@@ -4,11 +4,11 @@ pub mod object;
 use array::ArrayPattern;
 use object::ObjectPattern;
 use pest_ast::FromPest;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{Rule, diagnostics::Diagnostic, id::Id, literal::Literal};
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::eclipse_pattern))]
 pub struct EclipsePattern {
     #[pest_ast(outer(with(Diagnostic::from_span)))]
@@ -16,7 +16,7 @@ pub struct EclipsePattern {
     pub value: Id,
 }
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::pattern))]
 pub enum Pattern {
     Id(Id),
@@ -45,7 +45,7 @@ mod tests {
 
     #[test]
     fn test_pattern() {
-        let pair = crate::SapParser::parse(Rule::pattern, "^{c: ^[a,...b]")
+        let pair = crate::SapParser::parse(Rule::pattern, "^{c: ^[a,...b]}")
             .unwrap()
             .next()
             .unwrap();
@@ -1,36 +1,51 @@
 use pest_ast::FromPest;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{Rule, diagnostics::Diagnostic, literal::object::ObjectKey};
+use crate::{
+    Rule, diagnostics::Diagnostic, literal::object::ObjectKey, preprocessor::comment::doc_before,
+};
 
 use super::{EclipsePattern, Pattern};
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::object_pattern_kv))]
 pub struct ObjectPatternKv {
+    #[pest_ast(outer(with(Diagnostic::from_span)))]
+    pub diag: Diagnostic,
     pub key: ObjectKey,
     pub value: Option<Pattern>,
+    /// the `///` block right above the element
+    #[pest_ast(outer(with(doc_before)))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::object_pattern_elem))]
 pub enum ObjectPatternElem {
     ObjectPatternKv(ObjectPatternKv),
     EclipsePattern(EclipsePattern),
 }
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::object_pattern_body))]
 pub struct ObjectPatternBody {
     pub body: Vec<ObjectPatternElem>,
 }
 
-#[derive(Debug, Clone, PartialEq, FromPest, Serialize)]
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
 #[pest_ast(rule(Rule::object_pattern))]
 pub struct ObjectPattern {
     #[pest_ast(outer(with(Diagnostic::from_span)))]
     pub diag: Diagnostic,
-    pub body: ObjectPatternBody,
+    /// `None` is the empty `^{}`
+    pub body: Option<ObjectPatternBody>,
+}
+
+impl ObjectPattern {
+    pub fn elems(&self) -> &[ObjectPatternElem] {
+        self.body.as_ref().map_or(&[], |body| &body.body)
+    }
 }
 
 #[cfg(test)]
@@ -74,4 +89,18 @@ mod tests {
         let object_pattern_body = super::ObjectPatternBody::from_pest(&mut pairs).unwrap();
         assert_eq!(object_pattern_body.body.len(), 2);
     }
+
+    #[test]
+    fn test_object_pattern_kv_doc() {
+        let pair = crate::SapParser::parse(Rule::object_pattern, "^{\n  /// a\n  a : 1, ...b\n}")
+            .unwrap()
+            .next()
+            .unwrap();
+        let mut pairs = pest::iterators::Pairs::single(pair);
+        let object_pattern = super::ObjectPattern::from_pest(&mut pairs).unwrap();
+        let super::ObjectPatternElem::ObjectPatternKv(kv) = &object_pattern.elems()[0] else {
+            panic!("expected a key value pattern");
+        };
+        assert_eq!(kv.doc.as_deref(), Some("a"));
+    }
 }
@@ -1 +1,500 @@
+//! comment preprocessor, see `Comment_Preprocessor.md`
+//!
+//! the grammar skips every comment through its `COMMENT` rule, so instead of
+//! rewriting the source into `@@comment` macros step 1 classifies the
+//! comments found in the text. The steps run in the documented order and
+//! each one reports through `SapDiagnostic`.
 
+use pest::{Parser, Span, iterators::Pair};
+use serde::Serialize;
+
+use crate::{
+    Rule, SapParser,
+    diagnostics::{Applicability, Diagnostic, Label, SapDiagnostic, Suggestion},
+    recovery::{is_id_char, skip_comment, skip_raw_string, skip_string},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CommentKind {
+    /// `//`, does nothing
+    Comment,
+    /// `///`, documents the expr or object pattern element below it
+    StructuralDoc,
+    /// `//!`, a doc heading
+    DocHeading,
+}
+
+impl CommentKind {
+    pub fn marker(self) -> &'static str {
+        match self {
+            CommentKind::Comment => "//",
+            CommentKind::StructuralDoc => "///",
+            CommentKind::DocHeading => "//!",
+        }
+    }
+
+    fn of(comment: &str) -> Self {
+        if comment.starts_with("//!") {
+            CommentKind::DocHeading
+        } else if comment.starts_with("///") && !comment.starts_with("////") {
+            CommentKind::StructuralDoc
+        } else {
+            CommentKind::Comment
+        }
+    }
+}
+
+/// a single line comment
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Comment {
+    pub kind: CommentKind,
+    /// the text after the marker
+    pub text: String,
+    /// there is code before the comment on its line
+    pub trailing: bool,
+    /// the whole comment, marker included
+    pub diag: Diagnostic,
+}
+
+/// consecutive comment lines of the same kind
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommentBlock {
+    pub kind: CommentKind,
+    /// the lines without their markers, joined by newlines
+    pub text: String,
+    pub lines: Vec<Comment>,
+    pub diag: Diagnostic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentLint {
+    /// code before the comment on the same line
+    MisplacedComment,
+    /// `//` right above a function
+    FunctionComment,
+    /// `///` followed by something that can not be documented
+    DanglingDoc,
+    /// doc text that is not valid markdown
+    InvalidMarkdown,
+}
+
+impl CommentLint {
+    pub fn code(self) -> &'static str {
+        match self {
+            CommentLint::MisplacedComment => "W0001",
+            CommentLint::FunctionComment => "W0002",
+            CommentLint::DanglingDoc => "W0003",
+            CommentLint::InvalidMarkdown => "W0004",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Preprocessed {
+    pub blocks: Vec<CommentBlock>,
+    pub diagnostics: Vec<SapDiagnostic>,
+}
+
+/// run every step on `source`
+pub fn preprocess(source: &str) -> Preprocessed {
+    let comments = classify(source);
+    let mut diagnostics = check_positions(source, &comments);
+    let blocks = concatenate(source, comments);
+    for block in &blocks {
+        diagnostics.extend(validate_markdown(source, block));
+    }
+    diagnostics.sort_by_key(|diagnostic| diagnostic.primary.diag.start_offset);
+    Preprocessed {
+        blocks,
+        diagnostics,
+    }
+}
+
+/// step 1: find every comment outside of strings and classify it
+pub fn classify(source: &str) -> Vec<Comment> {
+    let mut comments = vec![];
+    let mut chars = source.char_indices().peekable();
+    let mut prev: Option<char> = None;
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => skip_string(&mut chars),
+            'r' if !prev.is_some_and(is_id_char) && skip_raw_string(source, i, &mut chars) => {}
+            '/' if chars.peek().is_some_and(|(_, c)| *c == '/') => {
+                skip_comment(&mut chars);
+                let end = chars.peek().map_or(source.len(), |(i, _)| *i);
+                let comment = &source[i..end];
+                let kind = CommentKind::of(comment);
+                comments.push(Comment {
+                    kind,
+                    text: comment[kind.marker().len()..].to_string(),
+                    trailing: !source[line_start(source, i)..i].trim().is_empty(),
+                    diag: Diagnostic::from_offsets(source, i, end),
+                });
+            }
+            _ => {}
+        }
+        prev = Some(c);
+    }
+    comments
+}
+
+/// step 0: check where the comments are placed
+pub fn check_positions(source: &str, comments: &[Comment]) -> Vec<SapDiagnostic> {
+    let mut diagnostics = vec![];
+    for (i, comment) in comments.iter().enumerate() {
+        let start = comment.diag.start_offset;
+        let end = comment.diag.end_offset;
+        if comment.trailing {
+            let line = line_start(source, start);
+            let indent: String = source[line..]
+                .chars()
+                .take_while(|c| *c == ' ' || *c == '\t')
+                .collect();
+            let code = source[line..start].trim_end();
+            diagnostics.push(
+                SapDiagnostic::warning(
+                    CommentLint::MisplacedComment.code(),
+                    "comment should be at the beginning of the line",
+                    Label::new(comment.diag, "comment after code"),
+                )
+                .with_suggestion(Suggestion {
+                    message: "move it above the line".to_string(),
+                    diag: Diagnostic::from_offsets(source, line, end),
+                    replacement: format!("{}{}\n{}", indent, &source[start..end], code),
+                    applicability: Applicability::MachineApplicable,
+                }),
+            );
+            continue;
+        }
+
+        // only the last line of a block is checked against the line below
+        let next_line = line_end(source, end);
+        let continued = comments.get(i + 1).is_some_and(|next| {
+            !next.trailing
+                && next.kind == comment.kind
+                && line_start(source, next.diag.start_offset) == next_line
+        });
+        if continued {
+            continue;
+        }
+        let below = &source[next_line.min(source.len())..];
+        match comment.kind {
+            CommentKind::Comment if is_function_definition(below) => diagnostics.push(
+                SapDiagnostic::warning(
+                    CommentLint::FunctionComment.code(),
+                    "use `///` for function doc",
+                    Label::new(comment.diag, "this comment documents a function"),
+                )
+                .with_suggestion(Suggestion {
+                    message: "turn it into a doc comment".to_string(),
+                    diag: Diagnostic::from_offsets(source, start, start + 2),
+                    replacement: "///".to_string(),
+                    applicability: Applicability::MaybeIncorrect,
+                }),
+            ),
+            CommentKind::StructuralDoc if !is_documentable(below) => diagnostics.push(
+                SapDiagnostic::warning(
+                    CommentLint::DanglingDoc.code(),
+                    "doc comment is not followed by an expression",
+                    Label::new(comment.diag, "this doc comment documents nothing"),
+                )
+                .with_note("`///` must be followed by an expression or an object pattern element")
+                .with_note("use `//` for a plain comment"),
+            ),
+            _ => {}
+        }
+    }
+    diagnostics
+}
+
+/// step 2: merge comments of the same kind on consecutive lines
+pub fn concatenate(source: &str, comments: Vec<Comment>) -> Vec<CommentBlock> {
+    let mut blocks: Vec<CommentBlock> = vec![];
+    for comment in comments {
+        if let Some(block) = blocks.last_mut() {
+            let last = block.lines.last().map(|line| line.diag.end_offset);
+            let follows = last.is_some_and(|end| {
+                line_end(source, end) == line_start(source, comment.diag.start_offset)
+            });
+            if follows && !comment.trailing && block.kind == comment.kind {
+                block.text.push('\n');
+                block.text.push_str(strip_space(&comment.text));
+                block.diag = block.diag.set_end_as(&comment.diag);
+                block.lines.push(comment);
+                continue;
+            }
+        }
+        blocks.push(CommentBlock {
+            kind: comment.kind,
+            text: strip_space(&comment.text).to_string(),
+            diag: comment.diag,
+            lines: vec![comment],
+        });
+    }
+    blocks
+}
+
+/// step 3: check that the text of a doc block is valid markdown
+///
+/// plain `//` comments often hold commented out code, only docs are checked
+pub fn validate_markdown(source: &str, block: &CommentBlock) -> Vec<SapDiagnostic> {
+    if block.kind == CommentKind::Comment {
+        return vec![];
+    }
+    let warning = |start: usize, end: usize, message: &str, label: &str| {
+        SapDiagnostic::warning(
+            CommentLint::InvalidMarkdown.code(),
+            message,
+            Label::new(Diagnostic::from_offsets(source, start, end), label),
+        )
+    };
+
+    let mut diagnostics = vec![];
+    // (offset, fence) of the open code block
+    let mut fence: Option<(usize, String)> = None;
+    // (offset, length) of the open inline code span
+    let mut code: Option<(usize, usize)> = None;
+    for line in &block.lines {
+        let offset = line.diag.start_offset + block.kind.marker().len();
+        let text = line.text.as_str();
+        let trimmed = text.trim_start();
+        let marker: String = trimmed
+            .chars()
+            .take_while(|c| *c == '`' || *c == '~')
+            .collect();
+        let is_fence = marker.len() >= 3 && marker.chars().all(|c| marker.starts_with(c));
+
+        if let Some((_, open)) = &fence {
+            if is_fence && marker.starts_with(open.as_str()) {
+                fence = None;
+            }
+            continue;
+        }
+        if is_fence {
+            fence = Some((line.diag.start_offset, marker));
+            continue;
+        }
+
+        let mut chars = text.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    chars.next();
+                }
+                '`' => {
+                    let mut len = 1;
+                    while chars.next_if(|(_, c)| *c == '`').is_some() {
+                        len += 1;
+                    }
+                    code = match code {
+                        Some((_, open)) if open == len => None,
+                        None => Some((offset + i, len)),
+                        open => open,
+                    };
+                }
+                ']' if code.is_none()
+                    && text[i..].starts_with("](")
+                    && !text[i..].contains(')') =>
+                {
+                    diagnostics.push(warning(
+                        offset + i,
+                        line.diag.end_offset,
+                        "doc comment is not valid markdown",
+                        "link destination is never closed",
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if let Some((start, _)) = fence {
+        diagnostics.push(warning(
+            start,
+            block.diag.end_offset,
+            "doc comment is not valid markdown",
+            "code block is never closed",
+        ));
+    }
+    if let Some((start, len)) = code {
+        diagnostics.push(warning(
+            start,
+            start + len,
+            "doc comment is not valid markdown",
+            "inline code is never closed",
+        ));
+    }
+    diagnostics
+}
+
+/// the `///` block right above the line `span` starts on, if nothing but
+/// whitespace comes before `span` on that line
+pub fn doc_before(span: Span<'_>) -> Option<String> {
+    let input = span.get_input();
+    let mut line = line_start(input, span.start());
+    if !input[line..span.start()].trim().is_empty() {
+        return None;
+    }
+    let mut lines = vec![];
+    while line > 0 {
+        let prev = line_start(input, line - 1);
+        let text = input[prev..line].trim();
+        if CommentKind::of(text) != CommentKind::StructuralDoc {
+            break;
+        }
+        lines.push(strip_space(&text[3..]));
+        line = prev;
+    }
+    lines.reverse();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// the text of every `//!` block in `source`
+pub fn doc_headings(source: &str) -> Vec<String> {
+    concatenate(source, classify(source))
+        .into_iter()
+        .filter(|block| block.kind == CommentKind::DocHeading)
+        .map(|block| block.text)
+        .collect()
+}
+
+fn strip_space(text: &str) -> &str {
+    text.strip_prefix(' ').unwrap_or(text)
+}
+
+fn line_start(source: &str, offset: usize) -> usize {
+    source[..offset].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// the start of the line after the one containing `offset`
+fn line_end(source: &str, offset: usize) -> usize {
+    source[offset..]
+        .find('\n')
+        .map_or(source.len(), |i| offset + i + 1)
+}
+
+/// `source` starts with an expr, an object element or an object pattern
+/// element
+fn is_documentable(source: &str) -> bool {
+    let source = source.trim_start_matches([' ', '\t']);
+    SapParser::parse(Rule::expr, source).is_ok()
+        || SapParser::parse(Rule::object_pattern_elem, source).is_ok()
+}
+
+/// `source` starts with `f = \x -> ..` or `f: \x -> ..`
+fn is_function_definition(source: &str) -> bool {
+    let source = source.trim_start_matches([' ', '\t']);
+    let is_lambda = |primary: Option<Pair<'_, Rule>>| {
+        primary
+            .and_then(|primary| primary.into_inner().next())
+            .is_some_and(|op| op.as_rule() == Rule::lambda_expr)
+    };
+    if let Ok(mut pairs) = SapParser::parse(Rule::expr, source) {
+        let mut inner = pairs
+            .next()
+            .into_iter()
+            .flat_map(|expr| expr.into_inner())
+            .skip(1);
+        if inner
+            .next()
+            .is_some_and(|op| op.as_rule() == Rule::infix_assign)
+            && is_lambda(inner.next())
+        {
+            return true;
+        }
+    }
+    SapParser::parse(Rule::object_elem_kv, source).is_ok_and(|mut pairs| {
+        let value = pairs.next().and_then(|kv| kv.into_inner().nth(1));
+        is_lambda(value.and_then(|expr| expr.into_inner().next()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::apply_suggestions;
+
+    fn codes(source: &str) -> Vec<String> {
+        preprocess(source)
+            .diagnostics
+            .into_iter()
+            .map(|diagnostic| diagnostic.code)
+            .collect()
+    }
+
+    #[test]
+    fn test_classify() {
+        let comments = classify("//! title\n/// doc\n  // note\na = \"// no\" //// x");
+        let kinds: Vec<_> = comments.iter().map(|comment| comment.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                CommentKind::DocHeading,
+                CommentKind::StructuralDoc,
+                CommentKind::Comment,
+                CommentKind::Comment
+            ]
+        );
+        assert_eq!(comments[1].text, " doc");
+        assert!(!comments[2].trailing);
+        assert!(comments[3].trailing);
+    }
+
+    #[test]
+    fn test_misplaced_comment() {
+        let source = "a = 1\n  b = 2 // two\nc";
+        let preprocessed = preprocess(source);
+        assert_eq!(codes(source), ["W0001"]);
+        assert_eq!(
+            apply_suggestions(source, &preprocessed.diagnostics),
+            "a = 1\n  // two\n  b = 2\nc"
+        );
+    }
+
+    #[test]
+    fn test_function_comment() {
+        assert_eq!(codes("// add one\nf = \\x -> x + 1"), ["W0002"]);
+        assert_eq!(codes("o = {\n  // add one\n  f: \\x -> x\n}"), ["W0002"]);
+        assert!(codes("/// add one\nf = \\x -> x + 1").is_empty());
+        assert!(codes("// one\nf = 1").is_empty());
+    }
+
+    #[test]
+    fn test_dangling_doc() {
+        assert_eq!(codes("/// nothing\n\na"), ["W0003"]);
+        assert_eq!(codes("a = {\n  b: 1\n  /// nothing\n}"), ["W0003"]);
+        assert!(codes("/// one\n/// two\na = 1").is_empty());
+        assert!(codes("\\^{\n  /// doc\n  ...rest\n} -> rest").is_empty());
+    }
+
+    #[test]
+    fn test_concatenate() {
+        let source = "//! a\n//! b\n/// c\n/// d\n\n/// e\nx = 1";
+        let blocks = preprocess(source).blocks;
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[0].text, "a\nb");
+        assert_eq!(blocks[1].text, "c\nd");
+        assert_eq!(blocks[1].diag.start_offset, 12);
+        assert_eq!(blocks[1].diag.end_offset, 23);
+        assert_eq!(blocks[2].text, "e");
+    }
+
+    #[test]
+    fn test_doc_before() {
+        let source = "/// a\n  ///b\n\nx\n  /// c\n  /// d\n  y = 1 /// e\nz";
+        let doc = |offset| doc_before(Span::new(source, offset, offset).unwrap());
+        assert_eq!(doc(source.find('x').unwrap()), None);
+        assert_eq!(doc(source.find('y').unwrap()), Some("c\nd".to_string()));
+        assert_eq!(doc(source.find('1').unwrap()), None);
+        assert_eq!(doc(source.find('z').unwrap()), None);
+    }
+
+    #[test]
+    fn test_validate_markdown() {
+        assert_eq!(codes("/// ```\n/// code\nx"), ["W0004"]);
+        assert_eq!(codes("/// `code\nx"), ["W0004"]);
+        assert_eq!(codes("/// [link](url\nx"), ["W0004"]);
+        assert!(codes("/// ```\n/// `a\n/// ```\n/// ``a ` b`` [l](u)\nx").is_empty());
+        assert!(codes("// `not a doc\nx").is_empty());
+    }
+}
@@ -0,0 +1,301 @@
+//! holes are the parameters of a partial application: `f(?a, 1)` is
+//! `\a -> f(a, 1)` and `x |> g ?a 2` is `x |> \a -> g a 2`
+//!
+//! a hole belongs to the nearest application around it, the holes of an
+//! application become the parameters of the lambda in order of appearance,
+//! a hole repeated by name is one parameter. A statement, a block and a
+//! lambda body end the search, a hole reaching one of them is an error and
+//! is left in the tree
+//!
+//! a parameter is renamed when its name is also an id of the application,
+//! which the lambda would capture: `f ?a a` is `\a1 -> f a1 a`
+
+use crate::{
+    diagnostics::{Diagnostic, Label, SapDiagnostic},
+    expr::{Expr, ExprInner, Inner, Primary},
+    function::{Guard, LambdaExpr, NoParamLambdaExpr, TrLambda},
+    id::{Hole, Id, NormalId},
+    pattern::Pattern,
+    program::{Program, Stmt},
+    quotations::Block,
+    visit::{self, Fold, Visit},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoleErrorKind {
+    EscapedHole,
+}
+
+impl HoleErrorKind {
+    pub fn code(self) -> &'static str {
+        match self {
+            HoleErrorKind::EscapedHole => "E0201",
+        }
+    }
+}
+
+/// replaces the holes of every application by a lambda
+pub fn desugar(program: Program) -> (Program, Vec<SapDiagnostic>) {
+    let mut desugar = Desugar::default();
+    let program = desugar.fold_program(program);
+    (program, desugar.diagnostics)
+}
+
+/// like `desugar`, for an expr standing on its own
+pub fn desugar_expr(expr: Expr) -> (Expr, Vec<SapDiagnostic>) {
+    let mut desugar = Desugar::default();
+    let expr = desugar.scope(expr);
+    (expr, desugar.diagnostics)
+}
+
+#[derive(Default)]
+struct Desugar {
+    /// the holes seen since the innermost application or scope
+    holes: Vec<(Id, Diagnostic)>,
+    diagnostics: Vec<SapDiagnostic>,
+}
+
+impl Desugar {
+    /// folds an expr no hole can escape from
+    fn scope(&mut self, expr: Expr) -> Expr {
+        let outer = std::mem::take(&mut self.holes);
+        let expr = self.fold_expr(expr);
+        for (id, diag) in std::mem::replace(&mut self.holes, outer) {
+            self.diagnostics.push(
+                SapDiagnostic::error(
+                    HoleErrorKind::EscapedHole.code(),
+                    format!("hole `?{}` is not inside an application", id.value()),
+                    Label::new(diag, "escapes to the top level"),
+                )
+                .with_note("a hole is a parameter of the nearest application around it"),
+            );
+        }
+        expr
+    }
+}
+
+impl Fold for Desugar {
+    fn fold_expr(&mut self, node: Expr) -> Expr {
+        match &node.inner {
+            ExprInner::Primary(Primary {
+                inner: Inner::Hole(hole),
+                diag,
+            }) => {
+                self.holes.push((hole.id.clone(), *diag));
+                node
+            }
+            ExprInner::CApply(..) | ExprInner::MLApply(..) => {
+                let outer = std::mem::take(&mut self.holes);
+                let node = visit::fold_expr(self, node);
+                let holes = std::mem::replace(&mut self.holes, outer);
+                if holes.is_empty() {
+                    node
+                } else {
+                    lambda(node, holes)
+                }
+            }
+            _ => visit::fold_expr(self, node),
+        }
+    }
+
+    fn fold_stmt(&mut self, node: Stmt) -> Stmt {
+        Stmt {
+            expr: self.scope(node.expr),
+            ..node
+        }
+    }
+
+    fn fold_block(&mut self, node: Block) -> Block {
+        Block {
+            exprs: node
+                .exprs
+                .into_iter()
+                .map(|expr| self.scope(expr))
+                .collect(),
+        }
+    }
+
+    fn fold_no_param_lambda_expr(&mut self, node: NoParamLambdaExpr) -> NoParamLambdaExpr {
+        NoParamLambdaExpr {
+            body: node.body.into_iter().map(|expr| self.scope(expr)).collect(),
+        }
+    }
+
+    fn fold_tr_lambda(&mut self, node: TrLambda) -> TrLambda {
+        TrLambda {
+            guard: node.guard.map(|guard| Guard {
+                expr: Box::new(self.scope(*guard.expr)),
+            }),
+            body: Box::new(self.scope(*node.body)),
+            ..node
+        }
+    }
+}
+
+/// `\holes -> application`, the doc moves to the lambda
+fn lambda(mut application: Expr, holes: Vec<(Id, Diagnostic)>) -> Expr {
+    let mut names: Vec<Id> = vec![];
+    for (id, _) in holes {
+        if !names.contains(&id) {
+            names.push(id);
+        }
+    }
+    let mut ids = Ids::default();
+    ids.visit_expr(&application);
+    // the names a fresh parameter can not take
+    let mut taken: Vec<String> = ids
+        .0
+        .iter()
+        .cloned()
+        .chain(names.iter().map(Id::value))
+        .collect();
+    // each hole with the id of its parameter
+    let mut params: Vec<(Id, Id)> = vec![];
+    for hole in names {
+        let name = hole.value();
+        let param = if ids.0.contains(&name) {
+            let fresh = (1..)
+                .map(|i| format!("{}{}", name, i))
+                .find(|fresh| !taken.contains(fresh))
+                .expect("a free name");
+            taken.push(fresh.clone());
+            Id::NormalId(NormalId { value: fresh })
+        } else {
+            hole.clone()
+        };
+        params.push((hole, param));
+    }
+    let diag = application.diag;
+    let doc = application.doc.take();
+    let body = Fill(&params).fold_expr(application);
+    let tr_lambda = TrLambda {
+        patterns: params
+            .into_iter()
+            .map(|(_, param)| Pattern::Id(param))
+            .collect(),
+        implicit_params: None,
+        guard: None,
+        body: Box::new(body),
+    };
+    let primary = Primary::LambdaExpr(LambdaExpr::TrLambda(tr_lambda), diag);
+    Expr {
+        doc,
+        ..Expr::Primary(primary, diag)
+    }
+}
+
+/// the ids of an application but its holes
+#[derive(Default)]
+struct Ids(Vec<String>);
+
+impl Visit<'_> for Ids {
+    fn visit_id(&mut self, id: &Id) {
+        self.0.push(id.value());
+    }
+    fn visit_hole(&mut self, _hole: &Hole) {}
+}
+
+/// turns the holes of an application into the ids of their parameters, the
+/// holes in a nested scope escaped and stay
+struct Fill<'a>(&'a [(Id, Id)]);
+
+impl Fold for Fill<'_> {
+    fn fold_expr(&mut self, node: Expr) -> Expr {
+        match node.inner {
+            ExprInner::Primary(Primary {
+                inner: Inner::Hole(hole),
+                diag,
+            }) => {
+                let param = self
+                    .0
+                    .iter()
+                    .find(|(id, _)| *id == hole.id)
+                    .map_or(hole.id, |(_, param)| param.clone());
+                Expr {
+                    inner: ExprInner::Primary(Primary::Id(param, diag)),
+                    ..node
+                }
+            }
+            _ => visit::fold_expr(self, node),
+        }
+    }
+
+    fn fold_block(&mut self, node: Block) -> Block {
+        node
+    }
+
+    fn fold_no_param_lambda_expr(&mut self, node: NoParamLambdaExpr) -> NoParamLambdaExpr {
+        node
+    }
+
+    fn fold_tr_lambda(&mut self, node: TrLambda) -> TrLambda {
+        node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{diagnostics::SameShape, parse_expr, parse_file};
+
+    fn desugared(source: &str) -> (String, Vec<String>) {
+        let (expr, diagnostics) = desugar_expr(parse_expr(source).unwrap());
+        let codes = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.code.clone())
+            .collect();
+        (expr.to_source(), codes)
+    }
+
+    #[test]
+    fn test_desugar_application() {
+        assert_eq!(desugared("f(?a, 1)").0, "\\a -> f(a, 1)");
+        assert_eq!(desugared("x |> g ?a 2").0, "x |> \\a -> g a 2");
+        assert_eq!(desugared("f ?b ?a ?b").0, "\\b a -> f b a b");
+        assert_eq!(desugared("f (?a + 1) ([?b])").0, "\\a b -> f (a + 1) ([b])");
+        assert_eq!(desugared("?f x").0, "\\f -> f x");
+    }
+
+    #[test]
+    fn test_desugar_no_capture() {
+        assert_eq!(desugared("f ?a a").0, "\\a1 -> f a1 a");
+        assert_eq!(desugared("f ?a ?a1 a").0, "\\a2 a1 -> f a2 a1 a");
+        assert_eq!(desugared("f ?x (\\y -> x)").0, "\\x1 -> f x1 (\\y -> x)");
+    }
+
+    #[test]
+    fn test_desugar_nearest_application() {
+        assert_eq!(desugared("f (g ?a) ?b").0, "\\b -> f (\\a -> g a) b");
+        assert_eq!(desugared("f ?a + 1").0, "(\\a -> f a) + 1");
+        assert_eq!(
+            desugared("map(\\x -> g x ?y, xs)").0,
+            "map(\\x -> \\y -> g x y, xs)"
+        );
+    }
+
+    #[test]
+    fn test_escaped_hole() {
+        assert_eq!(
+            desugared("?a + 1"),
+            ("?a + 1".to_string(), vec!["E0201".to_string()])
+        );
+        // the hole in the lambda body does not reach `f`
+        assert_eq!(
+            desugared("f ?b (\\x -> ?a)"),
+            (
+                "\\b -> f b (\\x -> ?a)".to_string(),
+                vec!["E0201".to_string()]
+            )
+        );
+        let (_, diagnostics) = desugar(parse_file("a = {\n  ?x\n}\nf ?y").unwrap());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].primary.diag.start_line, 2);
+    }
+
+    #[test]
+    fn test_desugar_keeps_doc() {
+        let (program, _) = desugar(parse_file("/// doc\nf ?a").unwrap());
+        let expected = parse_file("/// doc\n\\a -> f a").unwrap();
+        assert!(program.stmts[0].expr.same_shape(&expected.stmts[0].expr));
+    }
+}
@@ -1,2 +1,4 @@
 pub mod comment;
+pub mod hole;
 pub mod module_system;
+pub mod slot;
@@ -1,2 +1,616 @@
 //! although module system is just a macro, it is a built-in feature because no one
 //! can write a program without using it.
+//!
+//! both forms are annotative macro calls on a top level statement:
+//!
+//! - `@@import("./list") list` binds the module to `list`,
+//!   `@@import("list") ^{map, filter}` destructures its exports
+//! - `@@export name = ..` or `@@export name` exports a binding
+//!
+//! a path starting with `./` or `../` is relative to the importing file, any
+//! other path is looked up in the search path. `.sap` is appended to a path
+//! without extension. Every module is parsed once, however often it is
+//! imported.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::{
+    diagnostics::{Diagnostic, Label, SapDiagnostic},
+    expr::{Expr, ExprInner, Inner, infix::Infix, prefix::Prefix},
+    literal::{self, CompoundLiteral},
+    parse_file_recovering,
+    pattern::{Pattern, object::ObjectPatternElem},
+    program::Program,
+};
+
+/// the `MacroId` of `@@import`
+pub const IMPORT: &str = "@import";
+/// the `MacroId` of `@@export`
+pub const EXPORT: &str = "@export";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleErrorKind {
+    InvalidImport,
+    InvalidExport,
+    UnresolvedModule,
+    ImportCycle,
+    MissingExport,
+}
+
+impl ModuleErrorKind {
+    pub fn code(self) -> &'static str {
+        match self {
+            ModuleErrorKind::InvalidImport => "E0101",
+            ModuleErrorKind::InvalidExport => "E0102",
+            ModuleErrorKind::UnresolvedModule => "E0103",
+            ModuleErrorKind::ImportCycle => "E0104",
+            ModuleErrorKind::MissingExport => "E0105",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct ModuleId(pub usize);
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Import {
+    /// the path as written
+    pub path: String,
+    /// the id or pattern the module is bound to
+    pub binding: Expr,
+    /// the module the path resolved to, filled in by the loader
+    pub module: Option<ModuleId>,
+    pub diag: Diagnostic,
+    /// the path argument
+    pub path_diag: Diagnostic,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Export {
+    pub name: String,
+    pub diag: Diagnostic,
+}
+
+/// the imports and exports among the top level statements of `program`
+pub fn collect(program: &Program) -> (Vec<Import>, Vec<Export>, Vec<SapDiagnostic>) {
+    let mut imports = vec![];
+    let mut exports = vec![];
+    let mut diagnostics = vec![];
+    for stmt in &program.stmts {
+        let ExprInner::Prefix(Prefix::AnnotativeMacroCall(macro_id, arg), operand) =
+            &stmt.expr.inner
+        else {
+            continue;
+        };
+        match macro_id.value.as_str() {
+            IMPORT => match (arg.as_deref().and_then(path_arg), &operand.inner) {
+                (None, _) => diagnostics.push(error(
+                    ModuleErrorKind::InvalidImport,
+                    "`@@import` expects a module path string",
+                    stmt.diag,
+                    "write `@@import(\"path\") name`",
+                )),
+                (Some((path, path_diag)), ExprInner::Primary(primary))
+                    if matches!(primary.inner, Inner::Id(_) | Inner::Pattern(_)) =>
+                {
+                    imports.push(Import {
+                        path,
+                        binding: *operand.clone(),
+                        module: None,
+                        diag: stmt.diag,
+                        path_diag,
+                    })
+                }
+                (Some(_), _) => diagnostics.push(error(
+                    ModuleErrorKind::InvalidImport,
+                    "an import must be bound to a name or a pattern",
+                    operand.diag,
+                    "not a name or a pattern",
+                )),
+            },
+            EXPORT => match export_name(operand) {
+                Some(name) if arg.is_none() => exports.push(Export {
+                    name,
+                    diag: stmt.diag,
+                }),
+                _ => diagnostics.push(error(
+                    ModuleErrorKind::InvalidExport,
+                    "`@@export` expects a binding",
+                    stmt.diag,
+                    "write `@@export name = ..` or `@@export name`",
+                )),
+            },
+            _ => {}
+        }
+    }
+    (imports, exports, diagnostics)
+}
+
+fn error(kind: ModuleErrorKind, message: &str, diag: Diagnostic, label: &str) -> SapDiagnostic {
+    SapDiagnostic::error(kind.code(), message, Label::new(diag, label))
+}
+
+fn path_arg(arg: &Expr) -> Option<(String, Diagnostic)> {
+    Some((string_value(arg)?, arg.diag))
+}
+
+fn string_value(expr: &Expr) -> Option<String> {
+    match &expr.inner {
+        ExprInner::Primary(primary) => match &primary.inner {
+            Inner::CompoundLiteral(CompoundLiteral::Literal(literal)) => match &literal.inner {
+                literal::Inner::String(string) => Some(string.value()),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn export_name(expr: &Expr) -> Option<String> {
+    match &expr.inner {
+        ExprInner::Infix(Infix::Assign, lhs, _) => export_name(lhs),
+        ExprInner::Primary(primary) => match &primary.inner {
+            Inner::Id(id) => Some(id.value()),
+            Inner::Pattern(Pattern::Id(id)) => Some(id.value()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Module {
+    pub id: ModuleId,
+    /// the path the module was first imported by, or the entry path
+    pub name: String,
+    pub path: PathBuf,
+    pub source: String,
+    pub program: Program,
+    pub imports: Vec<Import>,
+    pub exports: Vec<Export>,
+}
+
+/// a diagnostic in one of the files of a module graph
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileDiagnostic {
+    pub path: PathBuf,
+    pub diagnostic: SapDiagnostic,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ModuleLoader {
+    /// directories searched for paths not starting with `./` or `../`
+    pub search_path: Vec<PathBuf>,
+}
+
+impl ModuleLoader {
+    pub fn new(search_path: Vec<PathBuf>) -> Self {
+        Self { search_path }
+    }
+
+    /// the file `path` refers to when imported from `from`
+    pub fn resolve(&self, from: &Path, path: &str) -> Option<PathBuf> {
+        let mut file = PathBuf::from(path);
+        if file.extension().is_none() {
+            file.set_extension("sap");
+        }
+        let candidates = if path.starts_with("./") || path.starts_with("../") {
+            let dir = from.parent().unwrap_or(Path::new("."));
+            vec![dir.join(file)]
+        } else if file.is_absolute() {
+            vec![file]
+        } else {
+            self.search_path.iter().map(|dir| dir.join(&file)).collect()
+        };
+        candidates
+            .into_iter()
+            .find(|candidate| candidate.is_file())
+            .and_then(|candidate| candidate.canonicalize().ok())
+    }
+
+    /// load `entry` and everything it imports
+    pub fn load(&self, entry: &Path) -> ModuleGraph {
+        let mut graph = ModuleGraph::default();
+        match entry.canonicalize() {
+            Ok(path) => {
+                let name = entry.display().to_string();
+                graph.entry = self.visit(&mut graph, path, name, None, &mut vec![]);
+            }
+            Err(err) => graph.diagnostics.push(FileDiagnostic {
+                path: entry.to_path_buf(),
+                diagnostic: error(
+                    ModuleErrorKind::UnresolvedModule,
+                    &format!("can not read `{}`: {}", entry.display(), err),
+                    Diagnostic::file_start(),
+                    "the entry module",
+                ),
+            }),
+        }
+        graph.check_exports();
+        graph
+    }
+
+    /// `stack` holds the modules being loaded with the import each one is
+    /// following, `import` is the path argument of the last one, `None` for
+    /// the entry
+    fn visit(
+        &self,
+        graph: &mut ModuleGraph,
+        path: PathBuf,
+        name: String,
+        import: Option<Diagnostic>,
+        stack: &mut Vec<(ModuleId, Diagnostic)>,
+    ) -> Option<ModuleId> {
+        if let Some(id) = graph.find(&path) {
+            return Some(id);
+        }
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(err) => {
+                let (file, diag, label) = match (import, stack.last()) {
+                    (Some(diag), Some(&(importer, _))) => {
+                        (graph.module(importer).path.clone(), diag, "imported here")
+                    }
+                    _ => (path.clone(), Diagnostic::file_start(), "the entry module"),
+                };
+                graph.diagnostics.push(FileDiagnostic {
+                    path: file,
+                    diagnostic: error(
+                        ModuleErrorKind::UnresolvedModule,
+                        &format!("can not read `{}`: {}", name, err),
+                        diag,
+                        label,
+                    ),
+                });
+                return None;
+            }
+        };
+
+        let (program, errors) = parse_file_recovering(&source);
+        let (imports, exports, diagnostics) = collect(&program);
+        let id = ModuleId(graph.modules.len());
+        graph.diagnostics.extend(
+            errors
+                .iter()
+                .map(SapDiagnostic::from)
+                .chain(diagnostics)
+                .map(|diagnostic| FileDiagnostic {
+                    path: path.clone(),
+                    diagnostic,
+                }),
+        );
+        graph.by_path.insert(path.clone(), id);
+        graph.modules.push(Module {
+            id,
+            name,
+            path: path.clone(),
+            source,
+            program,
+            imports: imports.clone(),
+            exports,
+        });
+
+        for (i, import) in imports.iter().enumerate() {
+            let Some(target) = self.resolve(&path, &import.path) else {
+                let searched = self
+                    .search_path
+                    .iter()
+                    .map(|dir| format!("`{}`", dir.display()))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let mut diagnostic = error(
+                    ModuleErrorKind::UnresolvedModule,
+                    &format!("can not find module `{}`", import.path),
+                    import.diag,
+                    "imported here",
+                );
+                if !import.path.starts_with('.') {
+                    diagnostic = diagnostic.with_note(format!("searched in: {}", searched));
+                }
+                graph.diagnostics.push(FileDiagnostic {
+                    path: path.clone(),
+                    diagnostic,
+                });
+                continue;
+            };
+
+            // on the stack before the check, a module may import itself
+            stack.push((id, import.diag));
+            if let Some(start) = graph
+                .find(&target)
+                .and_then(|dep| stack.iter().position(|(id, _)| *id == dep))
+            {
+                let diagnostic = graph.cycle(&stack[start..], &import.path);
+                stack.pop();
+                graph.diagnostics.push(FileDiagnostic {
+                    path: path.clone(),
+                    diagnostic,
+                });
+                graph.modules[id.0].imports[i].module = graph.find(&target);
+                continue;
+            }
+
+            let dep = self.visit(
+                graph,
+                target,
+                import.path.clone(),
+                Some(import.path_diag),
+                stack,
+            );
+            stack.pop();
+            graph.modules[id.0].imports[i].module = dep;
+        }
+        Some(id)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ModuleGraph {
+    pub modules: Vec<Module>,
+    pub entry: Option<ModuleId>,
+    pub diagnostics: Vec<FileDiagnostic>,
+    by_path: HashMap<PathBuf, ModuleId>,
+}
+
+impl ModuleGraph {
+    pub fn module(&self, id: ModuleId) -> &Module {
+        &self.modules[id.0]
+    }
+
+    /// the module loaded from the canonical `path`
+    pub fn find(&self, path: &Path) -> Option<ModuleId> {
+        self.by_path.get(path).copied()
+    }
+
+    /// the modules `id` imports, in import order
+    pub fn dependencies(&self, id: ModuleId) -> impl Iterator<Item = ModuleId> + '_ {
+        self.module(id)
+            .imports
+            .iter()
+            .filter_map(|import| import.module)
+    }
+
+    /// the modules importing `id`
+    pub fn dependents(&self, id: ModuleId) -> impl Iterator<Item = ModuleId> + '_ {
+        self.modules
+            .iter()
+            .filter(move |module| {
+                module
+                    .imports
+                    .iter()
+                    .any(|import| import.module == Some(id))
+            })
+            .map(|module| module.id)
+    }
+
+    /// every module after the modules it imports, a cycle is cut at the
+    /// import closing it
+    pub fn topological_order(&self) -> Vec<ModuleId> {
+        fn visit(
+            graph: &ModuleGraph,
+            id: ModuleId,
+            seen: &mut Vec<bool>,
+            order: &mut Vec<ModuleId>,
+        ) {
+            if seen[id.0] {
+                return;
+            }
+            seen[id.0] = true;
+            for dep in graph.dependencies(id) {
+                visit(graph, dep, seen, order);
+            }
+            order.push(id);
+        }
+
+        let mut seen = vec![false; self.modules.len()];
+        let mut order = vec![];
+        for module in &self.modules {
+            visit(self, module.id, &mut seen, &mut order);
+        }
+        order
+    }
+
+    fn cycle(&self, chain: &[(ModuleId, Diagnostic)], closing: &str) -> SapDiagnostic {
+        let (first, _) = chain[0];
+        let (_, diag) = chain[chain.len() - 1];
+        let mut diagnostic = error(
+            ModuleErrorKind::ImportCycle,
+            &format!("import cycle: `{}` imports itself", self.module(first).name),
+            diag,
+            format!("`{}` is imported here", closing).as_str(),
+        );
+        for (i, (id, diag)) in chain.iter().enumerate() {
+            let next = chain.get(i + 1).map_or(closing.to_string(), |(next, _)| {
+                self.module(*next).name.clone()
+            });
+            diagnostic = diagnostic.with_note(format!(
+                "`{}` imports `{}` at {}:{}",
+                self.module(*id).name,
+                next,
+                diag.start_line,
+                diag.start_col
+            ));
+        }
+        diagnostic
+    }
+
+    /// every name destructured from an import must be exported
+    fn check_exports(&mut self) {
+        let mut diagnostics = vec![];
+        for module in &self.modules {
+            for import in &module.imports {
+                let (Some(dep), ExprInner::Primary(primary)) =
+                    (import.module, &import.binding.inner)
+                else {
+                    continue;
+                };
+                let Inner::Pattern(Pattern::ObjectPattern(pattern)) = &primary.inner else {
+                    continue;
+                };
+                let exports = &self.module(dep).exports;
+                for elem in pattern.elems() {
+                    let ObjectPatternElem::ObjectPatternKv(kv) = elem else {
+                        continue;
+                    };
+                    let key = kv.key.value();
+                    if !exports.iter().any(|export| export.name == key) {
+                        diagnostics.push(FileDiagnostic {
+                            path: module.path.clone(),
+                            diagnostic: error(
+                                ModuleErrorKind::MissingExport,
+                                &format!("module `{}` does not export `{}`", import.path, key),
+                                pattern.diag,
+                                &format!("`{}` is not exported", key),
+                            ),
+                        });
+                    }
+                }
+            }
+        }
+        self.diagnostics.extend(diagnostics);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_file, test_util::TempDir};
+
+    /// a fresh directory holding `files`
+    fn write(name: &str, files: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new(&format!("modules-{}", name));
+        for (path, source) in files {
+            dir.write(path, source);
+        }
+        dir
+    }
+
+    fn codes(graph: &ModuleGraph) -> Vec<&str> {
+        graph
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.diagnostic.code.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_collect() {
+        let program = parse_file(
+            "@@import(\"./a\") a\n@@import(\"b\") ^{x, ...rest}\n@@export f = \\x -> x\n@@export g\n@@import c\n@@export 1",
+        )
+        .unwrap();
+        let (imports, exports, diagnostics) = collect(&program);
+        let paths: Vec<_> = imports.iter().map(|import| import.path.as_str()).collect();
+        assert_eq!(paths, ["./a", "b"]);
+        let names: Vec<_> = exports.iter().map(|export| export.name.as_str()).collect();
+        assert_eq!(names, ["f", "g"]);
+        let codes: Vec<_> = diagnostics.iter().map(|d| d.code.as_str()).collect();
+        assert_eq!(codes, ["E0101", "E0102"]);
+    }
+
+    #[test]
+    fn test_load_once() {
+        let dir = write(
+            "once",
+            &[
+                ("main.sap", "@@import(\"./b\") b\n@@import(\"./lib/c\") c"),
+                ("b.sap", "@@import(\"./lib/c\") c\n@@export b = 1"),
+                ("lib/c.sap", "@@export c = 2"),
+            ],
+        );
+        let graph = ModuleLoader::default().load(&dir.join("main.sap"));
+        assert!(graph.diagnostics.is_empty());
+        assert_eq!(graph.modules.len(), 3);
+        let entry = graph.entry.unwrap();
+        let c = graph
+            .find(&dir.join("lib/c.sap").canonicalize().unwrap())
+            .unwrap();
+        assert_eq!(graph.dependencies(entry).count(), 2);
+        assert_eq!(graph.dependents(c).count(), 2);
+        let order = graph.topological_order();
+        assert_eq!(order.first(), Some(&c));
+        assert_eq!(order.last(), Some(&entry));
+    }
+
+    #[test]
+    fn test_search_path_and_missing() {
+        let dir = write(
+            "search",
+            &[
+                (
+                    "src/main.sap",
+                    "@@import(\"std/list\") ^{map, fold}\n@@import(\"nope\") n",
+                ),
+                ("lib/std/list.sap", "@@export map = 1"),
+            ],
+        );
+        let loader = ModuleLoader::new(vec![dir.join("lib")]);
+        let graph = loader.load(&dir.join("src/main.sap"));
+        assert_eq!(graph.modules.len(), 2);
+        assert_eq!(codes(&graph), ["E0103", "E0105"]);
+        assert!(graph.diagnostics[1].diagnostic.message.contains("`fold`"));
+    }
+
+    #[test]
+    fn test_import_cycle() {
+        let dir = write(
+            "cycle",
+            &[
+                ("a.sap", "@@import(\"./b\") b"),
+                ("b.sap", "x = 1\n@@import(\"./c\") c"),
+                ("c.sap", "@@import(\"./a\") a"),
+            ],
+        );
+        let graph = ModuleLoader::default().load(&dir.join("a.sap"));
+        assert_eq!(graph.modules.len(), 3);
+        assert_eq!(codes(&graph), ["E0104"]);
+        let diagnostic = &graph.diagnostics[0];
+        assert!(diagnostic.path.ends_with("c.sap"));
+        assert_eq!(
+            diagnostic.diagnostic.notes[1..],
+            [
+                "`./b` imports `./c` at 2:1".to_string(),
+                "`./c` imports `./a` at 1:1".to_string()
+            ]
+        );
+        assert_eq!(graph.topological_order().len(), 3);
+    }
+
+    #[test]
+    fn test_self_import() {
+        let dir = write("self", &[("a.sap", "x = 1\n@@import(\"./a\") a")]);
+        let graph = ModuleLoader::default().load(&dir.join("a.sap"));
+        assert_eq!(graph.modules.len(), 1);
+        assert_eq!(codes(&graph), ["E0104"]);
+        let diagnostic = &graph.diagnostics[0].diagnostic;
+        assert_eq!(diagnostic.primary.diag.start_line, 2);
+        assert!(diagnostic.notes[0].ends_with("imports `./a` at 2:1"));
+        assert_eq!(graph.topological_order().len(), 1);
+    }
+
+    #[test]
+    fn test_unreadable() {
+        let source = "x = 1\n@@import(\"./bad\") b";
+        let dir = write("unreadable", &[("main.sap", source)]);
+        dir.write("bad.sap", [0xff]);
+        let graph = ModuleLoader::default().load(&dir.join("main.sap"));
+        assert_eq!(codes(&graph), ["E0103"]);
+        let diagnostic = &graph.diagnostics[0];
+        assert!(diagnostic.path.ends_with("main.sap"));
+        let diag = diagnostic.diagnostic.primary.diag;
+        assert_eq!(&source[diag.start_offset..diag.end_offset], "\"./bad\"");
+        assert_eq!((diag.start_line, diag.start_col), (2, 10));
+
+        let graph = ModuleLoader::default().load(&dir.join("bad.sap"));
+        assert_eq!(codes(&graph), ["E0103"]);
+        assert!(graph.diagnostics[0].path.ends_with("bad.sap"));
+        assert_eq!(graph.diagnostics[0].diagnostic.primary.diag.start_line, 1);
+    }
+}
@@ -0,0 +1,337 @@
+//! slots are bindings initialised later: `x = <>` binds `x` to an empty
+//! slot and `x ::= value` fills it, once
+//!
+//! the bindings of a scope are known before its statements are checked, so
+//! a closure may fill a slot bound below it. A block, a lambda and the file
+//! are scopes, a lambda binds its parameters. `::=` fills the innermost
+//! binding of the name, which must be a slot. Two fills among the
+//! statements of the scope of the slot are reported, fills inside a
+//! closure or a branch are not ordered and only count as filling it
+
+use crate::{
+    diagnostics::{Diagnostic, Label, SapDiagnostic},
+    expr::{Expr, ExprInner, Inner, Primary, infix::Infix},
+    function::{NoParamLambdaExpr, TrLambda},
+    id::Id,
+    literal::{self, CompoundLiteral, Literal},
+    pattern::{Pattern, array::ArrayPatternElem, object::ObjectPatternElem},
+    program::Program,
+    quotations::Block,
+    visit::{self, Visit},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotErrorKind {
+    MisplacedSlot,
+    InvalidTarget,
+    NotASlot,
+    FilledTwice,
+    NeverFilled,
+}
+
+impl SlotErrorKind {
+    pub fn code(self) -> &'static str {
+        match self {
+            SlotErrorKind::MisplacedSlot => "E0301",
+            SlotErrorKind::InvalidTarget => "E0302",
+            SlotErrorKind::NotASlot => "E0303",
+            SlotErrorKind::FilledTwice => "E0304",
+            SlotErrorKind::NeverFilled => "W0301",
+        }
+    }
+}
+
+/// the misuses of `<>` and `::=` in `program`
+pub fn validate(program: &Program) -> Vec<SapDiagnostic> {
+    let mut validate = Validate::default();
+    validate.scope(vec![], program.stmts.iter().map(|stmt| &stmt.expr));
+    validate.diagnostics
+}
+
+struct Binding {
+    name: String,
+    /// where the binding is, `None` for a lambda parameter
+    diag: Option<Diagnostic>,
+    slot: bool,
+    filled: bool,
+    /// the first fill among the statements of the scope
+    first_fill: Option<Diagnostic>,
+}
+
+#[derive(Default)]
+struct Validate {
+    scopes: Vec<Vec<Binding>>,
+    /// whether the next expr is a statement of the innermost scope
+    statement: bool,
+    diagnostics: Vec<SapDiagnostic>,
+}
+
+impl Validate {
+    fn scope<'ast>(
+        &mut self,
+        params: Vec<Binding>,
+        stmts: impl Iterator<Item = &'ast Expr> + Clone,
+    ) {
+        let mut bindings = params;
+        for stmt in stmts.clone() {
+            declare(stmt, &mut bindings);
+        }
+        self.scopes.push(bindings);
+        for stmt in stmts {
+            self.statement = true;
+            self.visit_expr(stmt);
+        }
+        for binding in self.scopes.pop().unwrap_or_default() {
+            if let (true, false, Some(diag)) = (binding.slot, binding.filled, binding.diag) {
+                self.diagnostics.push(SapDiagnostic::warning(
+                    SlotErrorKind::NeverFilled.code(),
+                    format!("slot `{}` is never filled", binding.name),
+                    Label::new(diag, "bound to an empty slot here"),
+                ));
+            }
+        }
+    }
+
+    fn fill(&mut self, target: &Expr, diag: Diagnostic, statement: bool) {
+        let Some(name) = id(target) else {
+            self.diagnostics.push(error(
+                SlotErrorKind::InvalidTarget,
+                "`::=` can only fill a named slot".to_string(),
+                Label::new(target.diag, "not a name"),
+            ));
+            return;
+        };
+        let innermost = self.scopes.len().saturating_sub(1);
+        let found = self
+            .scopes
+            .iter_mut()
+            .enumerate()
+            .rev()
+            .find_map(|(depth, scope)| {
+                let binding = scope
+                    .iter_mut()
+                    .rev()
+                    .find(|binding| binding.name == name)?;
+                Some((depth, binding))
+            });
+        let diagnostic = match found {
+            Some((depth, binding)) if binding.slot => {
+                binding.filled = true;
+                if !statement || depth != innermost {
+                    return;
+                }
+                let Some(first) = binding.first_fill else {
+                    binding.first_fill = Some(diag);
+                    return;
+                };
+                error(
+                    SlotErrorKind::FilledTwice,
+                    format!("slot `{}` is filled twice", name),
+                    Label::new(diag, "filled again here"),
+                )
+                .with_secondary(Label::new(first, "first filled here"))
+            }
+            Some((_, binding)) => {
+                let diagnostic = error(
+                    SlotErrorKind::NotASlot,
+                    format!("`{}` is not a slot", name),
+                    Label::new(target.diag, "can not be filled with `::=`"),
+                );
+                match binding.diag {
+                    Some(bound) => {
+                        diagnostic.with_secondary(Label::new(bound, "bound here without `<>`"))
+                    }
+                    None => diagnostic.with_note("it is a lambda parameter"),
+                }
+            }
+            None => error(
+                SlotErrorKind::NotASlot,
+                format!("no slot named `{}`", name),
+                Label::new(target.diag, "not bound in this scope"),
+            )
+            .with_note(format!("bind it first with `{} = <>`", name)),
+        };
+        self.diagnostics.push(diagnostic);
+    }
+}
+
+fn error(kind: SlotErrorKind, message: String, label: Label) -> SapDiagnostic {
+    SapDiagnostic::error(kind.code(), message, label)
+}
+
+impl<'ast> Visit<'ast> for Validate {
+    fn visit_expr(&mut self, node: &'ast Expr) {
+        let statement = std::mem::take(&mut self.statement);
+        match &node.inner {
+            ExprInner::Infix(Infix::AssignSlot, target, value) => {
+                self.fill(target, node.diag, statement);
+                self.visit_expr(value);
+            }
+            // the only place of a slot
+            ExprInner::Infix(Infix::Assign, target, value)
+                if is_slot(value) && id(target).is_some() => {}
+            _ => visit::visit_expr(self, node),
+        }
+    }
+
+    fn visit_literal(&mut self, node: &'ast Literal) {
+        if let literal::Inner::Slot(_) = node.inner {
+            self.diagnostics.push(error(
+                SlotErrorKind::MisplacedSlot,
+                "a slot can only be bound to a name".to_string(),
+                Label::new(node.diag, "write `name = <>`"),
+            ));
+        }
+    }
+
+    fn visit_block(&mut self, node: &'ast Block) {
+        self.scope(vec![], node.exprs.iter());
+    }
+
+    fn visit_no_param_lambda_expr(&mut self, node: &'ast NoParamLambdaExpr) {
+        self.scope(vec![], node.body.iter());
+    }
+
+    fn visit_tr_lambda(&mut self, node: &'ast TrLambda) {
+        let mut ids = vec![];
+        for pattern in &node.patterns {
+            self.visit_pattern(pattern);
+            pattern_ids(pattern, &mut ids);
+        }
+        ids.extend(
+            node.implicit_params
+                .iter()
+                .flat_map(|params| &params.params),
+        );
+        let params = ids
+            .into_iter()
+            .map(|id| Binding {
+                name: id.value(),
+                diag: None,
+                slot: false,
+                filled: false,
+                first_fill: None,
+            })
+            .collect();
+        // the guard sees the parameters, it is checked as a statement of the body
+        let guard = node.guard.iter().map(|guard| &*guard.expr);
+        self.scope(params, guard.chain(std::iter::once(&*node.body)));
+    }
+}
+
+/// adds the bindings `stmt` makes to its scope
+fn declare(stmt: &Expr, bindings: &mut Vec<Binding>) {
+    if let ExprInner::Infix(Infix::Assign, target, value) = &stmt.inner {
+        if let Some(name) = id(target) {
+            bindings.push(Binding {
+                name,
+                diag: Some(stmt.diag),
+                slot: is_slot(value),
+                filled: false,
+                first_fill: None,
+            });
+        }
+        // `a = b = <>`
+        declare(value, bindings);
+    }
+}
+
+fn id(expr: &Expr) -> Option<String> {
+    match &expr.inner {
+        ExprInner::Primary(Primary {
+            inner: Inner::Id(id) | Inner::Pattern(Pattern::Id(id)),
+            ..
+        }) => Some(id.value()),
+        _ => None,
+    }
+}
+
+fn is_slot(expr: &Expr) -> bool {
+    matches!(
+        &expr.inner,
+        ExprInner::Primary(Primary {
+            inner: Inner::CompoundLiteral(CompoundLiteral::Literal(Literal {
+                inner: literal::Inner::Slot(_),
+                ..
+            })),
+            ..
+        })
+    )
+}
+
+fn pattern_ids<'ast>(pattern: &'ast Pattern, ids: &mut Vec<&'ast Id>) {
+    match pattern {
+        Pattern::Id(id) => ids.push(id),
+        Pattern::Literal(_) => {}
+        Pattern::ArrayPattern(array) => {
+            for elem in array.elems() {
+                match elem {
+                    ArrayPatternElem::EclipsePattern(eclipse) => ids.push(&eclipse.value),
+                    ArrayPatternElem::Pattern(pattern) => pattern_ids(pattern, ids),
+                }
+            }
+        }
+        Pattern::ObjectPattern(object) => {
+            for elem in object.elems() {
+                match elem {
+                    ObjectPatternElem::ObjectPatternKv(kv) => match (&kv.value, &kv.key) {
+                        (Some(pattern), _) => pattern_ids(pattern, ids),
+                        (None, literal::object::ObjectKey::Id(id)) => ids.push(id),
+                        (None, literal::object::ObjectKey::String(_)) => {}
+                    },
+                    ObjectPatternElem::EclipsePattern(eclipse) => ids.push(&eclipse.value),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_file;
+
+    fn codes(source: &str) -> Vec<String> {
+        validate(&parse_file(source).unwrap())
+            .into_iter()
+            .map(|diagnostic| diagnostic.code)
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_slots() {
+        assert!(codes("x = <>\nx ::= 1").is_empty());
+        // filled by a closure bound above the slot
+        assert!(codes("init = \\v -> x ::= v\nx = <>").is_empty());
+        assert!(codes("x = <>\nc ? (x ::= 1) : (x ::= 2)").is_empty());
+        assert!(codes("a = b = <>\nb ::= 1").is_empty());
+        assert!(codes("x = <>\nf = _{ x ::= 1 }").is_empty());
+    }
+
+    #[test]
+    fn test_misplaced_slot() {
+        assert_eq!(codes("f <>"), ["E0301"]);
+        assert_eq!(codes("x = [<>]"), ["E0301"]);
+        assert_eq!(codes("x = <>\nx := <>\nx ::= 1"), ["E0301"]);
+        assert_eq!(codes("f = \\<> -> 1"), ["E0301"]);
+    }
+
+    #[test]
+    fn test_invalid_target() {
+        assert_eq!(codes("x = <>\nx.a ::= 1\nx ::= 2"), ["E0302"]);
+        assert_eq!(codes("y = 1\ny ::= 2"), ["E0303"]);
+        assert_eq!(codes("z ::= 2"), ["E0303"]);
+        assert_eq!(codes("x = <>\nf = \\x -> x ::= 1\nx ::= 2"), ["E0303"]);
+    }
+
+    #[test]
+    fn test_fills() {
+        assert_eq!(codes("x = <>\nx ::= 1\nx ::= 2"), ["E0304"]);
+        assert_eq!(codes("x = <>"), ["W0301"]);
+        // an inner binding shadows the slot
+        assert_eq!(codes("x = <>\n{\n  x = <>\n  x ::= 1\n}"), ["W0301"]);
+        let diagnostics = validate(&parse_file("x = <>\nx ::= 1\nx ::= 2").unwrap());
+        assert_eq!(diagnostics[0].primary.diag.start_line, 3);
+        assert_eq!(diagnostics[0].secondary[0].diag.start_line, 2);
+    }
+}
@@ -0,0 +1,162 @@
+use from_pest::FromPest;
+use pest::iterators::Pairs;
+use serde::{Deserialize, Serialize};
+
+use crate::{Rule, diagnostics::Diagnostic, expr::Expr, preprocessor::comment};
+
+/// what ends a top level statement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StmtSeparator {
+    /// `;`
+    Semicolon,
+    /// one or more newlines
+    Newline,
+    /// nothing, the statement is the last one in the file
+    Eof,
+}
+
+impl StmtSeparator {
+    /// the separator right after `offset`, skipping whitespace
+    pub(crate) fn after(input: &str, offset: usize) -> Self {
+        let rest = input[offset..].trim_start_matches([' ', '\t']);
+        match rest.chars().next() {
+            Some(';') => StmtSeparator::Semicolon,
+            Some('\n' | '\r') => StmtSeparator::Newline,
+            _ => StmtSeparator::Eof,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stmt {
+    pub expr: Expr,
+    pub separator: StmtSeparator,
+    pub diag: Diagnostic,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Program {
+    pub stmts: Vec<Stmt>,
+    /// the `//!` blocks of the file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub doc_headings: Vec<String>,
+    pub diag: Diagnostic,
+}
+
+impl FromPest<'_> for Program {
+    type Rule = Rule;
+
+    type FatalError = from_pest::Void;
+
+    fn from_pest(
+        pest: &mut Pairs<'_, Self::Rule>,
+    ) -> Result<Self, from_pest::ConversionError<Self::FatalError>> {
+        let pest = pest.next();
+        if pest.is_none() {
+            return Err(from_pest::ConversionError::NoMatch);
+        }
+        let pest = pest.unwrap();
+        if pest.as_rule() != Rule::file {
+            return Err(from_pest::ConversionError::NoMatch);
+        }
+
+        let span = pest.as_span();
+        let diag = Diagnostic::from_span(span);
+        let mut stmts = vec![];
+        for pair in pest.into_inner() {
+            if pair.as_rule() != Rule::expr {
+                continue;
+            }
+            let input = span.get_input();
+            let separator = StmtSeparator::after(input, pair.as_span().end());
+            let end = trim_end(input, pair.as_span().start(), pair.as_span().end());
+            let diag = Diagnostic::from_offsets(input, pair.as_span().start(), end);
+            let mut expr = Expr::from_pest(&mut Pairs::single(pair))?;
+            expr.diag = diag;
+            stmts.push(Stmt {
+                expr,
+                separator,
+                diag,
+            });
+        }
+        Ok(Program {
+            stmts,
+            doc_headings: comment::doc_headings(span.get_input()),
+            diag,
+        })
+    }
+}
+
+/// pest leaves the whitespace and the comment after the last token of a
+/// statement inside its span, cut them off
+pub fn trim_end(input: &str, start: usize, mut end: usize) -> usize {
+    loop {
+        let text = &input[start..end];
+        let trimmed = text.trim_end();
+        let last = comment::classify(trimmed).pop();
+        match last {
+            Some(last) if last.diag.end_offset == trimmed.len() => {
+                end = start + last.diag.start_offset;
+            }
+            _ => return start + trimmed.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_file;
+
+    #[test]
+    fn test_program() {
+        let program = parse_file("a = 1; b = 2\n\nc\n").unwrap();
+        assert_eq!(program.stmts.len(), 3);
+        assert_eq!(program.stmts[0].separator, StmtSeparator::Semicolon);
+        assert_eq!(program.stmts[1].separator, StmtSeparator::Newline);
+        assert_eq!(program.stmts[2].separator, StmtSeparator::Newline);
+        assert_eq!(program.stmts[1].diag.start_offset, 7);
+        assert_eq!(program.stmts[1].diag.end_offset, 12);
+    }
+
+    #[test]
+    fn test_program_last_stmt() {
+        let program = parse_file("\n\na b ;\nc").unwrap();
+        assert_eq!(program.stmts.len(), 2);
+        assert_eq!(program.stmts[0].separator, StmtSeparator::Semicolon);
+        assert_eq!(program.stmts[1].separator, StmtSeparator::Eof);
+    }
+
+    #[test]
+    fn test_program_comments() {
+        let program =
+            parse_file("//! title\n/// doc\na = { // one\n  1\n} // two\n\"//\"").unwrap();
+        assert_eq!(program.stmts.len(), 2);
+        assert_eq!(program.stmts[0].separator, StmtSeparator::Newline);
+        assert_eq!(program.stmts[0].diag.start_offset, 18);
+        assert_eq!(program.stmts[0].diag.end_offset, 36);
+        assert_eq!(program.stmts[0].expr.diag.end_offset, 36);
+        assert_eq!(program.doc_headings, ["title"]);
+        assert_eq!(program.stmts[0].expr.doc.as_deref(), Some("doc"));
+        assert_eq!(program.stmts[1].expr.doc, None);
+    }
+
+    #[test]
+    fn test_empty_program() {
+        let program = parse_file("\n;\n").unwrap();
+        assert!(program.stmts.is_empty());
+    }
+
+    #[test]
+    fn test_program_serde_round_trip() {
+        let source = "//! title\n@@import(\"./a\") ^{x, ...rest}\n/// doc\nf = \\^[a, ...b] ?i : a > 0x1F -> {\n  g(a, .5e-3)[1:2:3].c ? r#\"a\"b\"# : \"\\n\\u00e9\"\n}\n{k: true, \"s\": ()}; _{ <- @m(0b1_0) !~y }";
+        let program = parse_file(source).unwrap();
+        let json = serde_json::to_string(&program).unwrap();
+        let back: Program = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, program);
+        assert_eq!(serde_json::to_string(&back).unwrap(), json);
+        // `==` compares the values, the lexemes are the escapes as written
+        assert_eq!(back.to_source(), program.to_source());
+        assert!(back.to_source().contains(r#""\n\u00e9""#));
+    }
+}
@@ -0,0 +1,587 @@
+//! error-recovering parser
+//!
+//! pest stops at the first error, so recovery works on the source text:
+//! the broken region around an error is replaced by an `error_node`
+//! (`STX .. ETX`, or `SUB` for a single byte) and the statement is parsed
+//! again. Statements are repaired one by one, so an error costs a reparse of
+//! its statement, and the file is parsed again once they are all repaired.
+//! The replacement keeps byte offsets and newlines, so every `Diagnostic`
+//! of the partial AST still points into the original input.
+//!
+//! The sentinels only parse under `recovering_file`, one written in the
+//! source is a syntax error like any other character.
+//!
+//! A region is the innermost `{}`/`[]`/`()` group around the error that has
+//! not been replaced yet, or else the whole statement, found by splitting at
+//! top level `;` and newlines.
+
+use std::ops::Range;
+
+use pest::{Parser, iterators::Pair};
+use pest_ast::FromPest;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ParseOptions, Rule, SapParser,
+    diagnostics::Diagnostic,
+    error::{ParseError, check_one, lower, unbounded},
+    expr::{Expr, Primary},
+    literal::number::PromoteInts,
+    preprocessor::comment::doc_headings,
+    program::{Program, Stmt, StmtSeparator, trim_end},
+    visit::VisitMut,
+};
+
+const STX: char = '\u{02}';
+const ETX: char = '\u{03}';
+const SUB: char = '\u{1A}';
+
+#[derive(Debug, Clone, PartialEq, FromPest, Serialize, Deserialize)]
+#[pest_ast(rule(Rule::error_node))]
+pub struct ErrorNode {
+    #[pest_ast(outer(with(Diagnostic::from_span)))]
+    pub diag: Diagnostic,
+}
+
+impl ErrorNode {
+    pub fn expr(diag: Diagnostic) -> Expr {
+        Expr::Primary(Primary::Error(ErrorNode { diag }, diag), diag)
+    }
+}
+
+/// parse a whole file, replacing every broken region with an `ErrorNode`
+///
+/// returns the partial program together with every error found, in the
+/// order they were found
+pub fn parse_file_recovering(input: &str) -> (Program, Vec<ParseError>) {
+    parse_file_recovering_with(input, &ParseOptions::default())
+}
+
+pub fn parse_file_recovering_with(
+    input: &str,
+    options: &ParseOptions,
+) -> (Program, Vec<ParseError>) {
+    let mut text = input.to_string();
+    let mut errors = vec![];
+    let mut replaced: Vec<Range<usize>> = vec![];
+
+    if SapParser::parse(Rule::recovering_file, &text).is_err() {
+        repair_statements(input, &mut text, &mut errors, &mut replaced);
+    }
+
+    // the statements are found by a guess, what it gets wrong is repaired on
+    // the whole file, every round replaces at least one new region
+    for _ in 0..=input.len() {
+        let file = match SapParser::parse(Rule::recovering_file, &text) {
+            Ok(mut pairs) => pairs.next().and_then(|pair| pair.into_inner().next()),
+            Err(error) => {
+                let error = ParseError::from_pest_error(error, input);
+                let pos = error.diag.start_offset;
+                errors.push(error);
+                match broken_region(&text, pos, &replaced) {
+                    Some(region) => {
+                        replace(&mut text, region.clone());
+                        replaced.push(region);
+                        continue;
+                    }
+                    None => break,
+                }
+            }
+        };
+        let Some(file) = file else {
+            break;
+        };
+
+        let mut regions = vec![];
+        collect_check_errors(&file, None, options, &replaced, &mut errors, &mut regions);
+        if !regions.is_empty() {
+            regions.sort_by_key(|region| region.len());
+            for region in regions {
+                replace(&mut text, region.clone());
+                replaced.push(region);
+            }
+            continue;
+        }
+
+        let mut program = lower_recovering(file, input, &mut errors);
+        if options.promote_ints {
+            PromoteInts.visit_program_mut(&mut program);
+        }
+        return (program, errors);
+    }
+
+    let program = Program {
+        stmts: vec![],
+        doc_headings: doc_headings(input),
+        diag: Diagnostic::from_offsets(input, 0, input.len()),
+    };
+    (program, errors)
+}
+
+/// reparse every top level statement on its own until it parses, replacing
+/// the broken regions in `text`
+fn repair_statements(
+    input: &str,
+    text: &mut String,
+    errors: &mut Vec<ParseError>,
+    replaced: &mut Vec<Range<usize>>,
+) {
+    let tokens = scan(input);
+    let mut groups = tokens.groups.clone();
+    groups.sort_by_key(|group| group.start);
+    for stmt in statements(&tokens) {
+        let first = groups.partition_point(|group| group.start < stmt.start);
+        let last = groups.partition_point(|group| group.start < stmt.end);
+        let groups: Vec<_> = groups[first..last]
+            .iter()
+            .filter(|group| group.end <= stmt.end)
+            .collect();
+        // every round replaces at least one new region
+        for _ in 0..=stmt.len() {
+            let source = &text[stmt.clone()];
+            let error = match SapParser::parse(Rule::recovering_stmt, source) {
+                Ok(_) => break,
+                Err(error) => ParseError::from_pest_error(error, source),
+            };
+            let pos = stmt.start + error.diag.start_offset;
+            let diag = Diagnostic::from_offsets(input, pos, stmt.start + error.diag.end_offset);
+            errors.push(ParseError { diag, ..error });
+            let region = groups
+                .iter()
+                .filter(|group| group.start < pos && pos < group.end)
+                .filter(|group| !replaced.contains(group))
+                .min_by_key(|group| group.len())
+                .map(|group| (*group).clone())
+                .or_else(|| (!replaced.contains(&stmt)).then(|| stmt.clone()));
+            let Some(region) = region else {
+                break;
+            };
+            replace(text, region.clone());
+            replaced.push(region);
+        }
+    }
+}
+
+/// report what `check_one` rejects, and mark the innermost primary or
+/// expression around it as broken, and report the sentinels that were in
+/// the source and not put there by `replace`
+fn collect_check_errors(
+    pair: &Pair<'_, Rule>,
+    enclosing: Option<Range<usize>>,
+    options: &ParseOptions,
+    replaced: &[Range<usize>],
+    errors: &mut Vec<ParseError>,
+    regions: &mut Vec<Range<usize>>,
+) {
+    let span = pair.as_span();
+    if pair.as_rule() == Rule::error_node && !replaced.contains(&(span.start()..span.end())) {
+        let error = ParseError::from_pest_error(
+            pest::error::Error::new_from_pos(
+                pest::error::ErrorVariant::ParsingError {
+                    positives: vec![Rule::expr],
+                    negatives: vec![],
+                },
+                span.start_pos(),
+            ),
+            pair.get_input(),
+        );
+        errors.push(error);
+        return;
+    }
+    let enclosing = match pair.as_rule() {
+        Rule::primary | Rule::expr => Some(span.start()..span.end()),
+        _ => enclosing,
+    };
+    if let Some(error) = check_one(pair) {
+        errors.push(error);
+        regions.push(enclosing.clone().unwrap_or(span.start()..span.end()));
+    }
+    if unbounded(pair, options) {
+        return;
+    }
+    for inner in pair.clone().into_inner() {
+        collect_check_errors(
+            &inner,
+            enclosing.clone(),
+            options,
+            replaced,
+            errors,
+            regions,
+        );
+    }
+}
+
+/// lower statement by statement, so a failing statement only loses itself
+fn lower_recovering(file: Pair<'_, Rule>, input: &str, errors: &mut Vec<ParseError>) -> Program {
+    let mut stmts = vec![];
+    for pair in file.into_inner() {
+        if pair.as_rule() != Rule::expr {
+            continue;
+        }
+        let separator = StmtSeparator::after(input, pair.as_span().end());
+        let end = trim_end(input, pair.as_span().start(), pair.as_span().end());
+        let diag = Diagnostic::from_offsets(input, pair.as_span().start(), end);
+        let mut expr = lower::<Expr>(pair).unwrap_or_else(|error| {
+            errors.push(error);
+            ErrorNode::expr(diag)
+        });
+        expr.diag = diag;
+        stmts.push(Stmt {
+            expr,
+            separator,
+            diag,
+        });
+    }
+    Program {
+        stmts,
+        doc_headings: doc_headings(input),
+        diag: Diagnostic::from_offsets(input, 0, input.len()),
+    }
+}
+
+/// turn `text[region]` into an `error_node`, keeping offsets and newlines
+fn replace(text: &mut String, region: Range<usize>) {
+    let inner: String = text[region.clone()]
+        .chars()
+        .map(|c| match c {
+            STX | ETX | SUB => ' ',
+            c => c,
+        })
+        .collect();
+    let pad = |c: char| " ".repeat(c.len_utf8() - 1);
+    let mut chars = inner.chars();
+    let node = match (chars.next(), chars.next_back()) {
+        (Some(first), Some(last)) => format!(
+            "{}{}{}{}{}",
+            STX,
+            pad(first),
+            chars.as_str(),
+            pad(last),
+            ETX
+        ),
+        (Some(only), None) => format!("{}{}", SUB, pad(only)),
+        _ => return,
+    };
+    text.replace_range(region, &node);
+}
+
+/// the region to replace for an error at `pos`
+fn broken_region(text: &str, pos: usize, replaced: &[Range<usize>]) -> Option<Range<usize>> {
+    let tokens = scan(text);
+    let stmts = statements(&tokens);
+    let index = stmts
+        .iter()
+        .rposition(|stmt| stmt.start <= pos)
+        .unwrap_or_default();
+    let stmt = stmts.get(index)?.clone();
+
+    // an error at the start of a statement is usually caused by an opener
+    // the statement before never closed
+    if pos <= stmt.start && index > 0 {
+        let prev = stmts[index - 1].clone();
+        let unclosed = tokens.unclosed.iter().any(|i| prev.contains(i));
+        if unclosed && !replaced.contains(&prev) {
+            return Some(prev);
+        }
+    }
+
+    tokens
+        .groups
+        .iter()
+        .filter(|group| {
+            stmt.start <= group.start
+                && group.end <= stmt.end
+                && group.start < pos
+                && pos < group.end
+        })
+        .filter(|group| !replaced.contains(group))
+        .min_by_key(|group| group.len())
+        .cloned()
+        .or_else(|| (!replaced.contains(&stmt)).then_some(stmt))
+}
+
+#[derive(Debug, Default)]
+struct Tokens {
+    /// matched `{}`, `[]` and `()` groups, brackets included
+    groups: Vec<Range<usize>>,
+    /// offsets of openers that are never closed
+    unclosed: Vec<usize>,
+    /// every character, except that each string and error node is a single
+    /// `"` token and each comment a single space
+    code: Vec<(Range<usize>, char)>,
+}
+
+fn scan(text: &str) -> Tokens {
+    let mut tokens = Tokens::default();
+    let mut open: Vec<(usize, char)> = vec![];
+    let mut chars = text.char_indices().peekable();
+    let mut prev: Option<char> = None;
+
+    while let Some((i, c)) = chars.next() {
+        let token = match c {
+            '"' => {
+                skip_string(&mut chars);
+                '"'
+            }
+            'r' if !prev.is_some_and(is_id_char) && skip_raw_string(text, i, &mut chars) => '"',
+            '/' if chars.peek().is_some_and(|(_, c)| *c == '/') => {
+                skip_comment(&mut chars);
+                ' '
+            }
+            STX => {
+                while chars.next_if(|(_, c)| *c != ETX).is_some() {}
+                chars.next();
+                '"'
+            }
+            '{' | '[' | '(' => {
+                open.push((i, c));
+                c
+            }
+            '}' | ']' | ')' => {
+                let opener = match c {
+                    '}' => '{',
+                    ']' => '[',
+                    _ => '(',
+                };
+                // an unclosed opener must not swallow the closer of its parent
+                if let Some(depth) = open.iter().rposition(|(_, o)| *o == opener) {
+                    let (start, _) = open[depth];
+                    tokens
+                        .unclosed
+                        .extend(open.drain(depth..).skip(1).map(|(i, _)| i));
+                    tokens.groups.push(start..i + c.len_utf8());
+                }
+                c
+            }
+            c => c,
+        };
+        let end = chars.peek().map_or(text.len(), |(i, _)| *i);
+        tokens.code.push((i..end, token));
+        prev = Some(c);
+    }
+    tokens.unclosed.extend(open.into_iter().map(|(i, _)| i));
+    tokens
+}
+
+pub(crate) fn is_id_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+pub(crate) fn skip_string(chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>) {
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' => return,
+            _ => {}
+        }
+    }
+}
+
+/// skip a raw string starting at the `r` at `start`, if there is one
+pub(crate) fn skip_raw_string(
+    text: &str,
+    start: usize,
+    chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>,
+) -> bool {
+    let hashes = text[start + 1..].chars().take_while(|c| *c == '#').count();
+    if !text[start + 1 + hashes..].starts_with('"') {
+        return false;
+    }
+    let closing = format!("\"{}", "#".repeat(hashes));
+    let body = start + 2 + hashes;
+    let end = text[body..]
+        .find(&closing)
+        .map_or(text.len(), |i| body + i + closing.len());
+    while chars.next_if(|(i, _)| *i < end).is_some() {}
+    true
+}
+
+/// skip the rest of a `//` comment, up to the newline
+pub(crate) fn skip_comment(chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>) {
+    while chars.next_if(|(_, c)| *c != '\n' && *c != '\r').is_some() {}
+}
+
+/// top level statement ranges, without surrounding whitespace
+///
+/// a newline continues the statement when it is inside a matched group or
+/// follows an operator
+fn statements(tokens: &Tokens) -> Vec<Range<usize>> {
+    // how many groups are open between each offset and the next
+    let len = tokens.code.last().map_or(0, |(range, _)| range.end);
+    let mut depth = vec![0isize; len + 1];
+    for group in &tokens.groups {
+        depth[group.start + 1] += 1;
+        depth[group.end - 1] -= 1;
+    }
+    for i in 1..depth.len() {
+        depth[i] += depth[i - 1];
+    }
+    let in_group = |i: usize| depth[i] > 0;
+
+    let mut stmts = vec![];
+    let mut current: Option<Range<usize>> = None;
+    let mut last = ' ';
+    for (range, c) in &tokens.code {
+        let is_break = match c {
+            ';' => !in_group(range.start),
+            '\n' => !in_group(range.start) && !"+-*/%=<>!&|^~?:,.$@\\".contains(last),
+            _ => false,
+        };
+        if is_break {
+            stmts.extend(current.take());
+            last = ' ';
+        } else if !c.is_whitespace() {
+            let start = current.as_ref().map_or(range.start, |stmt| stmt.start);
+            current = Some(start..range.end);
+            last = *c;
+        }
+    }
+    stmts.extend(current);
+    stmts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::ParseErrorKind,
+        expr::{ExprInner, Inner},
+    };
+
+    fn is_error(expr: &Expr) -> bool {
+        matches!(
+            &expr.inner,
+            ExprInner::Primary(Primary {
+                inner: Inner::Error(_),
+                ..
+            })
+        )
+    }
+
+    #[test]
+    fn test_recover_statements() {
+        let (program, errors) = parse_file_recovering("a = 1\nb = + )\nc = 3\nd = * ;e");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(program.stmts.len(), 5);
+        assert!(is_error(&program.stmts[1].expr));
+        assert!(is_error(&program.stmts[3].expr));
+        assert!(!is_error(&program.stmts[4].expr));
+        assert_eq!(program.stmts[1].diag.start_offset, 6);
+        assert_eq!(program.stmts[1].diag.end_offset, 13);
+    }
+
+    #[test]
+    fn test_recover_positions() {
+        // each statement is reparsed alone, its errors point into the file
+        let src = "a = 1\n\nb = [1, +]\nc = f(\n  )\nd = é )";
+        let (program, errors) = parse_file_recovering(src);
+        let at: Vec<_> = errors
+            .iter()
+            .map(|error| (error.diag.start_line, error.diag.start_col))
+            .collect();
+        assert_eq!(at, [(3, 9), (5, 3), (6, 7)]);
+        assert_eq!(program.stmts.len(), 4);
+        assert!(!is_error(&program.stmts[0].expr));
+    }
+
+    #[test]
+    fn test_recover_leading_comment() {
+        let (program, errors) = parse_file_recovering("// a\na = 1\n// b\nb = )");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].diag.start_line, 4);
+        assert_eq!(program.stmts.len(), 2);
+        assert!(!is_error(&program.stmts[0].expr));
+        let (_, errors) = parse_file_recovering("// a\na = 1");
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_recover_groups() {
+        let src = "x = [1, +]\ny = f(1, ])\nz = { a\n b c ] }";
+        let (program, errors) = parse_file_recovering(src);
+        assert_eq!(errors.len(), 3);
+        assert_eq!(program.stmts.len(), 3);
+        let ExprInner::Infix(_, _, rhs) = &program.stmts[0].expr.inner else {
+            panic!("expected an assignment");
+        };
+        assert!(is_error(rhs));
+        assert_eq!(rhs.diag.start_offset, 4);
+        assert_eq!(rhs.diag.end_offset, 10);
+        assert_eq!(program.stmts[2].diag.start_line, 3);
+    }
+
+    #[test]
+    fn test_recover_unclosed() {
+        let (program, errors) = parse_file_recovering("a = [1, 2\nb = 2");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(program.stmts.len(), 2);
+        assert!(is_error(&program.stmts[0].expr));
+        assert!(!is_error(&program.stmts[1].expr));
+    }
+
+    #[test]
+    fn test_recover_literal() {
+        let (program, errors) =
+            parse_file_recovering("a = 0xFFFF_FFFF_FFFF_FFFF_FF\nb = \"\\uD800\"");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].kind, ParseErrorKind::IntOverflow);
+        assert_eq!(errors[1].kind, ParseErrorKind::InvalidEscape);
+        assert_eq!(program.stmts.len(), 2);
+
+        let promote = ParseOptions { promote_ints: true };
+        let (program, errors) =
+            parse_file_recovering_with("a = 0xFFFF_FFFF_FFFF_FFFF_FF\nb = )", &promote);
+        assert_eq!(errors.len(), 1);
+        assert!(!is_error(&program.stmts[0].expr));
+    }
+
+    #[test]
+    fn test_recover_strings() {
+        let (program, errors) = parse_file_recovering("a = \"; ]\" )\nb = r#\"[\"#");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(program.stmts.len(), 2);
+        assert!(!is_error(&program.stmts[1].expr));
+    }
+
+    #[test]
+    fn test_recover_comments() {
+        let (program, errors) = parse_file_recovering("a = [1, // ]\n 2]\nb = 2 // (\nc = )");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(program.stmts.len(), 3);
+        assert!(!is_error(&program.stmts[1].expr));
+        assert!(is_error(&program.stmts[2].expr));
+    }
+
+    #[test]
+    fn test_sentinels_in_source() {
+        for src in ["a = \u{1A}", "\u{02}junk\u{03} + 1"] {
+            let error = crate::parse_file(src).unwrap_err();
+            assert_eq!(error.kind, ParseErrorKind::Syntax);
+            assert!(crate::parse_expr(src).is_err());
+
+            let (_, errors) = parse_file_recovering(src);
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].kind, ParseErrorKind::Syntax);
+            assert_eq!(errors[0].diag.start_offset, error.diag.start_offset);
+            let (_, diagnostics) = crate::check_file(src);
+            assert!(
+                diagnostics
+                    .iter()
+                    .any(|diagnostic| diagnostic.code == "E0001")
+            );
+        }
+        let error = crate::parse_file("a = \u{1A}").unwrap_err();
+        assert_eq!(error.found.as_deref(), Some("`\\u{1a}`"));
+        assert_eq!(error.expected, ["expression"]);
+    }
+
+    #[test]
+    fn test_no_errors() {
+        let src = "a = 1 // one\nb = { c; d }";
+        let (program, errors) = parse_file_recovering(src);
+        assert!(errors.is_empty());
+        assert_eq!(program, crate::parse_file(src).unwrap());
+    }
+}
@@ -0,0 +1,135 @@
+//! rustc style reports of `SapDiagnostic`s rendered with ariadne
+//!
+//! every tool built on the parser renders its diagnostics here, so they
+//! all print the same way.
+
+use std::ops::Range;
+
+use ariadne::{Color, Config, IndexType, ReportKind, Source};
+
+use crate::diagnostics::{Diagnostic, SapDiagnostic, Severity};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportConfig {
+    /// colour is still left out when stderr is not a terminal, unless
+    /// `CLICOLOR_FORCE` is set
+    pub color: bool,
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        Self { color: true }
+    }
+}
+
+impl SapDiagnostic {
+    pub fn to_ariadne(
+        &self,
+        source_name: &str,
+        config: ReportConfig,
+    ) -> ariadne::Report<'static, (String, Range<usize>)> {
+        let span =
+            |diag: &Diagnostic| (source_name.to_string(), diag.start_offset..diag.end_offset);
+        let color = |color: Color| if config.color { color } else { Color::Primary };
+        let (kind, primary_color) = match self.severity {
+            Severity::Error => (ReportKind::Error, Color::Red),
+            Severity::Warning => (ReportKind::Warning, Color::Yellow),
+            Severity::Info => (ReportKind::Advice, Color::Fixed(147)),
+            Severity::Hint => (ReportKind::Custom("Hint", Color::Cyan), Color::Cyan),
+        };
+
+        let mut builder = ariadne::Report::build(kind, span(&self.primary.diag))
+            .with_config(
+                Config::default()
+                    .with_color(config.color)
+                    .with_index_type(IndexType::Byte),
+            )
+            .with_code(&self.code)
+            .with_message(&self.message)
+            .with_label(
+                ariadne::Label::new(span(&self.primary.diag))
+                    .with_message(&self.primary.message)
+                    .with_color(color(primary_color))
+                    .with_priority(1),
+            );
+        for label in &self.secondary {
+            builder.add_label(
+                ariadne::Label::new(span(&label.diag))
+                    .with_message(&label.message)
+                    .with_color(color(Color::Blue)),
+            );
+        }
+        for suggestion in &self.suggestions {
+            let message = if suggestion.replacement.is_empty() {
+                format!("{}: remove this", suggestion.message)
+            } else {
+                format!("{}: `{}`", suggestion.message, suggestion.replacement)
+            };
+            builder.add_label(
+                ariadne::Label::new(span(&suggestion.diag))
+                    .with_message(message)
+                    .with_color(color(Color::Green)),
+            );
+        }
+        for note in &self.notes {
+            builder.add_note(note);
+        }
+        builder.finish()
+    }
+
+    /// render against `source`, the text every `diag` points into
+    pub fn render(&self, source_name: &str, source: &str, config: ReportConfig) -> String {
+        let mut out = vec![];
+        // writing into a `Vec` can not fail
+        let _ = self
+            .to_ariadne(source_name, config)
+            .write((source_name.to_string(), Source::from(source)), &mut out);
+        String::from_utf8_lossy(&out).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostics::{Applicability, Label, Suggestion},
+        parse_expr,
+    };
+
+    #[test]
+    fn test_render_parse_error() {
+        let source = "a + )";
+        let error = parse_expr(source).unwrap_err();
+        let out =
+            SapDiagnostic::from(&error).render("test.sap", source, ReportConfig { color: false });
+        assert!(out.contains("[E0001] Error: expected expression, found `)`"));
+        assert!(out.contains("test.sap:1:5"));
+        assert!(!out.contains('\u{1b}'));
+    }
+
+    #[test]
+    fn test_render_labels_notes_and_suggestions() {
+        let source = "a = 1\nb = a";
+        let diagnostic = SapDiagnostic::warning(
+            "W9999",
+            "`a` is used here",
+            Label::new(Diagnostic::from_offsets(source, 10, 11), "used here"),
+        )
+        .with_secondary(Label::new(
+            Diagnostic::from_offsets(source, 0, 1),
+            "defined here",
+        ))
+        .with_note("a note")
+        .with_suggestion(Suggestion {
+            message: "inline it".to_string(),
+            diag: Diagnostic::from_offsets(source, 10, 11),
+            replacement: "1".to_string(),
+            applicability: Applicability::MaybeIncorrect,
+        });
+        let out = diagnostic.render("test.sap", source, ReportConfig { color: false });
+        assert!(out.contains("[W9999] Warning: `a` is used here"));
+        assert!(out.contains("defined here"));
+        assert!(out.contains("inline it: `1`"));
+        assert!(out.contains("Note: a note"));
+    }
+}
@@ -0,0 +1,685 @@
+//! the source of a tree on one line, with only the parentheses the
+//! `operator_precedence::LEVELS` ask for
+//!
+//! subtraction is the one exception: `a - b`, `a -b` and `a - (b)` all
+//! parse as the application `a (-b)`, and no parentheses change that, so
+//! `-` is followed by a line break, the only way the grammar reads it as
+//! an infix
+//!
+//! the formatter lays out parsed source and keeps its parentheses, this
+//! prints trees built by code generators and macros, which have none. A
+//! `///` doc is kept on statements and list items, where it can start its
+//! own line, and dropped elsewhere
+//!
+//! an ML application takes the rest of the expr as its last parameter and
+//! lowering splits it again at the outermost infix or trinary, so `f a + b`
+//! is `(f a) + b` but `f a * b + c` is `(f (a * b)) + c`. Applications, a
+//! trinary and a lambda body run to the end of the expr, anywhere else
+//! they are parenthesized
+
+use std::fmt::{self, Display, Write};
+
+use pest::pratt_parser::Assoc;
+
+use crate::{
+    Rule,
+    expr::{
+        Expr, ExprInner, Inner, Primary,
+        infix::Infix,
+        postfix::{CAppParams, Postfix},
+        prefix::Prefix,
+    },
+    function::{LambdaExpr, TrLambda},
+    id::Id,
+    literal::{
+        self, CompoundLiteral, Literal, number::SapNumber, object::ObjectKey, string::SapString,
+    },
+    operator_precedence::{self, Affix, LEVELS},
+    pattern::{EclipsePattern, Pattern, array::ArrayPatternElem, object::ObjectPatternElem},
+    program::{Program, StmtSeparator},
+};
+
+/// primaries and the postfixes of the strongest level of `LEVELS`
+const ATOM: u8 = LEVELS.len() as u8;
+
+/// the operators `magic_fn_id` can reference, without their parentheses
+const MAGIC_FNS: [&str; 25] = [
+    "+", "-", "*", "/", "%", "==", "!=", "<=", ">=", "<", ">", "&&", "||", "|", "&", "^", "<<",
+    ">>", "!", "~", "[]", "[::]", "neg", ".?", ".|",
+];
+
+/// the level of the operator parsed by `rule` in `LEVELS`
+fn op_level(rule: Rule) -> u8 {
+    operator_precedence::level(rule)
+        .expect("every operator has a level")
+        .0
+}
+
+/// the symbol, the level and whether it associates right
+fn infix(infix: &Infix) -> (&'static str, u8, bool) {
+    let symbol = match infix {
+        Infix::Assign => "=",
+        Infix::Set => ":=",
+        Infix::AssignYield => "=<=",
+        Infix::AssignSlot => "::=",
+        Infix::MatchEquals => "?=",
+        Infix::Pipe => "|>",
+        Infix::FindAndCallWithThis => "$",
+        Infix::Function(_) => "",
+        Infix::Extends => "<:",
+        Infix::Or => "||",
+        Infix::And => "&&",
+        Infix::BitOr => "|",
+        Infix::BitXor => "^",
+        Infix::BitAnd => "&",
+        Infix::Eq => "==",
+        Infix::Neq => "!=",
+        Infix::Lt => "<",
+        Infix::Gt => ">",
+        Infix::Le => "<=",
+        Infix::Ge => ">=",
+        Infix::HasField => ".?",
+        Infix::BitShiftL => "<<",
+        Infix::BitShiftR => ">>",
+        Infix::Add => "+",
+        Infix::Sub => "-",
+        Infix::Mul => "*",
+        Infix::Div => "/",
+        Infix::Mod => "%",
+    };
+    let (level, affix) =
+        operator_precedence::level(infix.rule()).expect("every operator has a level");
+    (symbol, level, affix == Affix::Infix(Assoc::Right))
+}
+
+fn level(expr: &Expr) -> u8 {
+    match &expr.inner {
+        ExprInner::Prefix(prefix, _) => op_level(prefix.rule()),
+        ExprInner::Infix(op, ..) => infix(op).1,
+        ExprInner::Postfix(postfix, _) => op_level(postfix.rule()),
+        ExprInner::MLApply(..) => op_level(Rule::postfix_ml_app_param),
+        ExprInner::CApply(..) => op_level(Rule::postfix_c_app_params),
+        ExprInner::Primary(_) => ATOM,
+    }
+}
+
+/// whether `expr` needs parentheses where the level `min` is expected, and
+/// something follows it unless `tail`
+fn needs_paren(expr: &Expr, min: u8, tail: bool) -> bool {
+    let level = level(expr);
+    match &expr.inner {
+        // a prefix takes its operand whatever the level around it
+        ExprInner::Prefix(..) => level < min && !tail,
+        ExprInner::Postfix(Postfix::Trinary(_), _) | ExprInner::MLApply(..) => level < min || !tail,
+        ExprInner::Primary(Primary {
+            inner: Inner::LambdaExpr(LambdaExpr::TrLambda(_)),
+            ..
+        }) => !tail,
+        _ => level < min,
+    }
+}
+
+/// whether a param of an ML application needs parentheses, `last` when it
+/// runs to the end of the expr
+pub(crate) fn ml_param_needs_paren(param: &Expr, last: bool) -> bool {
+    needs_paren(param, ATOM, last) || starts_with_bracket(param)
+}
+
+/// `f [a]` is an index
+fn starts_with_bracket(expr: &Expr) -> bool {
+    match &expr.inner {
+        ExprInner::Primary(Primary {
+            inner:
+                Inner::CompoundLiteral(CompoundLiteral::ArrayLiteral(_))
+                | Inner::Pattern(Pattern::ArrayPattern(_)),
+            ..
+        }) => true,
+        ExprInner::Postfix(_, operand)
+        | ExprInner::Infix(_, operand, _)
+        | ExprInner::CApply(operand, _)
+        | ExprInner::MLApply(operand, _) => starts_with_bracket(operand),
+        _ => false,
+    }
+}
+
+fn id(out: &mut String, id: &Id) {
+    match id {
+        Id::MagicFnId(magic_fn_id) if !magic_fn_id.value.starts_with('(') => {
+            let _ = write!(out, "({})", magic_fn_id.value);
+        }
+        // an operator is referenced as a magic fn
+        Id::NormalId(normal_id)
+            if MAGIC_FNS.contains(&normal_id.value.as_str()) && normal_id.value != "neg" =>
+        {
+            let _ = write!(out, "({})", normal_id.value);
+        }
+        id => out.push_str(&id.value()),
+    }
+}
+
+fn doc(out: &mut String, doc: Option<&str>) {
+    for line in doc.into_iter().flat_map(str::lines) {
+        out.push_str("/// ");
+        out.push_str(line);
+        out.push('\n');
+    }
+}
+
+/// `open a, b close`, or every item on its own line when one has a doc
+fn list(out: &mut String, open: &str, items: Vec<(Option<&str>, String)>, sep: &str, close: &str) {
+    out.push_str(open);
+    let docs = items.iter().any(|(doc, _)| doc.is_some());
+    for (i, (item_doc, item)) in items.iter().enumerate() {
+        if docs {
+            out.push_str(if i == 0 { "\n" } else { sep.trim_end() });
+            if i > 0 {
+                out.push('\n');
+            }
+            doc(out, *item_doc);
+        } else if i > 0 {
+            out.push_str(sep);
+        }
+        out.push_str(item);
+    }
+    if docs {
+        out.push('\n');
+    }
+    out.push_str(close);
+}
+
+/// an expr on its own, between brackets or separators
+fn item(expr: &Expr) -> (Option<&str>, String) {
+    let mut out = String::new();
+    self::expr(&mut out, expr, 0, true);
+    (expr.doc.as_deref(), out)
+}
+
+fn expr(out: &mut String, expr: &Expr, min: u8, tail: bool) {
+    if needs_paren(expr, min, tail) {
+        out.push('(');
+        inner(out, expr, true);
+        out.push(')');
+    } else {
+        inner(out, expr, tail);
+    }
+}
+
+fn inner(out: &mut String, expr: &Expr, tail: bool) {
+    match &expr.inner {
+        ExprInner::Prefix(prefix, operand) => {
+            match prefix {
+                Prefix::Not => out.push('!'),
+                Prefix::BitNot => out.push('~'),
+                Prefix::Neg => out.push('-'),
+                Prefix::Yield => out.push_str("<- "),
+                Prefix::AnnotativeMacroCall(macro_id, arg) => {
+                    out.push('@');
+                    out.push_str(&macro_id.value);
+                    if let Some(arg) = arg {
+                        out.push('(');
+                        self::expr(out, arg, 0, true);
+                        out.push(')');
+                    }
+                    out.push(' ');
+                }
+            }
+            self::expr(out, operand, level(expr) + 1, tail);
+        }
+        ExprInner::Primary(primary) => self::primary(out, primary),
+        ExprInner::Postfix(Postfix::Trinary(trinary), cond) => {
+            operand(out, cond, level(expr) + 1);
+            out.push_str(" ? ");
+            self::expr(out, &trinary.true_expr, 0, true);
+            out.push_str(" : ");
+            self::expr(out, &trinary.false_expr, 0, tail);
+        }
+        ExprInner::Postfix(postfix, operand) => {
+            self::expr(out, operand, ATOM, false);
+            self::postfix(out, postfix);
+        }
+        ExprInner::Infix(op, lhs, rhs) => {
+            let (symbol, level, right) = infix(op);
+            let (lhs_min, rhs_min) = if right {
+                (level + 1, level)
+            } else {
+                (level, level + 1)
+            };
+            if tail {
+                operand(out, lhs, lhs_min);
+            } else {
+                self::expr(out, lhs, lhs_min, false);
+            }
+            match op {
+                Infix::Function(function) => {
+                    out.push_str(" ~");
+                    id(out, function);
+                    out.push_str("~ ");
+                }
+                // see the module doc
+                Infix::Sub => out.push_str(" -\n"),
+                _ => {
+                    out.push(' ');
+                    out.push_str(symbol);
+                    out.push(' ');
+                }
+            }
+            self::expr(out, rhs, rhs_min, tail);
+        }
+        ExprInner::CApply(callee, params) => {
+            self::expr(out, callee, ATOM, false);
+            if params.is_empty() {
+                out.push_str("(,)");
+            } else {
+                // one parameter is read back as an ML application of `(a)`
+                list(out, "(", params.iter().map(item).collect(), ", ", ")");
+            }
+        }
+        ExprInner::MLApply(callee, params) => ml_apply(out, callee, params, ATOM, tail),
+    }
+}
+
+/// the lhs of an infix or trinary that runs to the end of the expr: an ML
+/// application there is split off the rest again by lowering, so only its
+/// last parameter has to be a valid lhs
+fn operand(out: &mut String, lhs: &Expr, min: u8) {
+    match &lhs.inner {
+        ExprInner::MLApply(callee, params) => ml_apply(out, callee, params, min, false),
+        _ => expr(out, lhs, min, false),
+    }
+}
+
+/// the last parameter is printed where `last_min` is expected
+fn ml_apply(out: &mut String, callee: &Expr, params: &[Expr], last_min: u8, tail: bool) {
+    expr(out, callee, ATOM, false);
+    for (i, param) in params.iter().enumerate() {
+        let last = i + 1 == params.len();
+        let mut printed = String::new();
+        if last {
+            expr(&mut printed, param, last_min, tail);
+        } else {
+            expr(&mut printed, param, ATOM, false);
+        }
+        // an ML application would take the rest of a parenthesized param,
+        // and `f [a]` is an index
+        let nested = matches!(param.inner, ExprInner::MLApply(..)) && !printed.starts_with('(');
+        out.push(' ');
+        if nested || printed.starts_with('[') {
+            let _ = write!(out, "({})", printed);
+        } else {
+            out.push_str(&printed);
+        }
+    }
+}
+
+fn postfix(out: &mut String, postfix: &Postfix) {
+    match postfix {
+        Postfix::Trinary(_) => unreachable!("printed with its operand"),
+        Postfix::Slice(slice) => {
+            let bound = |out: &mut String, bound: &Option<Box<Expr>>| {
+                if let Some(bound) = bound {
+                    expr(out, bound, 0, true);
+                }
+            };
+            out.push('[');
+            bound(out, &slice.start);
+            out.push(':');
+            bound(out, &slice.end);
+            if slice.step.is_some() {
+                out.push(':');
+                bound(out, &slice.step);
+            }
+            out.push(']');
+        }
+        Postfix::Index(index) => {
+            out.push('[');
+            expr(out, &index.postfix_index, 0, true);
+            out.push(']');
+        }
+        Postfix::Access(access) => {
+            out.push('.');
+            id(out, &access.id);
+        }
+        Postfix::Length => out.push_str(".|"),
+        Postfix::CAppParams(CAppParams(params)) => {
+            let params = params.as_ref().map_or(&[][..], |params| &params.0[..]);
+            list(out, "(", params.iter().map(item).collect(), ", ", ")");
+        }
+        Postfix::MlAppParam(param) => {
+            out.push(' ');
+            expr(out, &param.0, ATOM, true);
+        }
+    }
+}
+
+fn primary(out: &mut String, primary: &Primary) {
+    match &primary.inner {
+        // the token the recovering parser puts in place of a broken region
+        Inner::Error(_) => out.push('\u{1A}'),
+        Inner::Block(block) => list(
+            out,
+            "{ ",
+            block.exprs.iter().map(item).collect(),
+            "; ",
+            " }",
+        ),
+        Inner::LambdaExpr(LambdaExpr::TrLambda(tr_lambda)) => self::tr_lambda(out, tr_lambda),
+        Inner::LambdaExpr(LambdaExpr::NoParamLambdaExpr(lambda)) => list(
+            out,
+            "_{ ",
+            lambda.body.iter().map(item).collect(),
+            "; ",
+            " }",
+        ),
+        Inner::Hole(hole) => {
+            out.push('?');
+            id(out, &hole.id);
+        }
+        Inner::ParenExpr(paren_expr) => {
+            out.push('(');
+            expr(out, &paren_expr.expr, 0, true);
+            out.push(')');
+        }
+        Inner::CompoundLiteral(CompoundLiteral::ArrayLiteral(None)) => out.push_str("[]"),
+        Inner::CompoundLiteral(CompoundLiteral::ObjectLiteral(None)) => out.push_str("{}"),
+        Inner::CompoundLiteral(CompoundLiteral::ArrayLiteral(Some(array))) => {
+            list(out, "[", array.elems.iter().map(item).collect(), ", ", "]")
+        }
+        Inner::CompoundLiteral(CompoundLiteral::ObjectLiteral(Some(object))) => {
+            let elems = object
+                .body
+                .iter()
+                .map(|elem| {
+                    let mut out = String::new();
+                    object_key(&mut out, &elem.key);
+                    if let Some(value) = &elem.value {
+                        out.push_str(": ");
+                        expr(&mut out, value, 0, true);
+                    }
+                    (elem.doc.as_deref(), out)
+                })
+                .collect();
+            // `{a}` is a block
+            let close = match &object.body[..] {
+                [elem] if elem.value.is_none() => ",}",
+                _ => "}",
+            };
+            list(out, "{", elems, ", ", close);
+        }
+        Inner::CompoundLiteral(CompoundLiteral::Literal(literal)) => self::literal(out, literal),
+        Inner::Id(id) => self::id(out, id),
+        Inner::Pattern(pattern) => self::pattern(out, pattern),
+    }
+}
+
+fn tr_lambda(out: &mut String, tr_lambda: &TrLambda) {
+    out.push('\\');
+    for (i, pattern) in tr_lambda.patterns.iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        self::pattern(out, pattern);
+    }
+    if let Some(implicit_params) = &tr_lambda.implicit_params {
+        if !tr_lambda.patterns.is_empty() {
+            out.push(' ');
+        }
+        out.push('?');
+        for (i, param) in implicit_params.params.iter().enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            id(out, param);
+        }
+    }
+    if let Some(guard) = &tr_lambda.guard {
+        out.push_str(" : ");
+        expr(out, &guard.expr, 0, true);
+    }
+    let head = tr_lambda.patterns.is_empty()
+        && tr_lambda.implicit_params.is_none()
+        && tr_lambda.guard.is_none();
+    out.push_str(if head { "-> " } else { " -> " });
+    expr(out, &tr_lambda.body, 0, true);
+}
+
+fn object_key(out: &mut String, key: &ObjectKey) {
+    match key {
+        ObjectKey::Id(key) => id(out, key),
+        ObjectKey::String(key) => string(out, key),
+    }
+}
+
+fn string(out: &mut String, string: &SapString) {
+    match string {
+        SapString::NormalString(normal_string) => out.push_str(&normal_string.to_source()),
+        SapString::RawString(raw_string) => out.push_str(&raw_string.to_source()),
+    }
+}
+
+fn literal(out: &mut String, literal: &Literal) {
+    match &literal.inner {
+        literal::Inner::Boolean(boolean) => out.push_str(&boolean.value.to_string()),
+        literal::Inner::Void(_) => out.push_str("()"),
+        literal::Inner::Slot(_) => out.push_str("<>"),
+        literal::Inner::String(string) => self::string(out, string),
+        // a synthesized number may have no lexeme
+        literal::Inner::Number(SapNumber::Int(int)) => match int.lexeme() {
+            "" => out.push_str(&int.value().to_string()),
+            lexeme => out.push_str(lexeme),
+        },
+        literal::Inner::Number(SapNumber::BigInt(big_int)) => match big_int.lexeme.as_str() {
+            "" => out.push_str(&format!("{}n", big_int.value)),
+            lexeme => out.push_str(lexeme),
+        },
+        literal::Inner::Number(SapNumber::Float(float)) => match float.lexeme() {
+            "" => out.push_str(&format!("{:?}", float.value())),
+            lexeme => out.push_str(lexeme),
+        },
+    }
+}
+
+fn eclipse_pattern(out: &mut String, eclipse: &EclipsePattern) {
+    out.push_str("...");
+    id(out, &eclipse.value);
+}
+
+fn pattern(out: &mut String, pattern: &Pattern) {
+    match pattern {
+        Pattern::Id(id) => self::id(out, id),
+        Pattern::Literal(literal) => self::literal(out, literal),
+        Pattern::ArrayPattern(array) => {
+            let elems = array
+                .elems()
+                .iter()
+                .map(|elem| {
+                    let mut out = String::new();
+                    match elem {
+                        ArrayPatternElem::EclipsePattern(eclipse) => {
+                            eclipse_pattern(&mut out, eclipse)
+                        }
+                        ArrayPatternElem::Pattern(pattern) => self::pattern(&mut out, pattern),
+                    }
+                    (None, out)
+                })
+                .collect();
+            list(out, "^[", elems, ", ", "]");
+        }
+        Pattern::ObjectPattern(object) => {
+            let elems = object
+                .elems()
+                .iter()
+                .map(|elem| {
+                    let mut out = String::new();
+                    let doc = match elem {
+                        ObjectPatternElem::ObjectPatternKv(kv) => {
+                            object_key(&mut out, &kv.key);
+                            if let Some(value) = &kv.value {
+                                out.push_str(": ");
+                                self::pattern(&mut out, value);
+                            }
+                            kv.doc.as_deref()
+                        }
+                        ObjectPatternElem::EclipsePattern(eclipse) => {
+                            eclipse_pattern(&mut out, eclipse);
+                            None
+                        }
+                    };
+                    (doc, out)
+                })
+                .collect();
+            list(out, "^{", elems, ", ", "}");
+        }
+    }
+}
+
+impl Expr {
+    /// source that parses back to this expr, up to parentheses, on one line
+    /// but after a subtraction
+    pub fn to_source(&self) -> String {
+        let mut out = String::new();
+        expr(&mut out, self, 0, true);
+        out
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_source())
+    }
+}
+
+impl Program {
+    /// the `//!` headings, then the statements with their separators
+    pub fn to_source(&self) -> String {
+        let mut out = String::new();
+        for heading in &self.doc_headings {
+            for line in heading.lines() {
+                out.push_str("//! ");
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+        if !self.doc_headings.is_empty() {
+            out.push('\n');
+        }
+        for (i, stmt) in self.stmts.iter().enumerate() {
+            let (stmt_doc, source) = item(&stmt.expr);
+            if i > 0 && out.ends_with(';') {
+                // a doc comment starts its own line
+                out.push(if stmt_doc.is_some() { '\n' } else { ' ' });
+            }
+            doc(&mut out, stmt_doc);
+            out.push_str(&source);
+            match stmt.separator {
+                StmtSeparator::Semicolon => out.push(';'),
+                StmtSeparator::Newline => out.push('\n'),
+                StmtSeparator::Eof => {}
+            }
+        }
+        out
+    }
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_source())
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        pattern(&mut out, self);
+        f.write_str(&out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostics::SameShape,
+        parse_expr, parse_file,
+        visit::{Fold, fold_expr},
+    };
+
+    /// drops the parentheses, which the unparser chooses for itself
+    struct Unparen;
+
+    impl Fold for Unparen {
+        fn fold_expr(&mut self, node: Expr) -> Expr {
+            let node = fold_expr(self, node);
+            match node.inner {
+                ExprInner::Primary(Primary {
+                    inner: Inner::ParenExpr(paren_expr),
+                    ..
+                }) => Expr {
+                    doc: node.doc,
+                    ..*paren_expr.expr
+                },
+                _ => node,
+            }
+        }
+    }
+
+    fn assert_round_trip(source: &str) {
+        let program = Unparen.fold_program(parse_file(source).unwrap());
+        let printed = program.to_source();
+        let reparsed = parse_file(&printed).unwrap_or_else(|e| panic!("{printed}\n{e:?}"));
+        assert!(
+            Unparen.fold_program(reparsed).same_shape(&program),
+            "{printed}"
+        );
+    }
+
+    fn unparse(source: &str) -> String {
+        Unparen.fold_expr(parse_expr(source).unwrap()).to_source()
+    }
+
+    #[test]
+    fn test_minimal_parens() {
+        assert_eq!(unparse("(a + b) * c"), "(a + b) * c");
+        assert_eq!(unparse("a + (b * c)"), "a + b * c");
+        assert_eq!(unparse("(a + b) + c"), "a + b + c");
+        assert_eq!(unparse("a + (b + c)"), "a + (b + c)");
+        assert_eq!(unparse("a = (b = c)"), "a = b = c");
+        assert_eq!(unparse("(a = b) = c"), "(a = b) = c");
+        assert_eq!(unparse("(-a).b"), "(-a).b");
+        assert_eq!(unparse("-(a.b)"), "-a.b");
+        assert_eq!(unparse("(f a) * b + c"), "(f a) * b + c");
+        assert_eq!(unparse("f (a * b) + c"), "f a * b + c");
+        assert_eq!(unparse("a -\nb"), "a -\nb");
+        assert_eq!(unparse("(a -\nb) * c"), "(a -\nb) * c");
+        assert_eq!(unparse("(c ? a : b) + 1"), "(c ? a : b) + 1");
+        assert_eq!(unparse("(\\x -> x) 1"), "(\\x -> x) 1");
+        assert_eq!(unparse("x |> (\\x -> x)"), "x |> \\x -> x");
+    }
+
+    #[test]
+    fn test_magic_fn_id() {
+        assert_eq!(unparse("fold (+) 0 xs"), "fold (+) 0 xs");
+        let mut expr = parse_expr("f x").unwrap();
+        if let ExprInner::MLApply(_, params) = &mut expr.inner
+            && let ExprInner::Primary(Primary {
+                inner: Inner::Id(Id::NormalId(id)),
+                ..
+            }) = &mut params[0].inner
+        {
+            id.value = "<<".into();
+        }
+        assert_eq!(expr.to_string(), "f (<<)");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        assert_round_trip(
+            "//! title\n\n/// doc\nf = \\^[a, ...b] ?i : a > 0x1F -> {\n  g(a, .5e-3)[1:2:3].c ? r#\"a\"b\"# : \"\\n\\u00e9\"\n}\n{k: true, \"s\": ()}; _{ <- @m(0b1_0) !~y }",
+        );
+        assert_round_trip(
+            "a = b ?= c |> d $ e ~f~ g <: h || i && j | k ^ l & m == n < o .? p << q + r * -s\n(((a * b) - c) / d) % e\nx := f a b (g c) * 2 + 1\nf (a + b) [1]\ny = c ? \\-> 1 : \\^{k: v, ...r} -> v\n@@import(\"./a\") {a,}\n[1, (f x), g(,), h(1, 2)] |> len",
+        );
+        assert_round_trip("x = <>\nx ::= f ?a 1 (g ?b).|\nxs.| >> 1n");
+        assert_round_trip("a = {\n  /// first\n  b\n  c\n}");
+        assert_round_trip("x = [] {} {k: [], j: 1}\n\\^[] ^{} -> { [] }; _{ ^[] }");
+    }
+}
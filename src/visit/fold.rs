@@ -0,0 +1,471 @@
+//! `Fold` takes the tree by value and rebuilds it, for passes replacing a
+//! node by one of another kind
+
+use crate::{
+    expr::{
+        Expr, ExprInner, Inner, Primary,
+        infix::Infix,
+        postfix::{Access, CAppParams, CParamsBody, Index, MlAppParam, Postfix, Slice, Trinary},
+        prefix::Prefix,
+    },
+    function::{Guard, ImplicitParams, LambdaExpr, NoParamLambdaExpr, TrLambda},
+    id::{Hole, Id, MacroId},
+    literal::{
+        CompoundLiteral, Literal,
+        array::ArrayBody,
+        object::{ObjectBody, ObjectElemKv, ObjectKey},
+    },
+    pattern::{
+        EclipsePattern, Pattern,
+        array::{ArrayPattern, ArrayPatternBody, ArrayPatternElem},
+        object::{ObjectPattern, ObjectPatternBody, ObjectPatternElem, ObjectPatternKv},
+    },
+    program::{Program, Stmt},
+    quotations::{Block, ParenExpr},
+    recovery::ErrorNode,
+};
+
+pub trait Fold {
+    fn fold_program(&mut self, node: Program) -> Program {
+        fold_program(self, node)
+    }
+    fn fold_stmt(&mut self, node: Stmt) -> Stmt {
+        fold_stmt(self, node)
+    }
+    fn fold_expr(&mut self, node: Expr) -> Expr {
+        fold_expr(self, node)
+    }
+    fn fold_prefix(&mut self, node: Prefix) -> Prefix {
+        fold_prefix(self, node)
+    }
+    fn fold_infix(&mut self, node: Infix) -> Infix {
+        fold_infix(self, node)
+    }
+    fn fold_postfix(&mut self, node: Postfix) -> Postfix {
+        fold_postfix(self, node)
+    }
+    fn fold_trinary(&mut self, node: Trinary) -> Trinary {
+        fold_trinary(self, node)
+    }
+    fn fold_slice(&mut self, node: Slice) -> Slice {
+        fold_slice(self, node)
+    }
+    fn fold_index(&mut self, node: Index) -> Index {
+        fold_index(self, node)
+    }
+    fn fold_access(&mut self, node: Access) -> Access {
+        fold_access(self, node)
+    }
+    fn fold_c_app_params(&mut self, node: CAppParams) -> CAppParams {
+        fold_c_app_params(self, node)
+    }
+    fn fold_ml_app_param(&mut self, node: MlAppParam) -> MlAppParam {
+        fold_ml_app_param(self, node)
+    }
+    fn fold_primary(&mut self, node: Primary) -> Primary {
+        fold_primary(self, node)
+    }
+    fn fold_error_node(&mut self, node: ErrorNode) -> ErrorNode {
+        node
+    }
+    fn fold_block(&mut self, node: Block) -> Block {
+        fold_block(self, node)
+    }
+    fn fold_lambda_expr(&mut self, node: LambdaExpr) -> LambdaExpr {
+        fold_lambda_expr(self, node)
+    }
+    fn fold_tr_lambda(&mut self, node: TrLambda) -> TrLambda {
+        fold_tr_lambda(self, node)
+    }
+    fn fold_no_param_lambda_expr(&mut self, node: NoParamLambdaExpr) -> NoParamLambdaExpr {
+        fold_no_param_lambda_expr(self, node)
+    }
+    fn fold_implicit_params(&mut self, node: ImplicitParams) -> ImplicitParams {
+        fold_implicit_params(self, node)
+    }
+    fn fold_guard(&mut self, node: Guard) -> Guard {
+        fold_guard(self, node)
+    }
+    fn fold_hole(&mut self, node: Hole) -> Hole {
+        fold_hole(self, node)
+    }
+    fn fold_paren_expr(&mut self, node: ParenExpr) -> ParenExpr {
+        fold_paren_expr(self, node)
+    }
+    fn fold_compound_literal(&mut self, node: CompoundLiteral) -> CompoundLiteral {
+        fold_compound_literal(self, node)
+    }
+    fn fold_array_body(&mut self, node: ArrayBody) -> ArrayBody {
+        fold_array_body(self, node)
+    }
+    fn fold_object_body(&mut self, node: ObjectBody) -> ObjectBody {
+        fold_object_body(self, node)
+    }
+    fn fold_object_elem_kv(&mut self, node: ObjectElemKv) -> ObjectElemKv {
+        fold_object_elem_kv(self, node)
+    }
+    fn fold_object_key(&mut self, node: ObjectKey) -> ObjectKey {
+        fold_object_key(self, node)
+    }
+    fn fold_literal(&mut self, node: Literal) -> Literal {
+        node
+    }
+    fn fold_id(&mut self, node: Id) -> Id {
+        node
+    }
+    fn fold_macro_id(&mut self, node: MacroId) -> MacroId {
+        node
+    }
+    fn fold_pattern(&mut self, node: Pattern) -> Pattern {
+        fold_pattern(self, node)
+    }
+    fn fold_array_pattern(&mut self, node: ArrayPattern) -> ArrayPattern {
+        fold_array_pattern(self, node)
+    }
+    fn fold_array_pattern_elem(&mut self, node: ArrayPatternElem) -> ArrayPatternElem {
+        fold_array_pattern_elem(self, node)
+    }
+    fn fold_object_pattern(&mut self, node: ObjectPattern) -> ObjectPattern {
+        fold_object_pattern(self, node)
+    }
+    fn fold_object_pattern_elem(&mut self, node: ObjectPatternElem) -> ObjectPatternElem {
+        fold_object_pattern_elem(self, node)
+    }
+    fn fold_object_pattern_kv(&mut self, node: ObjectPatternKv) -> ObjectPatternKv {
+        fold_object_pattern_kv(self, node)
+    }
+    fn fold_eclipse_pattern(&mut self, node: EclipsePattern) -> EclipsePattern {
+        fold_eclipse_pattern(self, node)
+    }
+}
+
+fn fold_box<F: Fold + ?Sized>(f: &mut F, expr: Box<Expr>) -> Box<Expr> {
+    Box::new(f.fold_expr(*expr))
+}
+
+fn fold_exprs<F: Fold + ?Sized>(f: &mut F, exprs: Vec<Expr>) -> Vec<Expr> {
+    exprs.into_iter().map(|expr| f.fold_expr(expr)).collect()
+}
+
+pub fn fold_program<F: Fold + ?Sized>(f: &mut F, node: Program) -> Program {
+    Program {
+        stmts: node
+            .stmts
+            .into_iter()
+            .map(|stmt| f.fold_stmt(stmt))
+            .collect(),
+        ..node
+    }
+}
+
+pub fn fold_stmt<F: Fold + ?Sized>(f: &mut F, node: Stmt) -> Stmt {
+    Stmt {
+        expr: f.fold_expr(node.expr),
+        ..node
+    }
+}
+
+pub fn fold_expr<F: Fold + ?Sized>(f: &mut F, node: Expr) -> Expr {
+    let inner = match node.inner {
+        ExprInner::Prefix(prefix, expr) => {
+            let prefix = f.fold_prefix(prefix);
+            ExprInner::Prefix(prefix, fold_box(f, expr))
+        }
+        ExprInner::Primary(primary) => ExprInner::Primary(f.fold_primary(primary)),
+        ExprInner::Postfix(postfix, expr) => {
+            let expr = fold_box(f, expr);
+            ExprInner::Postfix(f.fold_postfix(postfix), expr)
+        }
+        ExprInner::Infix(infix, lhs, rhs) => {
+            let lhs = fold_box(f, lhs);
+            let infix = f.fold_infix(infix);
+            ExprInner::Infix(infix, lhs, fold_box(f, rhs))
+        }
+        ExprInner::CApply(expr, params) => {
+            let expr = fold_box(f, expr);
+            ExprInner::CApply(expr, fold_exprs(f, params))
+        }
+        ExprInner::MLApply(expr, params) => {
+            let expr = fold_box(f, expr);
+            ExprInner::MLApply(expr, fold_exprs(f, params))
+        }
+    };
+    Expr { inner, ..node }
+}
+
+pub fn fold_prefix<F: Fold + ?Sized>(f: &mut F, node: Prefix) -> Prefix {
+    match node {
+        Prefix::AnnotativeMacroCall(macro_id, arg) => {
+            Prefix::AnnotativeMacroCall(f.fold_macro_id(macro_id), arg.map(|arg| fold_box(f, arg)))
+        }
+        prefix => prefix,
+    }
+}
+
+pub fn fold_infix<F: Fold + ?Sized>(f: &mut F, node: Infix) -> Infix {
+    match node {
+        Infix::Function(id) => Infix::Function(f.fold_id(id)),
+        infix => infix,
+    }
+}
+
+pub fn fold_postfix<F: Fold + ?Sized>(f: &mut F, node: Postfix) -> Postfix {
+    match node {
+        Postfix::Trinary(trinary) => Postfix::Trinary(f.fold_trinary(trinary)),
+        Postfix::Slice(slice) => Postfix::Slice(f.fold_slice(slice)),
+        Postfix::Index(index) => Postfix::Index(f.fold_index(index)),
+        Postfix::Access(access) => Postfix::Access(f.fold_access(access)),
+        Postfix::Length => Postfix::Length,
+        Postfix::CAppParams(params) => Postfix::CAppParams(f.fold_c_app_params(params)),
+        Postfix::MlAppParam(param) => Postfix::MlAppParam(f.fold_ml_app_param(param)),
+    }
+}
+
+pub fn fold_trinary<F: Fold + ?Sized>(f: &mut F, node: Trinary) -> Trinary {
+    let true_expr = fold_box(f, node.true_expr);
+    Trinary {
+        true_expr,
+        false_expr: fold_box(f, node.false_expr),
+    }
+}
+
+pub fn fold_slice<F: Fold + ?Sized>(f: &mut F, node: Slice) -> Slice {
+    let start = node.start.map(|start| fold_box(f, start));
+    let end = node.end.map(|end| fold_box(f, end));
+    Slice {
+        start,
+        end,
+        step: node.step.map(|step| fold_box(f, step)),
+    }
+}
+
+pub fn fold_index<F: Fold + ?Sized>(f: &mut F, node: Index) -> Index {
+    Index {
+        postfix_index: fold_box(f, node.postfix_index),
+    }
+}
+
+pub fn fold_access<F: Fold + ?Sized>(f: &mut F, node: Access) -> Access {
+    Access {
+        id: f.fold_id(node.id),
+    }
+}
+
+pub fn fold_c_app_params<F: Fold + ?Sized>(f: &mut F, node: CAppParams) -> CAppParams {
+    CAppParams(node.0.map(|params| CParamsBody(fold_exprs(f, params.0))))
+}
+
+pub fn fold_ml_app_param<F: Fold + ?Sized>(f: &mut F, node: MlAppParam) -> MlAppParam {
+    MlAppParam(fold_box(f, node.0))
+}
+
+pub fn fold_primary<F: Fold + ?Sized>(f: &mut F, node: Primary) -> Primary {
+    let inner = match node.inner {
+        Inner::Error(error_node) => Inner::Error(f.fold_error_node(error_node)),
+        Inner::Block(block) => Inner::Block(f.fold_block(block)),
+        Inner::LambdaExpr(lambda_expr) => Inner::LambdaExpr(f.fold_lambda_expr(lambda_expr)),
+        Inner::Hole(hole) => Inner::Hole(f.fold_hole(hole)),
+        Inner::ParenExpr(paren_expr) => Inner::ParenExpr(f.fold_paren_expr(paren_expr)),
+        Inner::CompoundLiteral(compound_literal) => {
+            Inner::CompoundLiteral(f.fold_compound_literal(compound_literal))
+        }
+        Inner::Id(id) => Inner::Id(f.fold_id(id)),
+        Inner::Pattern(pattern) => Inner::Pattern(f.fold_pattern(pattern)),
+    };
+    Primary { inner, ..node }
+}
+
+pub fn fold_block<F: Fold + ?Sized>(f: &mut F, node: Block) -> Block {
+    Block {
+        exprs: fold_exprs(f, node.exprs),
+    }
+}
+
+pub fn fold_lambda_expr<F: Fold + ?Sized>(f: &mut F, node: LambdaExpr) -> LambdaExpr {
+    match node {
+        LambdaExpr::TrLambda(tr_lambda) => LambdaExpr::TrLambda(f.fold_tr_lambda(tr_lambda)),
+        LambdaExpr::NoParamLambdaExpr(lambda) => {
+            LambdaExpr::NoParamLambdaExpr(f.fold_no_param_lambda_expr(lambda))
+        }
+    }
+}
+
+pub fn fold_tr_lambda<F: Fold + ?Sized>(f: &mut F, node: TrLambda) -> TrLambda {
+    let patterns = node
+        .patterns
+        .into_iter()
+        .map(|pattern| f.fold_pattern(pattern))
+        .collect();
+    let implicit_params = node
+        .implicit_params
+        .map(|implicit_params| f.fold_implicit_params(implicit_params));
+    let guard = node.guard.map(|guard| f.fold_guard(guard));
+    TrLambda {
+        patterns,
+        implicit_params,
+        guard,
+        body: fold_box(f, node.body),
+    }
+}
+
+pub fn fold_no_param_lambda_expr<F: Fold + ?Sized>(
+    f: &mut F,
+    node: NoParamLambdaExpr,
+) -> NoParamLambdaExpr {
+    NoParamLambdaExpr {
+        body: fold_exprs(f, node.body),
+    }
+}
+
+pub fn fold_implicit_params<F: Fold + ?Sized>(f: &mut F, node: ImplicitParams) -> ImplicitParams {
+    ImplicitParams {
+        params: node.params.into_iter().map(|id| f.fold_id(id)).collect(),
+    }
+}
+
+pub fn fold_guard<F: Fold + ?Sized>(f: &mut F, node: Guard) -> Guard {
+    Guard {
+        expr: fold_box(f, node.expr),
+    }
+}
+
+pub fn fold_hole<F: Fold + ?Sized>(f: &mut F, node: Hole) -> Hole {
+    Hole {
+        id: f.fold_id(node.id),
+    }
+}
+
+pub fn fold_paren_expr<F: Fold + ?Sized>(f: &mut F, node: ParenExpr) -> ParenExpr {
+    ParenExpr {
+        expr: fold_box(f, node.expr),
+    }
+}
+
+pub fn fold_compound_literal<F: Fold + ?Sized>(
+    f: &mut F,
+    node: CompoundLiteral,
+) -> CompoundLiteral {
+    match node {
+        CompoundLiteral::ArrayLiteral(array_body) => {
+            CompoundLiteral::ArrayLiteral(array_body.map(|body| f.fold_array_body(body)))
+        }
+        CompoundLiteral::ObjectLiteral(object_body) => {
+            CompoundLiteral::ObjectLiteral(object_body.map(|body| f.fold_object_body(body)))
+        }
+        CompoundLiteral::Literal(literal) => CompoundLiteral::Literal(f.fold_literal(literal)),
+    }
+}
+
+pub fn fold_array_body<F: Fold + ?Sized>(f: &mut F, node: ArrayBody) -> ArrayBody {
+    ArrayBody {
+        elems: fold_exprs(f, node.elems),
+        ..node
+    }
+}
+
+pub fn fold_object_body<F: Fold + ?Sized>(f: &mut F, node: ObjectBody) -> ObjectBody {
+    ObjectBody {
+        body: node
+            .body
+            .into_iter()
+            .map(|elem| f.fold_object_elem_kv(elem))
+            .collect(),
+        ..node
+    }
+}
+
+pub fn fold_object_elem_kv<F: Fold + ?Sized>(f: &mut F, node: ObjectElemKv) -> ObjectElemKv {
+    let key = f.fold_object_key(node.key);
+    ObjectElemKv {
+        key,
+        value: node.value.map(|value| f.fold_expr(value)),
+        ..node
+    }
+}
+
+pub fn fold_object_key<F: Fold + ?Sized>(f: &mut F, node: ObjectKey) -> ObjectKey {
+    match node {
+        ObjectKey::Id(id) => ObjectKey::Id(f.fold_id(id)),
+        key => key,
+    }
+}
+
+pub fn fold_pattern<F: Fold + ?Sized>(f: &mut F, node: Pattern) -> Pattern {
+    match node {
+        Pattern::Id(id) => Pattern::Id(f.fold_id(id)),
+        Pattern::Literal(literal) => Pattern::Literal(f.fold_literal(literal)),
+        Pattern::ArrayPattern(array_pattern) => {
+            Pattern::ArrayPattern(f.fold_array_pattern(array_pattern))
+        }
+        Pattern::ObjectPattern(object_pattern) => {
+            Pattern::ObjectPattern(f.fold_object_pattern(object_pattern))
+        }
+    }
+}
+
+pub fn fold_array_pattern<F: Fold + ?Sized>(f: &mut F, node: ArrayPattern) -> ArrayPattern {
+    let body = node.body.map(|body| ArrayPatternBody {
+        elems: body
+            .elems
+            .into_iter()
+            .map(|elem| f.fold_array_pattern_elem(elem))
+            .collect(),
+    });
+    ArrayPattern { body, ..node }
+}
+
+pub fn fold_array_pattern_elem<F: Fold + ?Sized>(
+    f: &mut F,
+    node: ArrayPatternElem,
+) -> ArrayPatternElem {
+    match node {
+        ArrayPatternElem::EclipsePattern(eclipse) => {
+            ArrayPatternElem::EclipsePattern(f.fold_eclipse_pattern(eclipse))
+        }
+        ArrayPatternElem::Pattern(pattern) => ArrayPatternElem::Pattern(f.fold_pattern(pattern)),
+    }
+}
+
+pub fn fold_object_pattern<F: Fold + ?Sized>(f: &mut F, node: ObjectPattern) -> ObjectPattern {
+    let body = node.body.map(|body| ObjectPatternBody {
+        body: body
+            .body
+            .into_iter()
+            .map(|elem| f.fold_object_pattern_elem(elem))
+            .collect(),
+    });
+    ObjectPattern { body, ..node }
+}
+
+pub fn fold_object_pattern_elem<F: Fold + ?Sized>(
+    f: &mut F,
+    node: ObjectPatternElem,
+) -> ObjectPatternElem {
+    match node {
+        ObjectPatternElem::ObjectPatternKv(kv) => {
+            ObjectPatternElem::ObjectPatternKv(f.fold_object_pattern_kv(kv))
+        }
+        ObjectPatternElem::EclipsePattern(eclipse) => {
+            ObjectPatternElem::EclipsePattern(f.fold_eclipse_pattern(eclipse))
+        }
+    }
+}
+
+pub fn fold_object_pattern_kv<F: Fold + ?Sized>(
+    f: &mut F,
+    node: ObjectPatternKv,
+) -> ObjectPatternKv {
+    let key = f.fold_object_key(node.key);
+    ObjectPatternKv {
+        key,
+        value: node.value.map(|value| f.fold_pattern(value)),
+        ..node
+    }
+}
+
+pub fn fold_eclipse_pattern<F: Fold + ?Sized>(f: &mut F, node: EclipsePattern) -> EclipsePattern {
+    EclipsePattern {
+        value: f.fold_id(node.value),
+        ..node
+    }
+}
@@ -0,0 +1,506 @@
+//! traversals of the whole AST: `Visit` borrows it, `VisitMut` borrows it
+//! mutably and `Fold` takes it by value and rebuilds it
+//!
+//! every node type has a method that by default calls the free walk
+//! function of the same name, which visits the children of the node in
+//! source order. A pass overrides the methods of the nodes it cares about
+//! and calls the walk function to keep descending:
+//!
+//! ```
+//! use sap_parser::{expr::Expr, parse_expr, visit::{self, Visit}};
+//!
+//! struct Count(usize);
+//!
+//! impl Visit<'_> for Count {
+//!     fn visit_expr(&mut self, expr: &Expr) {
+//!         self.0 += 1;
+//!         visit::visit_expr(self, expr);
+//!     }
+//! }
+//!
+//! let mut count = Count(0);
+//! count.visit_expr(&parse_expr("f a + 1").unwrap());
+//! assert_eq!(count.0, 5);
+//! ```
+
+mod fold;
+mod visit_mut;
+
+pub use fold::*;
+pub use visit_mut::*;
+
+use crate::{
+    expr::{
+        Expr, ExprInner, Inner, Primary,
+        infix::Infix,
+        postfix::{Access, CAppParams, Index, MlAppParam, Postfix, Slice, Trinary},
+        prefix::Prefix,
+    },
+    function::{Guard, ImplicitParams, LambdaExpr, NoParamLambdaExpr, TrLambda},
+    id::{Hole, Id, MacroId},
+    literal::{
+        CompoundLiteral, Literal,
+        array::ArrayBody,
+        object::{ObjectBody, ObjectElemKv, ObjectKey},
+    },
+    pattern::{
+        EclipsePattern, Pattern,
+        array::{ArrayPattern, ArrayPatternElem},
+        object::{ObjectPattern, ObjectPatternElem, ObjectPatternKv},
+    },
+    program::{Program, Stmt},
+    quotations::{Block, ParenExpr},
+    recovery::ErrorNode,
+};
+
+pub trait Visit<'ast> {
+    fn visit_program(&mut self, node: &'ast Program) {
+        visit_program(self, node)
+    }
+    fn visit_stmt(&mut self, node: &'ast Stmt) {
+        visit_stmt(self, node)
+    }
+    fn visit_expr(&mut self, node: &'ast Expr) {
+        visit_expr(self, node)
+    }
+    fn visit_prefix(&mut self, node: &'ast Prefix) {
+        visit_prefix(self, node)
+    }
+    fn visit_infix(&mut self, node: &'ast Infix) {
+        visit_infix(self, node)
+    }
+    fn visit_postfix(&mut self, node: &'ast Postfix) {
+        visit_postfix(self, node)
+    }
+    fn visit_trinary(&mut self, node: &'ast Trinary) {
+        visit_trinary(self, node)
+    }
+    fn visit_slice(&mut self, node: &'ast Slice) {
+        visit_slice(self, node)
+    }
+    fn visit_index(&mut self, node: &'ast Index) {
+        visit_index(self, node)
+    }
+    fn visit_access(&mut self, node: &'ast Access) {
+        visit_access(self, node)
+    }
+    fn visit_c_app_params(&mut self, node: &'ast CAppParams) {
+        visit_c_app_params(self, node)
+    }
+    fn visit_ml_app_param(&mut self, node: &'ast MlAppParam) {
+        visit_ml_app_param(self, node)
+    }
+    fn visit_primary(&mut self, node: &'ast Primary) {
+        visit_primary(self, node)
+    }
+    fn visit_error_node(&mut self, _node: &'ast ErrorNode) {}
+    fn visit_block(&mut self, node: &'ast Block) {
+        visit_block(self, node)
+    }
+    fn visit_lambda_expr(&mut self, node: &'ast LambdaExpr) {
+        visit_lambda_expr(self, node)
+    }
+    fn visit_tr_lambda(&mut self, node: &'ast TrLambda) {
+        visit_tr_lambda(self, node)
+    }
+    fn visit_no_param_lambda_expr(&mut self, node: &'ast NoParamLambdaExpr) {
+        visit_no_param_lambda_expr(self, node)
+    }
+    fn visit_implicit_params(&mut self, node: &'ast ImplicitParams) {
+        visit_implicit_params(self, node)
+    }
+    fn visit_guard(&mut self, node: &'ast Guard) {
+        visit_guard(self, node)
+    }
+    fn visit_hole(&mut self, node: &'ast Hole) {
+        visit_hole(self, node)
+    }
+    fn visit_paren_expr(&mut self, node: &'ast ParenExpr) {
+        visit_paren_expr(self, node)
+    }
+    fn visit_compound_literal(&mut self, node: &'ast CompoundLiteral) {
+        visit_compound_literal(self, node)
+    }
+    fn visit_array_body(&mut self, node: &'ast ArrayBody) {
+        visit_array_body(self, node)
+    }
+    fn visit_object_body(&mut self, node: &'ast ObjectBody) {
+        visit_object_body(self, node)
+    }
+    fn visit_object_elem_kv(&mut self, node: &'ast ObjectElemKv) {
+        visit_object_elem_kv(self, node)
+    }
+    fn visit_object_key(&mut self, node: &'ast ObjectKey) {
+        visit_object_key(self, node)
+    }
+    fn visit_literal(&mut self, _node: &'ast Literal) {}
+    fn visit_id(&mut self, _node: &'ast Id) {}
+    fn visit_macro_id(&mut self, _node: &'ast MacroId) {}
+    fn visit_pattern(&mut self, node: &'ast Pattern) {
+        visit_pattern(self, node)
+    }
+    fn visit_array_pattern(&mut self, node: &'ast ArrayPattern) {
+        visit_array_pattern(self, node)
+    }
+    fn visit_array_pattern_elem(&mut self, node: &'ast ArrayPatternElem) {
+        visit_array_pattern_elem(self, node)
+    }
+    fn visit_object_pattern(&mut self, node: &'ast ObjectPattern) {
+        visit_object_pattern(self, node)
+    }
+    fn visit_object_pattern_elem(&mut self, node: &'ast ObjectPatternElem) {
+        visit_object_pattern_elem(self, node)
+    }
+    fn visit_object_pattern_kv(&mut self, node: &'ast ObjectPatternKv) {
+        visit_object_pattern_kv(self, node)
+    }
+    fn visit_eclipse_pattern(&mut self, node: &'ast EclipsePattern) {
+        visit_eclipse_pattern(self, node)
+    }
+}
+
+pub fn visit_program<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Program) {
+    for stmt in &node.stmts {
+        v.visit_stmt(stmt);
+    }
+}
+
+pub fn visit_stmt<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Stmt) {
+    v.visit_expr(&node.expr);
+}
+
+/// an operator before its operands, `f` before its parameters
+pub fn visit_expr<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Expr) {
+    match &node.inner {
+        ExprInner::Prefix(prefix, expr) => {
+            v.visit_prefix(prefix);
+            v.visit_expr(expr);
+        }
+        ExprInner::Primary(primary) => v.visit_primary(primary),
+        ExprInner::Postfix(postfix, expr) => {
+            v.visit_expr(expr);
+            v.visit_postfix(postfix);
+        }
+        ExprInner::Infix(infix, lhs, rhs) => {
+            v.visit_expr(lhs);
+            v.visit_infix(infix);
+            v.visit_expr(rhs);
+        }
+        ExprInner::CApply(expr, params) | ExprInner::MLApply(expr, params) => {
+            v.visit_expr(expr);
+            for param in params {
+                v.visit_expr(param);
+            }
+        }
+    }
+}
+
+pub fn visit_prefix<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Prefix) {
+    if let Prefix::AnnotativeMacroCall(macro_id, arg) = node {
+        v.visit_macro_id(macro_id);
+        if let Some(arg) = arg {
+            v.visit_expr(arg);
+        }
+    }
+}
+
+pub fn visit_infix<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Infix) {
+    if let Infix::Function(id) = node {
+        v.visit_id(id);
+    }
+}
+
+pub fn visit_postfix<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Postfix) {
+    match node {
+        Postfix::Trinary(trinary) => v.visit_trinary(trinary),
+        Postfix::Slice(slice) => v.visit_slice(slice),
+        Postfix::Index(index) => v.visit_index(index),
+        Postfix::Access(access) => v.visit_access(access),
+        Postfix::Length => {}
+        Postfix::CAppParams(params) => v.visit_c_app_params(params),
+        Postfix::MlAppParam(param) => v.visit_ml_app_param(param),
+    }
+}
+
+pub fn visit_trinary<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Trinary) {
+    v.visit_expr(&node.true_expr);
+    v.visit_expr(&node.false_expr);
+}
+
+pub fn visit_slice<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Slice) {
+    for bound in [&node.start, &node.end, &node.step].into_iter().flatten() {
+        v.visit_expr(bound);
+    }
+}
+
+pub fn visit_index<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Index) {
+    v.visit_expr(&node.postfix_index);
+}
+
+pub fn visit_access<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Access) {
+    v.visit_id(&node.id);
+}
+
+pub fn visit_c_app_params<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast CAppParams) {
+    for param in node.0.iter().flat_map(|params| &params.0) {
+        v.visit_expr(param);
+    }
+}
+
+pub fn visit_ml_app_param<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast MlAppParam) {
+    v.visit_expr(&node.0);
+}
+
+pub fn visit_primary<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Primary) {
+    match &node.inner {
+        Inner::Error(error_node) => v.visit_error_node(error_node),
+        Inner::Block(block) => v.visit_block(block),
+        Inner::LambdaExpr(lambda_expr) => v.visit_lambda_expr(lambda_expr),
+        Inner::Hole(hole) => v.visit_hole(hole),
+        Inner::ParenExpr(paren_expr) => v.visit_paren_expr(paren_expr),
+        Inner::CompoundLiteral(compound_literal) => v.visit_compound_literal(compound_literal),
+        Inner::Id(id) => v.visit_id(id),
+        Inner::Pattern(pattern) => v.visit_pattern(pattern),
+    }
+}
+
+pub fn visit_block<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Block) {
+    for expr in &node.exprs {
+        v.visit_expr(expr);
+    }
+}
+
+pub fn visit_lambda_expr<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast LambdaExpr) {
+    match node {
+        LambdaExpr::TrLambda(tr_lambda) => v.visit_tr_lambda(tr_lambda),
+        LambdaExpr::NoParamLambdaExpr(lambda) => v.visit_no_param_lambda_expr(lambda),
+    }
+}
+
+pub fn visit_tr_lambda<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast TrLambda) {
+    for pattern in &node.patterns {
+        v.visit_pattern(pattern);
+    }
+    if let Some(implicit_params) = &node.implicit_params {
+        v.visit_implicit_params(implicit_params);
+    }
+    if let Some(guard) = &node.guard {
+        v.visit_guard(guard);
+    }
+    v.visit_expr(&node.body);
+}
+
+pub fn visit_no_param_lambda_expr<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast NoParamLambdaExpr,
+) {
+    for expr in &node.body {
+        v.visit_expr(expr);
+    }
+}
+
+pub fn visit_implicit_params<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ImplicitParams) {
+    for param in &node.params {
+        v.visit_id(param);
+    }
+}
+
+pub fn visit_guard<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Guard) {
+    v.visit_expr(&node.expr);
+}
+
+pub fn visit_hole<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Hole) {
+    v.visit_id(&node.id);
+}
+
+pub fn visit_paren_expr<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ParenExpr) {
+    v.visit_expr(&node.expr);
+}
+
+pub fn visit_compound_literal<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast CompoundLiteral,
+) {
+    match node {
+        CompoundLiteral::ArrayLiteral(Some(array_body)) => v.visit_array_body(array_body),
+        CompoundLiteral::ObjectLiteral(Some(object_body)) => v.visit_object_body(object_body),
+        CompoundLiteral::ArrayLiteral(None) | CompoundLiteral::ObjectLiteral(None) => {}
+        CompoundLiteral::Literal(literal) => v.visit_literal(literal),
+    }
+}
+
+pub fn visit_array_body<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ArrayBody) {
+    for elem in &node.elems {
+        v.visit_expr(elem);
+    }
+}
+
+pub fn visit_object_body<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ObjectBody) {
+    for elem in &node.body {
+        v.visit_object_elem_kv(elem);
+    }
+}
+
+pub fn visit_object_elem_kv<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ObjectElemKv) {
+    v.visit_object_key(&node.key);
+    if let Some(value) = &node.value {
+        v.visit_expr(value);
+    }
+}
+
+/// a string key is not visited, it is not a literal of the program
+pub fn visit_object_key<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ObjectKey) {
+    if let ObjectKey::Id(id) = node {
+        v.visit_id(id);
+    }
+}
+
+pub fn visit_pattern<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Pattern) {
+    match node {
+        Pattern::Id(id) => v.visit_id(id),
+        Pattern::Literal(literal) => v.visit_literal(literal),
+        Pattern::ArrayPattern(array_pattern) => v.visit_array_pattern(array_pattern),
+        Pattern::ObjectPattern(object_pattern) => v.visit_object_pattern(object_pattern),
+    }
+}
+
+pub fn visit_array_pattern<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ArrayPattern) {
+    for elem in node.elems() {
+        v.visit_array_pattern_elem(elem);
+    }
+}
+
+pub fn visit_array_pattern_elem<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ArrayPatternElem,
+) {
+    match node {
+        ArrayPatternElem::EclipsePattern(eclipse) => v.visit_eclipse_pattern(eclipse),
+        ArrayPatternElem::Pattern(pattern) => v.visit_pattern(pattern),
+    }
+}
+
+pub fn visit_object_pattern<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ObjectPattern) {
+    for elem in node.elems() {
+        v.visit_object_pattern_elem(elem);
+    }
+}
+
+pub fn visit_object_pattern_elem<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ObjectPatternElem,
+) {
+    match node {
+        ObjectPatternElem::ObjectPatternKv(kv) => v.visit_object_pattern_kv(kv),
+        ObjectPatternElem::EclipsePattern(eclipse) => v.visit_eclipse_pattern(eclipse),
+    }
+}
+
+pub fn visit_object_pattern_kv<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ObjectPatternKv,
+) {
+    v.visit_object_key(&node.key);
+    if let Some(value) = &node.value {
+        v.visit_pattern(value);
+    }
+}
+
+pub fn visit_eclipse_pattern<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast EclipsePattern) {
+    v.visit_id(&node.value);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        expr::{Expr, ExprInner, Inner, Primary},
+        id::{Id, MacroId, NormalId},
+        literal::{self, CompoundLiteral, Literal},
+        parse_expr, parse_file,
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Ids(Vec<String>);
+
+    impl Visit<'_> for Ids {
+        fn visit_id(&mut self, id: &Id) {
+            self.0.push(id.value());
+        }
+        fn visit_macro_id(&mut self, macro_id: &MacroId) {
+            self.0.push(macro_id.value.clone());
+        }
+    }
+
+    fn ids(source: &str) -> Vec<String> {
+        let mut ids = Ids::default();
+        ids.visit_program(&parse_file(source).unwrap());
+        ids.0
+    }
+
+    #[test]
+    fn test_visit_order() {
+        assert_eq!(
+            ids("@@import(\"./a\") a\nf = \\x ?i : x > y -> g(x, i).b[z]"),
+            [
+                "@import", "a", "f", "x", "i", "x", "y", "g", "x", "i", "b", "z"
+            ]
+        );
+        assert_eq!(
+            ids("o = {a: p, \"s\": q}\n\\^[x, ...xs] ^{k: y, ...r} -> c ? d[e:] : w"),
+            [
+                "o", "a", "p", "q", "x", "xs", "k", "y", "r", "c", "d", "e", "w"
+            ]
+        );
+    }
+
+    struct Rename;
+
+    impl VisitMut for Rename {
+        fn visit_id_mut(&mut self, id: &mut Id) {
+            *id = Id::NormalId(NormalId {
+                value: format!("{}_", id.value()),
+            });
+        }
+    }
+
+    #[test]
+    fn test_visit_mut() {
+        let mut expr = parse_expr("\\x ?i : x -> f x").unwrap();
+        Rename.visit_expr_mut(&mut expr);
+        let mut ids = Ids::default();
+        ids.visit_expr(&expr);
+        assert_eq!(ids.0, ["x_", "i_", "x_", "f_", "x_"]);
+    }
+
+    /// every number literal becomes `n`
+    struct Numbers<'a>(&'a str);
+
+    impl Fold for Numbers<'_> {
+        fn fold_expr(&mut self, expr: Expr) -> Expr {
+            match &expr.inner {
+                ExprInner::Primary(Primary {
+                    inner:
+                        Inner::CompoundLiteral(CompoundLiteral::Literal(Literal {
+                            inner: literal::Inner::Number(_),
+                            ..
+                        })),
+                    ..
+                }) => Expr {
+                    doc: expr.doc.clone(),
+                    ..parse_expr(self.0).unwrap()
+                },
+                _ => fold_expr(self, expr),
+            }
+        }
+    }
+
+    #[test]
+    fn test_fold() {
+        let expr = Numbers("n").fold_expr(parse_expr("[1, f(2), @m(3) x]").unwrap());
+        let mut ids = Ids::default();
+        ids.visit_expr(&expr);
+        assert_eq!(ids.0, ["n", "f", "n", "@m", "n", "x"]);
+    }
+}
@@ -0,0 +1,402 @@
+//! `Visit` over `&mut` nodes, for passes rewriting the tree in place
+//!
+//! unlike `Visit`, the walk functions also hand every span of the tree to
+//! `visit_diagnostic_mut`, see `diagnostics::EraseSpans`
+
+use crate::{
+    diagnostics::Diagnostic,
+    expr::{
+        Expr, ExprInner, Inner, Primary,
+        infix::Infix,
+        postfix::{Access, CAppParams, Index, MlAppParam, Postfix, Slice, Trinary},
+        prefix::Prefix,
+    },
+    function::{Guard, ImplicitParams, LambdaExpr, NoParamLambdaExpr, TrLambda},
+    id::{Hole, Id, MacroId},
+    literal::{
+        CompoundLiteral, Literal,
+        array::ArrayBody,
+        object::{ObjectBody, ObjectElemKv, ObjectKey},
+    },
+    pattern::{
+        EclipsePattern, Pattern,
+        array::{ArrayPattern, ArrayPatternElem},
+        object::{ObjectPattern, ObjectPatternElem, ObjectPatternKv},
+    },
+    program::{Program, Stmt},
+    quotations::{Block, ParenExpr},
+    recovery::ErrorNode,
+};
+
+pub trait VisitMut {
+    fn visit_program_mut(&mut self, node: &mut Program) {
+        visit_program_mut(self, node)
+    }
+    fn visit_stmt_mut(&mut self, node: &mut Stmt) {
+        visit_stmt_mut(self, node)
+    }
+    fn visit_expr_mut(&mut self, node: &mut Expr) {
+        visit_expr_mut(self, node)
+    }
+    fn visit_prefix_mut(&mut self, node: &mut Prefix) {
+        visit_prefix_mut(self, node)
+    }
+    fn visit_infix_mut(&mut self, node: &mut Infix) {
+        visit_infix_mut(self, node)
+    }
+    fn visit_postfix_mut(&mut self, node: &mut Postfix) {
+        visit_postfix_mut(self, node)
+    }
+    fn visit_trinary_mut(&mut self, node: &mut Trinary) {
+        visit_trinary_mut(self, node)
+    }
+    fn visit_slice_mut(&mut self, node: &mut Slice) {
+        visit_slice_mut(self, node)
+    }
+    fn visit_index_mut(&mut self, node: &mut Index) {
+        visit_index_mut(self, node)
+    }
+    fn visit_access_mut(&mut self, node: &mut Access) {
+        visit_access_mut(self, node)
+    }
+    fn visit_c_app_params_mut(&mut self, node: &mut CAppParams) {
+        visit_c_app_params_mut(self, node)
+    }
+    fn visit_ml_app_param_mut(&mut self, node: &mut MlAppParam) {
+        visit_ml_app_param_mut(self, node)
+    }
+    fn visit_primary_mut(&mut self, node: &mut Primary) {
+        visit_primary_mut(self, node)
+    }
+    fn visit_error_node_mut(&mut self, node: &mut ErrorNode) {
+        visit_error_node_mut(self, node)
+    }
+    fn visit_block_mut(&mut self, node: &mut Block) {
+        visit_block_mut(self, node)
+    }
+    fn visit_lambda_expr_mut(&mut self, node: &mut LambdaExpr) {
+        visit_lambda_expr_mut(self, node)
+    }
+    fn visit_tr_lambda_mut(&mut self, node: &mut TrLambda) {
+        visit_tr_lambda_mut(self, node)
+    }
+    fn visit_no_param_lambda_expr_mut(&mut self, node: &mut NoParamLambdaExpr) {
+        visit_no_param_lambda_expr_mut(self, node)
+    }
+    fn visit_implicit_params_mut(&mut self, node: &mut ImplicitParams) {
+        visit_implicit_params_mut(self, node)
+    }
+    fn visit_guard_mut(&mut self, node: &mut Guard) {
+        visit_guard_mut(self, node)
+    }
+    fn visit_hole_mut(&mut self, node: &mut Hole) {
+        visit_hole_mut(self, node)
+    }
+    fn visit_paren_expr_mut(&mut self, node: &mut ParenExpr) {
+        visit_paren_expr_mut(self, node)
+    }
+    fn visit_compound_literal_mut(&mut self, node: &mut CompoundLiteral) {
+        visit_compound_literal_mut(self, node)
+    }
+    fn visit_array_body_mut(&mut self, node: &mut ArrayBody) {
+        visit_array_body_mut(self, node)
+    }
+    fn visit_object_body_mut(&mut self, node: &mut ObjectBody) {
+        visit_object_body_mut(self, node)
+    }
+    fn visit_object_elem_kv_mut(&mut self, node: &mut ObjectElemKv) {
+        visit_object_elem_kv_mut(self, node)
+    }
+    fn visit_object_key_mut(&mut self, node: &mut ObjectKey) {
+        visit_object_key_mut(self, node)
+    }
+    fn visit_literal_mut(&mut self, node: &mut Literal) {
+        visit_literal_mut(self, node)
+    }
+    fn visit_id_mut(&mut self, _node: &mut Id) {}
+    fn visit_macro_id_mut(&mut self, _node: &mut MacroId) {}
+    fn visit_pattern_mut(&mut self, node: &mut Pattern) {
+        visit_pattern_mut(self, node)
+    }
+    fn visit_array_pattern_mut(&mut self, node: &mut ArrayPattern) {
+        visit_array_pattern_mut(self, node)
+    }
+    fn visit_array_pattern_elem_mut(&mut self, node: &mut ArrayPatternElem) {
+        visit_array_pattern_elem_mut(self, node)
+    }
+    fn visit_object_pattern_mut(&mut self, node: &mut ObjectPattern) {
+        visit_object_pattern_mut(self, node)
+    }
+    fn visit_object_pattern_elem_mut(&mut self, node: &mut ObjectPatternElem) {
+        visit_object_pattern_elem_mut(self, node)
+    }
+    fn visit_object_pattern_kv_mut(&mut self, node: &mut ObjectPatternKv) {
+        visit_object_pattern_kv_mut(self, node)
+    }
+    fn visit_eclipse_pattern_mut(&mut self, node: &mut EclipsePattern) {
+        visit_eclipse_pattern_mut(self, node)
+    }
+    fn visit_diagnostic_mut(&mut self, _node: &mut Diagnostic) {}
+}
+
+pub fn visit_program_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Program) {
+    v.visit_diagnostic_mut(&mut node.diag);
+    for stmt in &mut node.stmts {
+        v.visit_stmt_mut(stmt);
+    }
+}
+
+pub fn visit_stmt_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Stmt) {
+    v.visit_diagnostic_mut(&mut node.diag);
+    v.visit_expr_mut(&mut node.expr);
+}
+
+pub fn visit_expr_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Expr) {
+    v.visit_diagnostic_mut(&mut node.diag);
+    match &mut node.inner {
+        ExprInner::Prefix(prefix, expr) => {
+            v.visit_prefix_mut(prefix);
+            v.visit_expr_mut(expr);
+        }
+        ExprInner::Primary(primary) => v.visit_primary_mut(primary),
+        ExprInner::Postfix(postfix, expr) => {
+            v.visit_expr_mut(expr);
+            v.visit_postfix_mut(postfix);
+        }
+        ExprInner::Infix(infix, lhs, rhs) => {
+            v.visit_expr_mut(lhs);
+            v.visit_infix_mut(infix);
+            v.visit_expr_mut(rhs);
+        }
+        ExprInner::CApply(expr, params) | ExprInner::MLApply(expr, params) => {
+            v.visit_expr_mut(expr);
+            for param in params {
+                v.visit_expr_mut(param);
+            }
+        }
+    }
+}
+
+pub fn visit_prefix_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Prefix) {
+    if let Prefix::AnnotativeMacroCall(macro_id, arg) = node {
+        v.visit_macro_id_mut(macro_id);
+        if let Some(arg) = arg {
+            v.visit_expr_mut(arg);
+        }
+    }
+}
+
+pub fn visit_infix_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Infix) {
+    if let Infix::Function(id) = node {
+        v.visit_id_mut(id);
+    }
+}
+
+pub fn visit_postfix_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Postfix) {
+    match node {
+        Postfix::Trinary(trinary) => v.visit_trinary_mut(trinary),
+        Postfix::Slice(slice) => v.visit_slice_mut(slice),
+        Postfix::Index(index) => v.visit_index_mut(index),
+        Postfix::Access(access) => v.visit_access_mut(access),
+        Postfix::Length => {}
+        Postfix::CAppParams(params) => v.visit_c_app_params_mut(params),
+        Postfix::MlAppParam(param) => v.visit_ml_app_param_mut(param),
+    }
+}
+
+pub fn visit_trinary_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Trinary) {
+    v.visit_expr_mut(&mut node.true_expr);
+    v.visit_expr_mut(&mut node.false_expr);
+}
+
+pub fn visit_slice_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Slice) {
+    for bound in [&mut node.start, &mut node.end, &mut node.step]
+        .into_iter()
+        .flatten()
+    {
+        v.visit_expr_mut(bound);
+    }
+}
+
+pub fn visit_index_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Index) {
+    v.visit_expr_mut(&mut node.postfix_index);
+}
+
+pub fn visit_access_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Access) {
+    v.visit_id_mut(&mut node.id);
+}
+
+pub fn visit_c_app_params_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut CAppParams) {
+    for param in node.0.iter_mut().flat_map(|params| &mut params.0) {
+        v.visit_expr_mut(param);
+    }
+}
+
+pub fn visit_ml_app_param_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut MlAppParam) {
+    v.visit_expr_mut(&mut node.0);
+}
+
+pub fn visit_primary_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Primary) {
+    v.visit_diagnostic_mut(&mut node.diag);
+    match &mut node.inner {
+        Inner::Error(error_node) => v.visit_error_node_mut(error_node),
+        Inner::Block(block) => v.visit_block_mut(block),
+        Inner::LambdaExpr(lambda_expr) => v.visit_lambda_expr_mut(lambda_expr),
+        Inner::Hole(hole) => v.visit_hole_mut(hole),
+        Inner::ParenExpr(paren_expr) => v.visit_paren_expr_mut(paren_expr),
+        Inner::CompoundLiteral(compound_literal) => v.visit_compound_literal_mut(compound_literal),
+        Inner::Id(id) => v.visit_id_mut(id),
+        Inner::Pattern(pattern) => v.visit_pattern_mut(pattern),
+    }
+}
+
+pub fn visit_error_node_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ErrorNode) {
+    v.visit_diagnostic_mut(&mut node.diag);
+}
+
+pub fn visit_block_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Block) {
+    for expr in &mut node.exprs {
+        v.visit_expr_mut(expr);
+    }
+}
+
+pub fn visit_lambda_expr_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut LambdaExpr) {
+    match node {
+        LambdaExpr::TrLambda(tr_lambda) => v.visit_tr_lambda_mut(tr_lambda),
+        LambdaExpr::NoParamLambdaExpr(lambda) => v.visit_no_param_lambda_expr_mut(lambda),
+    }
+}
+
+pub fn visit_tr_lambda_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut TrLambda) {
+    for pattern in &mut node.patterns {
+        v.visit_pattern_mut(pattern);
+    }
+    if let Some(implicit_params) = &mut node.implicit_params {
+        v.visit_implicit_params_mut(implicit_params);
+    }
+    if let Some(guard) = &mut node.guard {
+        v.visit_guard_mut(guard);
+    }
+    v.visit_expr_mut(&mut node.body);
+}
+
+pub fn visit_no_param_lambda_expr_mut<V: VisitMut + ?Sized>(
+    v: &mut V,
+    node: &mut NoParamLambdaExpr,
+) {
+    for expr in &mut node.body {
+        v.visit_expr_mut(expr);
+    }
+}
+
+pub fn visit_implicit_params_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ImplicitParams) {
+    for param in &mut node.params {
+        v.visit_id_mut(param);
+    }
+}
+
+pub fn visit_guard_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Guard) {
+    v.visit_expr_mut(&mut node.expr);
+}
+
+pub fn visit_hole_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Hole) {
+    v.visit_id_mut(&mut node.id);
+}
+
+pub fn visit_paren_expr_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ParenExpr) {
+    v.visit_expr_mut(&mut node.expr);
+}
+
+pub fn visit_compound_literal_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut CompoundLiteral) {
+    match node {
+        CompoundLiteral::ArrayLiteral(Some(array_body)) => v.visit_array_body_mut(array_body),
+        CompoundLiteral::ObjectLiteral(Some(object_body)) => v.visit_object_body_mut(object_body),
+        CompoundLiteral::ArrayLiteral(None) | CompoundLiteral::ObjectLiteral(None) => {}
+        CompoundLiteral::Literal(literal) => v.visit_literal_mut(literal),
+    }
+}
+
+pub fn visit_literal_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Literal) {
+    v.visit_diagnostic_mut(&mut node.diag);
+}
+
+pub fn visit_array_body_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ArrayBody) {
+    v.visit_diagnostic_mut(&mut node.diag);
+    for elem in &mut node.elems {
+        v.visit_expr_mut(elem);
+    }
+}
+
+pub fn visit_object_body_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ObjectBody) {
+    v.visit_diagnostic_mut(&mut node.diag);
+    for elem in &mut node.body {
+        v.visit_object_elem_kv_mut(elem);
+    }
+}
+
+pub fn visit_object_elem_kv_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ObjectElemKv) {
+    v.visit_diagnostic_mut(&mut node.diag);
+    v.visit_object_key_mut(&mut node.key);
+    if let Some(value) = &mut node.value {
+        v.visit_expr_mut(value);
+    }
+}
+
+pub fn visit_object_key_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ObjectKey) {
+    if let ObjectKey::Id(id) = node {
+        v.visit_id_mut(id);
+    }
+}
+
+pub fn visit_pattern_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Pattern) {
+    match node {
+        Pattern::Id(id) => v.visit_id_mut(id),
+        Pattern::Literal(literal) => v.visit_literal_mut(literal),
+        Pattern::ArrayPattern(array_pattern) => v.visit_array_pattern_mut(array_pattern),
+        Pattern::ObjectPattern(object_pattern) => v.visit_object_pattern_mut(object_pattern),
+    }
+}
+
+pub fn visit_array_pattern_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ArrayPattern) {
+    v.visit_diagnostic_mut(&mut node.diag);
+    for elem in node.body.iter_mut().flat_map(|body| &mut body.elems) {
+        v.visit_array_pattern_elem_mut(elem);
+    }
+}
+
+pub fn visit_array_pattern_elem_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ArrayPatternElem) {
+    match node {
+        ArrayPatternElem::EclipsePattern(eclipse) => v.visit_eclipse_pattern_mut(eclipse),
+        ArrayPatternElem::Pattern(pattern) => v.visit_pattern_mut(pattern),
+    }
+}
+
+pub fn visit_object_pattern_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ObjectPattern) {
+    v.visit_diagnostic_mut(&mut node.diag);
+    for elem in node.body.iter_mut().flat_map(|body| &mut body.body) {
+        v.visit_object_pattern_elem_mut(elem);
+    }
+}
+
+pub fn visit_object_pattern_elem_mut<V: VisitMut + ?Sized>(
+    v: &mut V,
+    node: &mut ObjectPatternElem,
+) {
+    match node {
+        ObjectPatternElem::ObjectPatternKv(kv) => v.visit_object_pattern_kv_mut(kv),
+        ObjectPatternElem::EclipsePattern(eclipse) => v.visit_eclipse_pattern_mut(eclipse),
+    }
+}
+
+pub fn visit_object_pattern_kv_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ObjectPatternKv) {
+    v.visit_diagnostic_mut(&mut node.diag);
+    v.visit_object_key_mut(&mut node.key);
+    if let Some(value) = &mut node.value {
+        v.visit_pattern_mut(value);
+    }
+}
+
+pub fn visit_eclipse_pattern_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut EclipsePattern) {
+    v.visit_diagnostic_mut(&mut node.diag);
+    v.visit_id_mut(&mut node.value);
+}